@@ -0,0 +1,77 @@
+#version 450
+
+layout(location = 0) out vec4 o_Target;
+
+layout(binding = 0) uniform sampler2D normaltexture;
+layout(binding = 1) uniform sampler2D depthtexture;
+
+uniform mat4 inverse_projection_matrix;
+uniform vec3 camera_position;
+uniform vec3 edge_color;
+uniform float edge_width;
+// Cosine of the smallest angle between neighbouring normals that counts as a crease.
+uniform float crease_cosine;
+// Distance from the plane of a surface beyond which a neighbouring point lies on another surface.
+uniform float depth_threshold;
+uniform float depth_scaling;
+// Distances from the camera of the closest and furthest points of the model, as in distance
+// shading.
+uniform float near_plane;
+uniform float far_plane;
+
+layout(location = 0) in vec2 uv;
+
+// Position in the scene of the surface drawn at the given texture coordinates.
+vec3 surfacePosition(vec2 coordinates) {
+    float depth = texture(depthtexture, coordinates).r;
+    vec4 position = inverse_projection_matrix * vec4(vec3(coordinates, depth) * 2.0 - 1.0, 1.0);
+    return position.xyz / position.w;
+}
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(depthtexture, 0));
+    vec4 normal = texture(normaltexture, uv);
+    bool background = normal.a == 0.0;
+    vec3 position = surfacePosition(uv);
+    float depth = length(camera_position - position);
+
+    // Look for surfaces within the line width that this pixel is the outline of. Lines are drawn
+    // on the far side of an edge, so they outline the surface in front, and only on one side of a
+    // crease.
+    float alpha = 0.0;
+    int reach = int(ceil(edge_width));
+    for (int x = -reach; x <= reach; x++) {
+        for (int y = -reach; y <= reach; y++) {
+            float offset = length(vec2(x, y));
+            if (offset == 0.0 || offset > edge_width + 0.5) {
+                continue;
+            }
+            vec2 neighbour_uv = uv + vec2(x, y) * texel;
+            vec4 neighbour_normal = texture(normaltexture, neighbour_uv);
+            if (neighbour_normal.a == 0.0) {
+                continue;
+            }
+            vec3 neighbour_position = surfacePosition(neighbour_uv);
+            float neighbour_depth = length(camera_position - neighbour_position);
+
+            bool edge = background;
+            if (!background && neighbour_depth <= depth) {
+                bool separate = abs(dot(neighbour_position - position, normal.xyz)) > depth_threshold;
+                bool crease = dot(normal.xyz, neighbour_normal.xyz) < crease_cosine;
+                edge = separate || crease;
+            }
+            if (!edge) {
+                continue;
+            }
+
+            // Lines get thinner and lighter with distance, like distance shading.
+            float d = 1.0 - smoothstep(near_plane, far_plane, neighbour_depth) * depth_scaling;
+            float coverage = clamp(edge_width * d + 0.5 - offset, 0.0, 1.0);
+            alpha = max(alpha, coverage * d);
+        }
+    }
+    if (alpha == 0.0) {
+        discard;
+    }
+    o_Target = vec4(edge_color, alpha);
+}
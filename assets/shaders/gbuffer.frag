@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) out vec4 o_Normal;
+
+layout(location = 0) in vec3 normal_vector;
+
+void main() {
+    // The inside of the model shows where clipping planes cut it open.
+    vec3 normal = normalize(normal_vector) * (gl_FrontFacing ? 1.0 : -1.0);
+    o_Normal = vec4(normal, 1.0);
+}
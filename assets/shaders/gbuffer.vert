@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec3 vertex_position;
+layout(location = 1) in vec3 vertex_normal;
+
+uniform mat4 projection_matrix;
+// Placement of the model in the scene.
+uniform mat4 model_matrix;
+
+// Planes as vectors whose dot product with a position is its distance from the plane, positive on
+// the side that is kept.
+#define MAX_CLIP_PLANES 4
+uniform vec4 clip_planes[MAX_CLIP_PLANES];
+uniform uint clip_plane_count;
+out float gl_ClipDistance[MAX_CLIP_PLANES];
+
+layout(location = 0) out vec3 normal_vector;
+
+void main() {
+    vec4 position = model_matrix * vec4(vertex_position, 1.0);
+    normal_vector = normalize(mat3(model_matrix) * vertex_normal);
+
+    gl_Position = projection_matrix * position;
+
+    for (int i = 0; i < MAX_CLIP_PLANES; i++) {
+        gl_ClipDistance[i] = uint(i) < clip_plane_count ? dot(clip_planes[i], position) : 1.0;
+    }
+}
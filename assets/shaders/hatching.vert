@@ -5,11 +5,25 @@ layout(location = 1) in vec3 vertex_normal;
 layout(location = 2) in vec3 vertex_color;
 
 uniform mat4 projection_matrix;
+// Placement of the model in the scene.
+uniform mat4 model_matrix;
 uniform float hatching_depth;
 
+// Planes as vectors whose dot product with a position is its distance from the plane, positive on
+// the side that is kept.
+#define MAX_CLIP_PLANES 4
+uniform vec4 clip_planes[MAX_CLIP_PLANES];
+uniform uint clip_plane_count;
+out float gl_ClipDistance[MAX_CLIP_PLANES];
+
 void main() {
-    vec3 normal = normalize(vertex_normal);
-    vec3 position = vertex_position + normal * hatching_depth;
+    vec4 surface = model_matrix * vec4(vertex_position, 1.0);
+    vec3 normal = normalize(mat3(model_matrix) * vertex_normal);
+    vec3 position = surface.xyz + normal * hatching_depth;
     
     gl_Position = projection_matrix * vec4(position, 1.0);
+
+    for (int i = 0; i < MAX_CLIP_PLANES; i++) {
+        gl_ClipDistance[i] = uint(i) < clip_plane_count ? dot(clip_planes[i], surface) : 1.0;
+    }
 }
@@ -4,16 +4,22 @@
 #define DSC_HUE 1
 #define DSC_SATURATION 2
 #define DSC_VALUE 3
+#define CS_VERTEX 0
+#define CS_MATERIAL 1
+#define CS_FLAT 2
 #define M_PI 3.141592653589793;
 
 layout(location = 0) out vec4 o_Target;
 
 layout(binding = 0) uniform sampler2DShadow shadowtexture;
 layout(binding = 1) uniform sampler2DShadow hatchingtexture;
+layout(binding = 2) uniform sampler2D occlusiontexture;
 
 uniform vec3 camera_position;
 uniform vec3 light_vector;
 uniform vec3 color;
+uniform vec3 object_color;
+uniform uint color_source;
 uniform vec3 distance_shading_closest;
 uniform vec3 distance_shading_furthest;
 uniform float model_size;
@@ -27,6 +33,11 @@ uniform uint hatching_frequency;
 uniform float hatching_intensity;
 uniform float hatching_far_plane;
 uniform bool replace_shadows_with_hatching;
+uniform bool cap_enabled;
+uniform vec3 cap_color;
+uniform float ambient_occlusion_intensity;
+// Lower left corner of the viewport, which the occlusion texture covers.
+uniform vec2 viewport_origin;
 
 layout(location = 0) in vec3 normal_vector;
 layout(location = 1) in vec3 toon_light_vector;
@@ -34,6 +45,7 @@ layout(location = 2) in vec3 position_vector;
 layout(location = 3) in vec3 vertex_color;
 layout(location = 4) in vec4 uv;
 layout(location = 5) in vec4 hatchpos;
+layout(location = 6) in vec3 material_color;
 
 // https://stackoverflow.com/a/17897228
 // All components are in the range [0…1], including hue.
@@ -130,12 +142,28 @@ float hatchingCalculation()
 }
 
 void main() {
-    vec3 color = mix(color, vertex_color, vertex_color_mix);
+    // Back faces are only drawn where clipping planes cut the model open, and are either filled
+    // with the cap colour or shaded as the inside of the model.
+    if (!gl_FrontFacing && cap_enabled) {
+        o_Target = vec4(cap_color, 1);
+        return;
+    }
+    float facing = gl_FrontFacing ? 1.0 : -1.0;
+    vec3 normal = normal_vector * facing;
+
+    vec3 surface_color = vertex_color;
+    if (color_source == CS_MATERIAL) {
+        surface_color = material_color;
+    }
+    else if (color_source == CS_FLAT) {
+        surface_color = color;
+    }
+    vec3 color = mix(color, surface_color, vertex_color_mix);
 
     vec3 toonShadingColor;
     {
         vec3 cl = color;    
-        vec3 light = -normalize(toon_light_vector.xyz);    
+        vec3 light = -normalize(toon_light_vector.xyz) * facing;    
         float vdn = light.z;
         cl *= round(vdn * 5) / 5;
         cl *= vdn;
@@ -167,7 +195,7 @@ void main() {
         vec3 lm = normalize(light_position - position_vector);
 
         // Reflected light vector
-        vec3 np = 2 * normalize(dot(lm, normal_vector) * normal_vector);
+        vec3 np = 2 * normalize(dot(lm, normal) * normal);
         vec3 rm = normalize(np - lm);
 
 
@@ -176,9 +204,9 @@ void main() {
         float roughness = 0.3;
         float A = 1 - 0.5 * pow(roughness, 2) / (pow(roughness, 2) + 0.33);
         float B = 0.45 * pow(roughness, 2) / (pow(roughness, 2) + 0.09);
-        float NdotL = clamp(dot(normal_vector, lm), 0.0, 1.0);
+        float NdotL = clamp(dot(normal, lm), 0.0, 1.0);
         float angleLN = acos(NdotL);
-        float NdotV = clamp(dot(normal_vector, v), 0.0, 1.0);
+        float NdotV = clamp(dot(normal, v), 0.0, 1.0);
         float angleVN = acos(NdotV);
         float alpha = max(angleVN, angleLN);
         float beta = min(angleVN, angleLN);        
@@ -186,11 +214,11 @@ void main() {
         diffuseIntensity = orenNayar;
         
         // Light intensity
-        float ip = ambientReflection * ambientIntensity + diffuseReflection * diffuseIntensity * dot(lm, normal_vector) + specularReflection * specularIntensity * pow(max(0, min(1, dot(rm, v))), shininess);
+        float ip = ambientReflection * ambientIntensity + diffuseReflection * diffuseIntensity * dot(lm, normal) + specularReflection * specularIntensity * pow(max(0, min(1, dot(rm, v))), shininess);
 
-        standardShadingColor = ip * vertex_color;
+        standardShadingColor = ip * surface_color;
     }
-    color = mix(standardShadingColor, toonShadingColor, toon_factor);
+    color = mix(standardShadingColor, toonShadingColor, toon_factor) * object_color;
 
     // Reduce Value of colour based on distance from camera.    
     // float camera_dist = length(camera_position);
@@ -228,6 +256,13 @@ void main() {
         color.z *= d;
     }
 
+    // Ambient occlusion darkens the value like shadows, so it combines with any distance shading
+    // channel.
+    if (ambient_occlusion_intensity > 0.0) {
+        float occlusion = texelFetch(occlusiontexture, ivec2(gl_FragCoord.xy - viewport_origin), 0).r;
+        color.z *= mix(1.0, occlusion, ambient_occlusion_intensity);
+    }
+
     // Shadows
     if (!replace_shadows_with_hatching) {
         float shadow = shadow_intensity < 0.005 ? 1.0 : ShadowCalculation(uv);
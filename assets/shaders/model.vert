@@ -3,25 +3,42 @@
 layout(location = 0) in vec3 vertex_position;
 layout(location = 1) in vec3 vertex_normal;
 layout(location = 2) in vec3 vertex_color;
+layout(location = 3) in vec3 vertex_material_color;
 
 uniform mat4 projection_matrix;
+// Placement of the model in the scene.
+uniform mat4 model_matrix;
 uniform mat4 light_space_matrix;
 uniform mat4 hatch_space_matrix;
 
+// Planes as vectors whose dot product with a position is its distance from the plane, positive on
+// the side that is kept.
+#define MAX_CLIP_PLANES 4
+uniform vec4 clip_planes[MAX_CLIP_PLANES];
+uniform uint clip_plane_count;
+out float gl_ClipDistance[MAX_CLIP_PLANES];
+
 layout(location = 0) out vec3 normal_vector;
 layout(location = 1) out vec3 toon_light_vector;
 layout(location = 2) out vec3 position_vector;
 layout(location = 3) out vec3 out_vertex_color;
 layout(location = 4) out vec4 uv;
 layout(location = 5) out vec4 hatchpos;
+layout(location = 6) out vec3 out_material_color;
 
 void main() {
-    normal_vector = normalize(vertex_normal);
+    vec4 position = model_matrix * vec4(vertex_position, 1.0);
+    normal_vector = normalize(mat3(model_matrix) * vertex_normal);
     toon_light_vector = vec3(projection_matrix * vec4(normal_vector, 0.0));
-    position_vector = vertex_position;
+    position_vector = position.xyz;
     out_vertex_color = vertex_color;
-    uv = light_space_matrix * vec4(vertex_position, 1.0);
-    hatchpos = hatch_space_matrix * vec4(vertex_position, 1.0);
+    out_material_color = vertex_material_color;
+    uv = light_space_matrix * position;
+    hatchpos = hatch_space_matrix * position;
     
-    gl_Position = projection_matrix * vec4(vertex_position, 1.0);
+    gl_Position = projection_matrix * position;
+
+    for (int i = 0; i < MAX_CLIP_PLANES; i++) {
+        gl_ClipDistance[i] = uint(i) < clip_plane_count ? dot(clip_planes[i], position) : 1.0;
+    }
 }
@@ -0,0 +1,9 @@
+#version 330 core
+
+layout (location = 0) in vec3 vertex_position;
+
+uniform mat4 projection_matrix;
+
+void main() {
+  gl_Position = projection_matrix * vec4(vertex_position, 1.0);
+}
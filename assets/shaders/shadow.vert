@@ -2,9 +2,22 @@
 layout (location = 0) in vec3 aPos;
 
 uniform mat4 lightSpaceMatrix;
-uniform mat4 model;
+// Placement of the model in the scene.
+uniform mat4 model_matrix;
+
+// Planes as vectors whose dot product with a position is its distance from the plane, positive on
+// the side that is kept.
+#define MAX_CLIP_PLANES 4
+uniform vec4 clip_planes[MAX_CLIP_PLANES];
+uniform uint clip_plane_count;
+out float gl_ClipDistance[MAX_CLIP_PLANES];
 
 void main()
 {
-    gl_Position = lightSpaceMatrix * vec4(aPos, 1.0);
+    vec4 position = model_matrix * vec4(aPos, 1.0);
+    gl_Position = lightSpaceMatrix * position;
+
+    for (int i = 0; i < MAX_CLIP_PLANES; i++) {
+        gl_ClipDistance[i] = uint(i) < clip_plane_count ? dot(clip_planes[i], position) : 1.0;
+    }
 }
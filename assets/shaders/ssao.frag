@@ -0,0 +1,60 @@
+#version 450
+
+#define MAX_SAMPLES 64
+
+layout(location = 0) out float o_Occlusion;
+
+layout(binding = 0) uniform sampler2D normaltexture;
+layout(binding = 1) uniform sampler2D depthtexture;
+layout(binding = 2) uniform sampler2D noisetexture;
+
+uniform mat4 projection_matrix;
+uniform mat4 inverse_projection_matrix;
+uniform vec3 camera_position;
+uniform float radius;
+uniform vec3 samples[MAX_SAMPLES];
+uniform uint sample_count;
+
+layout(location = 0) in vec2 uv;
+
+// Position in the scene of the surface drawn at the given texture coordinates.
+vec3 surfacePosition(vec2 coordinates) {
+    float depth = texture(depthtexture, coordinates).r;
+    vec4 position = inverse_projection_matrix * vec4(vec3(coordinates, depth) * 2.0 - 1.0, 1.0);
+    return position.xyz / position.w;
+}
+
+void main() {
+    vec4 normal = texture(normaltexture, uv);
+    // Nothing was drawn here.
+    if (normal.a == 0.0) {
+        o_Occlusion = 1.0;
+        return;
+    }
+    vec3 position = surfacePosition(uv);
+    vec3 n = normalize(normal.xyz);
+
+    // Rotate the samples around the normal by a small tile of random rotations, which trades the
+    // banding of few samples for noise that the blur removes.
+    vec2 noise_scale = vec2(textureSize(depthtexture, 0)) / vec2(textureSize(noisetexture, 0));
+    vec3 random = texture(noisetexture, uv * noise_scale).xyz * 2.0 - 1.0;
+    vec3 tangent = normalize(random - n * dot(random, n));
+    mat3 tbn = mat3(tangent, cross(n, tangent), n);
+
+    float depth = length(camera_position - position);
+    float bias = radius * 0.025;
+    float occlusion = 0.0;
+    for (uint i = 0; i < sample_count; i++) {
+        vec3 sample_position = position + tbn * samples[i] * radius;
+        vec4 projected = projection_matrix * vec4(sample_position, 1.0);
+        vec2 sample_uv = projected.xy / projected.w * 0.5 + 0.5;
+        float surface_depth = length(camera_position - surfacePosition(sample_uv));
+        float sample_depth = length(camera_position - sample_position);
+
+        // Surfaces far in front of the point, such as another vessel passing in front of it,
+        // occlude it less the further away they are.
+        float range = smoothstep(0.0, 1.0, radius / abs(depth - surface_depth));
+        occlusion += (surface_depth < sample_depth - bias ? 1.0 : 0.0) * range;
+    }
+    o_Occlusion = 1.0 - occlusion / float(sample_count);
+}
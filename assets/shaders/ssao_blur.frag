@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) out float o_Occlusion;
+
+layout(binding = 0) uniform sampler2D occlusiontexture;
+
+layout(location = 0) in vec2 uv;
+
+// Averages over the size of the tile of random rotations, which removes its pattern.
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(occlusiontexture, 0));
+    float occlusion = 0.0;
+    for (int x = -2; x < 2; x++) {
+        for (int y = -2; y < 2; y++) {
+            occlusion += texture(occlusiontexture, uv + vec2(x, y) * texel).r;
+        }
+    }
+    o_Occlusion = occlusion / 16.0;
+}
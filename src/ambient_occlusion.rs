@@ -0,0 +1,268 @@
+//! Contains [AmbientOcclusion], the screen-space ambient occlusion pass, which darkens creases,
+//! branchings and places where vessels lie close to each other.
+//!
+//! For every pixel of the [GBuffer], points in the hemisphere around its normal are checked against
+//! its depth, and the share of them hidden behind the surface is blurred into the occlusion
+//! texture sampled by the main shader.
+
+use crate::{
+    gbuffer::GBuffer,
+    render_gl::{
+        self,
+        buffer::{self, FrameBuffer, Texture},
+    },
+    resources::Resources,
+};
+use anyhow::Result;
+use nalgebra as na;
+use std::cell::Cell;
+
+const OCCLUSION_SHADER_PATH: &str = "shaders/ssao";
+const OCCLUSION_SHADER_NAME: &str = "ssao";
+const BLUR_SHADER_PATH: &str = "shaders/ssao_blur";
+const BLUR_SHADER_NAME: &str = "ssao_blur";
+/// Texture unit the main shader samples the occlusion texture from.
+pub const TEXTURE_UNIT_OCCLUSION: gl::types::GLenum = gl::TEXTURE2;
+/// Largest number of samples per pixel, matching `MAX_SAMPLES` in `ssao.frag`.
+pub const MAX_SAMPLES: u32 = 64;
+/// Side length of the tile of random rotations repeated over the screen, in pixels. The blur
+/// averages over the same number of pixels, which removes the pattern.
+const NOISE_SIZE: usize = 4;
+/// Angle between consecutive points of a Fibonacci spiral, which spreads them evenly.
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+/// Settings of the pass, taken from the shader attributes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OcclusionSettings {
+    /// Radius of the hemisphere checked around each point, in scene units.
+    pub radius: f32,
+    /// Number of points checked per pixel, at most [MAX_SAMPLES].
+    pub samples: u32,
+}
+
+/// The occlusion and blur passes, and the textures they render to.
+pub struct AmbientOcclusion {
+    occlusion_program: render_gl::Program,
+    blur_program: render_gl::Program,
+    occlusion_map: Texture,
+    occlusion_fbo: FrameBuffer,
+    blurred_map: Texture,
+    blur_fbo: FrameBuffer,
+    noise: Texture,
+    /// Empty vertex array for drawing the triangle covering the screen, whose corners are
+    /// generated in the vertex shader.
+    vao: buffer::VertexArray,
+    /// Size the textures were last allocated for.
+    size: Cell<(u32, u32)>,
+}
+
+impl AmbientOcclusion {
+    /// Sets up [AmbientOcclusion], compiling shaders and initializing textures and framebuffers.
+    pub fn new(res: &Resources) -> Result<Self> {
+        let occlusion_program = render_gl::Program::from_res(res, OCCLUSION_SHADER_PATH)?;
+        let blur_program = render_gl::Program::from_res(res, BLUR_SHADER_PATH)?;
+
+        let noise = Texture::new(gl::TEXTURE0);
+        noise.load_texture(
+            (NOISE_SIZE as i32, NOISE_SIZE as i32),
+            Some(&noise_pixels()),
+            gl::RGBA8 as gl::types::GLint,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            true,
+        );
+        noise.unbind();
+
+        let value = Self {
+            occlusion_program,
+            blur_program,
+            occlusion_map: Texture::new(gl::TEXTURE0),
+            occlusion_fbo: FrameBuffer::new(),
+            blurred_map: Texture::new(TEXTURE_UNIT_OCCLUSION),
+            blur_fbo: FrameBuffer::new(),
+            noise,
+            vao: buffer::VertexArray::new(),
+            size: Cell::new((0, 0)),
+        };
+        value.resize((1, 1));
+        Ok(value)
+    }
+
+    /// Get the blurred occlusion texture, which is 1 where nothing is occluded.
+    pub fn get_texture(&self) -> &Texture {
+        &self.blurred_map
+    }
+
+    /// Allocates the textures for a viewport of the given size, if they are of another size.
+    fn resize(&self, size: (u32, u32)) {
+        if self.size.get() == size {
+            return;
+        }
+        self.size.set(size);
+        let dimensions = (size.0.max(1) as i32, size.1.max(1) as i32);
+        for map in [&self.occlusion_map, &self.blurred_map] {
+            map.load_texture(
+                dimensions,
+                None,
+                gl::R8 as gl::types::GLint,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                false,
+            );
+            map.set_border_color(&[1.0, 1.0, 1.0, 1.0]);
+            // Show the occlusion in grey rather than red in the texture tester.
+            // Safety: the texture is bound by `load_texture`, and the parameters are valid.
+            unsafe {
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_G, gl::RED as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_B, gl::RED as i32);
+            }
+            map.unbind();
+        }
+
+        self.occlusion_fbo
+            .bind_texture(gl::COLOR_ATTACHMENT0, &self.occlusion_map);
+        self.occlusion_fbo
+            .set_type(gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT0);
+        self.blur_fbo
+            .bind_texture(gl::COLOR_ATTACHMENT0, &self.blurred_map);
+        self.blur_fbo
+            .set_type(gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT0);
+        self.blur_fbo.unbind();
+    }
+
+    /// Calculates the occlusion from the G-buffer of a viewport of the given size and blurs it.
+    /// `projection_matrix` is the one the scene was drawn into the G-buffer with.
+    ///
+    /// ### Safety
+    ///
+    /// Requires a current OpenGL context. This function should only be called from
+    /// [crate::model::Model::render].
+    pub unsafe fn render(
+        &self,
+        gbuffer: &GBuffer,
+        size: (u32, u32),
+        projection_matrix: &na::Matrix4<f32>,
+        camera_position: na::Vector3<f32>,
+        settings: OcclusionSettings,
+    ) {
+        let inverse = projection_matrix
+            .try_inverse()
+            .unwrap_or_else(na::Matrix4::identity);
+        let samples = kernel(settings.samples.clamp(1, MAX_SAMPLES) as usize);
+        self.resize(size);
+        gl::Viewport(0, 0, size.0 as i32, size.1 as i32);
+        gl::Disable(gl::DEPTH_TEST);
+        self.vao.bind();
+
+        self.occlusion_fbo.bind();
+        self.occlusion_program.set_used();
+        self.occlusion_program
+            .set_uniform_matrix4("projection_matrix", projection_matrix);
+        self.occlusion_program
+            .set_uniform_matrix4("inverse_projection_matrix", &inverse);
+        self.occlusion_program
+            .set_uniform_3f_na("camera_position", camera_position);
+        self.occlusion_program
+            .set_uniform_f("radius", settings.radius);
+        self.occlusion_program
+            .set_uniform_ui("sample_count", samples.len() as u32);
+        for (i, sample) in samples.iter().enumerate() {
+            self.occlusion_program
+                .set_uniform_3f_na(&format!("samples[{}]", i), *sample);
+        }
+        gbuffer.bind_to(gl::TEXTURE0, gl::TEXTURE1);
+        self.noise.bind_to(gl::TEXTURE2);
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+        self.blur_fbo.bind();
+        self.blur_program.set_used();
+        self.occlusion_map.bind_to(gl::TEXTURE0);
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+        self.blur_fbo.unbind();
+        self.vao.unbind();
+        for unit in [gl::TEXTURE2, gl::TEXTURE1, gl::TEXTURE0] {
+            gl::ActiveTexture(unit);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        gl::Enable(gl::DEPTH_TEST);
+    }
+
+    /// Check if any of the shaders have been updated.
+    pub fn check_shader_update(&mut self, path: &std::path::Path, res: &Resources) -> bool {
+        let path = path.file_stem().map(|p| p.to_string_lossy().to_string());
+        let (program, shader_path) = match path.as_deref() {
+            Some(OCCLUSION_SHADER_NAME) => (&mut self.occlusion_program, OCCLUSION_SHADER_PATH),
+            Some(BLUR_SHADER_NAME) => (&mut self.blur_program, BLUR_SHADER_PATH),
+            _ => return false,
+        };
+        match render_gl::Program::from_res(res, shader_path) {
+            Ok(new_program) => {
+                program.unset_used();
+                *program = new_program;
+                true
+            }
+            Err(e) => {
+                eprintln!("Shader reload error: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Gets the given number of points in the unit hemisphere around the z axis. Their directions
+/// follow a Fibonacci spiral and their distances a van der Corput sequence, so they are spread
+/// evenly but the same on every frame, and more of them lie close to the centre, where nearby
+/// geometry matters most. Directions close to the surface are left out, as curved surfaces would
+/// occlude themselves there.
+fn kernel(count: usize) -> Vec<na::Vector3<f32>> {
+    (0..count)
+        .map(|i| {
+            let z = 1.0 - 0.85 * (i as f32 + 0.5) / count as f32;
+            let r = (1.0 - z * z).sqrt();
+            let angle = i as f32 * GOLDEN_ANGLE;
+            let direction = na::Vector3::new(r * angle.cos(), r * angle.sin(), z);
+            let distance = (i as u32 + 1).reverse_bits() as f32 / 2f32.powi(32);
+            direction * (0.1 + 0.9 * distance * distance)
+        })
+        .collect()
+}
+
+/// Gets the RGBA pixels of the tile of random rotations around the normal, as unit vectors in
+/// the tangent plane mapped from [-1, 1] to [0, 255].
+fn noise_pixels() -> Vec<u8> {
+    (0..NOISE_SIZE * NOISE_SIZE)
+        .flat_map(|i| {
+            let angle = i as f32 * GOLDEN_ANGLE;
+            let to_byte = |x: f32| ((x * 0.5 + 0.5) * 255.0).round() as u8;
+            [
+                to_byte(angle.cos()),
+                to_byte(angle.sin()),
+                to_byte(0.0),
+                255,
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_in_hemisphere() {
+        for count in [1, 16, MAX_SAMPLES as usize] {
+            let samples = kernel(count);
+            assert_eq!(samples.len(), count);
+            assert!(samples
+                .iter()
+                .all(|s| s.z > 0.0 && s.norm() <= 1.0 && s.norm() >= 0.1));
+        }
+        // Samples are spread around the normal, rather than all on one side of it.
+        let centre: na::Vector3<f32> = kernel(16).iter().sum::<na::Vector3<f32>>() / 16.0;
+        assert!(centre.xy().norm() < 0.1);
+
+        let noise = noise_pixels();
+        assert_eq!(noise.len(), NOISE_SIZE * NOISE_SIZE * 4);
+    }
+}
@@ -0,0 +1,317 @@
+//! Contains [Bvh], a bounding volume hierarchy over the triangles of a model, used to quickly
+//! find the triangle under the cursor.
+
+use crate::geometry::{intersect_ray_and_triangle, Ray};
+use na::{Point3, Vector3, Vector4};
+use nalgebra as na;
+
+/// Largest number of triangles in a leaf node.
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+/// Where a ray hits a triangle of a [Bvh].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    pub point: Point3<f32>,
+    /// Index of the triangle, in the order the triangles were given.
+    pub triangle: usize,
+    /// Unit normal of the triangle, facing the ray origin.
+    pub normal: Vector3<f32>,
+    /// Distance along the ray.
+    pub distance: f32,
+}
+
+/// A node of the hierarchy, covering either two child nodes or a range of triangles.
+#[derive(Debug, Clone)]
+struct Node {
+    min: Point3<f32>,
+    max: Point3<f32>,
+    /// Index of the second child for inner nodes, with the first directly following this node.
+    /// Index of the first triangle for leaf nodes.
+    start: usize,
+    /// Number of triangles in a leaf node, or zero for inner nodes.
+    count: usize,
+}
+
+/// Bounding volume hierarchy over a triangle mesh, split along the longest axis at the median.
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    /// Triangles ordered so every leaf covers a contiguous range.
+    triangles: Vec<[Point3<f32>; 3]>,
+    /// Original index of each triangle in `triangles`.
+    order: Vec<usize>,
+}
+
+impl Bvh {
+    /// Builds the hierarchy for triangles given by indices into a list of vertex positions.
+    pub fn new(positions: &[Point3<f32>], indices: &[u32]) -> Self {
+        let triangles: Vec<[Point3<f32>; 3]> = indices
+            .chunks_exact(3)
+            .map(|t| [0, 1, 2].map(|v| positions[t[v] as usize]))
+            .collect();
+        let mut order: Vec<usize> = (0..triangles.len()).collect();
+        let centroids: Vec<Point3<f32>> = triangles
+            .iter()
+            .map(|[a, b, c]| Point3::from((a.coords + b.coords + c.coords) / 3.0))
+            .collect();
+
+        let mut nodes = Vec::with_capacity(2 * triangles.len() / MAX_LEAF_TRIANGLES + 1);
+        if !triangles.is_empty() {
+            build(&mut nodes, &triangles, &centroids, &mut order, 0);
+        }
+        Self {
+            nodes,
+            triangles: order.iter().map(|&i| triangles[i]).collect(),
+            order,
+        }
+    }
+
+    /// Finds the closest triangle hit by the ray, only considering hits for which `include`
+    /// returns true given the triangle index and the point hit.
+    pub fn intersect(
+        &self,
+        ray: &Ray,
+        include: impl Fn(usize, &Point3<f32>) -> bool,
+    ) -> Option<Hit> {
+        let inverse_direction = ray.direction.map(|d| 1.0 / d);
+        let mut closest: Option<(f32, usize)> = None;
+        let mut stack = Vec::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let max_distance = closest.map_or(f32::INFINITY, |(distance, _)| distance);
+            if !intersect_ray_and_box(ray, &inverse_direction, node, max_distance) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.start);
+                stack.push(index + 1);
+                continue;
+            }
+            for sorted in node.start..node.start + node.count {
+                let Some(distance) = intersect_ray_and_triangle(ray, &self.triangles[sorted])
+                else {
+                    continue;
+                };
+                if distance < closest.map_or(f32::INFINITY, |(closest, _)| closest)
+                    && include(self.order[sorted], &ray.at(distance))
+                {
+                    closest = Some((distance, sorted));
+                }
+            }
+        }
+
+        let (distance, sorted) = closest?;
+        let [a, b, c] = self.triangles[sorted];
+        let normal = (b - a).cross(&(c - a)).normalize();
+        Some(Hit {
+            point: ray.at(distance),
+            triangle: self.order[sorted],
+            normal: match normal.dot(&ray.direction) > 0.0 {
+                true => -normal,
+                false => normal,
+            },
+            distance,
+        })
+    }
+}
+
+impl Bvh {
+    /// Gets the triangles that may touch a plane, given as the vector whose dot product with a
+    /// homogeneous point is its distance from the plane, along with their indices.
+    pub fn triangles_near_plane(&self, plane: &Vector4<f32>) -> Vec<(usize, [Point3<f32>; 3])> {
+        let normal = plane.xyz();
+        let mut triangles = Vec::new();
+        let mut stack = Vec::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let centre = na::center(&node.min, &node.max);
+            let radius = ((node.max - node.min) / 2.0).dot(&normal.abs());
+            if (normal.dot(&centre.coords) + plane.w).abs() > radius {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.start);
+                stack.push(index + 1);
+                continue;
+            }
+            for sorted in node.start..node.start + node.count {
+                triangles.push((self.order[sorted], self.triangles[sorted]));
+            }
+        }
+        triangles
+    }
+}
+
+/// Recursively builds the node covering the triangles in `order`, which become the sorted
+/// triangles from index `start` on. The node is pushed to the end of `nodes`, followed by its
+/// descendants.
+fn build(
+    nodes: &mut Vec<Node>,
+    triangles: &[[Point3<f32>; 3]],
+    centroids: &[Point3<f32>],
+    order: &mut [usize],
+    start: usize,
+) {
+    let (min, max) = bounds(order.iter().flat_map(|&i| triangles[i]));
+    let index = nodes.len();
+    nodes.push(Node {
+        min,
+        max,
+        start,
+        count: order.len(),
+    });
+    if order.len() <= MAX_LEAF_TRIANGLES {
+        return;
+    }
+
+    // Split at the median centroid along the axis where the centroids are spread the most.
+    let (centroid_min, centroid_max) = bounds(order.iter().map(|&i| centroids[i]));
+    let axis = (centroid_max - centroid_min).imax();
+    let middle = order.len() / 2;
+    order.select_nth_unstable_by(middle, |&a, &b| {
+        centroids[a][axis].total_cmp(&centroids[b][axis])
+    });
+
+    let (left, right) = order.split_at_mut(middle);
+    build(nodes, triangles, centroids, left, start);
+    let right_index = nodes.len();
+    build(nodes, triangles, centroids, right, start + middle);
+    nodes[index].start = right_index;
+    nodes[index].count = 0;
+}
+
+/// Gets the corners of the bounding box of the points.
+fn bounds(points: impl Iterator<Item = Point3<f32>>) -> (Point3<f32>, Point3<f32>) {
+    let empty = (
+        Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+    );
+    points.fold(empty, |(min, max), p| (min.inf(&p), max.sup(&p)))
+}
+
+/// Checks if the ray passes through the bounding box of the node closer than `max_distance`,
+/// using the slab method.
+fn intersect_ray_and_box(
+    ray: &Ray,
+    inverse_direction: &Vector3<f32>,
+    node: &Node,
+    max_distance: f32,
+) -> bool {
+    let mut near = 0.0f32;
+    let mut far = max_distance;
+    for axis in 0..3 {
+        let t1 = (node.min[axis] - ray.origin[axis]) * inverse_direction[axis];
+        let t2 = (node.max[axis] - ray.origin[axis]) * inverse_direction[axis];
+        // NaN from rays lying in a box face is ignored by `max` and `min`.
+        near = near.max(t1.min(t2));
+        far = far.min(t1.max(t2));
+    }
+    near <= far
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid of small triangles in several layers along z, with pseudo-random jitter.
+    fn layered_grid() -> (Vec<Point3<f32>>, Vec<u32>) {
+        let mut seed = 12345u32;
+        let mut random = move || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as f32 / 65536.0
+        };
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for layer in 0..3 {
+            for y in 0..10 {
+                for x in 0..10 {
+                    let base = positions.len() as u32;
+                    let (x, y, z) = (x as f32, y as f32, layer as f32 * 2.0 + random());
+                    positions.push(Point3::new(x, y, z));
+                    positions.push(Point3::new(x + 1.0 + random(), y, z + random()));
+                    positions.push(Point3::new(x, y + 1.0 + random(), z));
+                    indices.extend([base, base + 1, base + 2]);
+                }
+            }
+        }
+        (positions, indices)
+    }
+
+    /// Finds the closest hit by testing every triangle.
+    fn brute_force(positions: &[Point3<f32>], indices: &[u32], ray: &Ray) -> Option<(usize, f32)> {
+        indices
+            .chunks_exact(3)
+            .enumerate()
+            .filter_map(|(i, t)| {
+                let triangle = [0, 1, 2].map(|v| positions[t[v] as usize]);
+                intersect_ray_and_triangle(ray, &triangle).map(|distance| (i, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let (positions, indices) = layered_grid();
+        let bvh = Bvh::new(&positions, &indices);
+        for i in 0..200 {
+            let (x, y) = ((i % 20) as f32 * 0.55 - 0.3, (i / 20) as f32 * 1.05 + 0.2);
+            for direction in [Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.2, -0.1, 1.0)] {
+                let origin = match direction.z < 0.0 {
+                    true => Point3::new(x, y, 10.0),
+                    false => Point3::new(x, y, -5.0),
+                };
+                let ray = Ray {
+                    origin,
+                    direction: direction.normalize(),
+                };
+                let expected = brute_force(&positions, &indices, &ray);
+                let hit = bvh.intersect(&ray, |_, _| true);
+                assert_eq!(hit.map(|hit| hit.triangle), expected.map(|(i, _)| i));
+                if let (Some(hit), Some((_, distance))) = (hit, expected) {
+                    assert_eq!(hit.distance, distance);
+                    assert!(hit.normal.dot(&ray.direction) <= 0.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn excluded_triangles_are_skipped() {
+        let (positions, indices) = layered_grid();
+        let bvh = Bvh::new(&positions, &indices);
+        let ray = Ray {
+            origin: Point3::new(0.25, 0.25, 10.0),
+            direction: Vector3::new(0.0, 0.0, -1.0),
+        };
+        // The top layer is hit first, then the layers below it once it is excluded.
+        assert_eq!(bvh.intersect(&ray, |_, _| true).unwrap().triangle, 200);
+        assert_eq!(bvh.intersect(&ray, |i, _| i < 200).unwrap().triangle, 100);
+        assert_eq!(bvh.intersect(&ray, |_, _| false), None);
+        assert_eq!(Bvh::new(&[], &[]).intersect(&ray, |_, _| true), None);
+    }
+
+    #[test]
+    fn triangles_near_plane() {
+        let (positions, indices) = layered_grid();
+        let bvh = Bvh::new(&positions, &indices);
+        // The plane z = 2.5 only cuts the middle layer, so the other layers are mostly skipped.
+        let near: Vec<usize> = bvh
+            .triangles_near_plane(&Vector4::new(0.0, 0.0, 1.0, -2.5))
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect();
+        assert!(near.len() < 200);
+        for (index, triangle) in indices.chunks_exact(3).enumerate() {
+            let above = triangle.iter().filter(|&&i| positions[i as usize].z > 2.5);
+            if (1..3).contains(&above.count()) {
+                assert!(near.contains(&index));
+            }
+        }
+    }
+}
@@ -1,19 +1,79 @@
 //! Contains the [Camera] struct and related constants.
 
+use crate::geometry::Ray;
 use core::f32;
 use na::Point3;
 use nalgebra as na;
+use serde::{Deserialize, Serialize};
 
 /// Avoids a camera distance of zero, messing up math elsewhere.
 const MIN_ZOOM: f32 = 1.0;
 /// Reasonable far plane distance.
 const MAX_ZOOM: f32 = 400.0;
+/// Keeps the orbit camera just short of the poles, where its direction is undefined.
+const MAX_ELEVATION: f32 = f32::consts::PI / 2.0 - 0.001;
+/// Angle the orbit camera turns per pixel of mouse movement.
+const ORBIT_SPEED: f32 = f32::consts::TAU / 500.0;
+
+/// Direction and distance of the camera, in a form that is easy to edit by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraView {
+    /// Angle around the vertical axis, in degrees.
+    pub azimuth: f32,
+    /// Angle above the horizontal plane, in degrees.
+    pub elevation: f32,
+    /// Distance from the model centre, or none to fit the model in view.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance: Option<f32>,
+    /// Point the camera looks at and rotates around.
+    pub target: na::Vector3<f32>,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        Self {
+            azimuth: 45.0,
+            elevation: 0.0,
+            distance: None,
+            target: na::Vector3::zeros(),
+        }
+    }
+}
+
+/// How dragging with the mouse rotates the camera.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Turns around the vertical axis and up or down, keeping the horizon level.
+    #[default]
+    Orbit,
+    /// Rolls the view like a ball under the cursor, free to rotate in any direction.
+    Arcball,
+}
+
+impl std::fmt::Display for CameraMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            CameraMode::Orbit => write!(f, "Orbit"),
+            CameraMode::Arcball => write!(f, "Arcball"),
+        }
+    }
+}
+
+/// What dragging with a mouse button held down does to the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DragAction {
+    Rotate,
+    Pan,
+}
 
 pub struct Camera {
     fov: f32,
-    mousedown: bool,
-    roll: f32,
-    pitch: f32,
+    mode: CameraMode,
+    drag: Option<DragAction>,
+    /// Rotation from view space to world space.
+    rotation: na::UnitQuaternion<f32>,
+    target: Point3<f32>,
     dist: f32,
 }
 
@@ -21,60 +81,157 @@ pub struct Camera {
 impl Camera {
     /// Initialize a new [Camera] with default values.
     pub fn new() -> Self {
-        Self {
+        let mut camera = Self {
             fov: f32::consts::PI / 4.0,
-            mousedown: false,
-            roll: 0.0,
-            pitch: f32::consts::PI / 4.0,
+            mode: CameraMode::Orbit,
+            drag: None,
+            rotation: na::UnitQuaternion::identity(),
+            target: Point3::origin(),
             dist: 5.0,
-        }
+        };
+        camera.set_orientation(f32::consts::PI / 4.0, 0.0);
+        camera
     }
 
     /// Returns the position of the camera.
     pub fn position(&self) -> Point3<f32> {
-        let rot = na::Rotation3::from_euler_angles(self.roll, self.pitch, 0.0);
-        let rotated = rot * na::Vector3::z();
-        Point3::from(rotated) * self.dist
+        self.target + self.rotation * na::Vector3::z() * self.dist
     }
 
     /// Constructs a model-view-projection matrix using the camera.
     pub fn construct_mvp(&self, aspect: f32, model: na::Isometry3<f32>) -> na::Matrix4<f32> {
         let eye = self.position();
-        let target = na::Point3::new(0.0, 0.0, 0.0);
-        let view = na::Isometry3::look_at_rh(&eye, &target, &na::Vector3::y());
+        let up = self.rotation * na::Vector3::y();
+        let view = na::Isometry3::look_at_rh(&eye, &self.target, &up);
         let projection = na::Perspective3::new(aspect, self.fov, 0.1, 1000.0);
         projection.into_inner() * (view * model).to_homogeneous()
     }
 
-    /// Informs the camera that the mouse button is held down, to enable camera movement.
-    pub fn mousedown(&mut self) {
-        self.mousedown = true;
+    /// Gets the ray from the camera through the given cursor position in a window of the given
+    /// size, for picking what is under the cursor.
+    pub fn ray_through(&self, cursor: (i32, i32), window: (u32, u32)) -> Option<Ray> {
+        let (width, height) = (window.0.max(1) as f32, window.1.max(1) as f32);
+        let mvp = self.construct_mvp(width / height, na::Isometry3::identity());
+        let x = 2.0 * cursor.0 as f32 / width - 1.0;
+        let y = 1.0 - 2.0 * cursor.1 as f32 / height;
+        Ray::unproject(&mvp, x, y)
+    }
+
+    /// Informs the camera that a mouse button is held down, to enable camera movement.
+    pub fn mousedown(&mut self, action: DragAction) {
+        self.drag = Some(action);
     }
 
     /// Informs the camera that the mouse button is no longer being held down, to disable camera
     /// movement.
     pub fn mouseup(&mut self) {
-        self.mousedown = false;
+        self.drag = None;
     }
 
-    /// Mouse movement handler. Returns true if camera view has changed.
-    pub fn mousemove(&mut self, xrel: i32, yrel: i32) -> bool {
-        if !self.mousedown {
-            return false;
+    /// Mouse movement handler, given the cursor position and movement in pixels and the window
+    /// size. Returns true if camera view has changed.
+    pub fn mousemove(&mut self, x: i32, y: i32, xrel: i32, yrel: i32, window: (u32, u32)) -> bool {
+        match (self.drag, self.mode) {
+            (None, _) => return false,
+            (Some(DragAction::Pan), _) => self.pan(xrel as f32, yrel as f32, window.1 as f32),
+            (Some(DragAction::Rotate), CameraMode::Orbit) => {
+                let (azimuth, elevation) = self.orientation();
+                self.set_orientation(
+                    azimuth - ORBIT_SPEED * xrel as f32,
+                    elevation + ORBIT_SPEED * yrel as f32,
+                );
+            }
+            (Some(DragAction::Rotate), CameraMode::Arcball) => {
+                let from = arcball_point(x - xrel, y - yrel, window);
+                let to = arcball_point(x, y, window);
+                if let Some(rotation) = na::UnitQuaternion::rotation_between(&from, &to) {
+                    // Turning the model with the cursor means turning the camera the other way.
+                    self.rotation *= rotation.inverse();
+                    self.rotation.renormalize();
+                }
+            }
         }
+        true
+    }
 
-        self.pitch = (self.pitch + f32::consts::TAU / 500.0 * -xrel as f32) % f32::consts::TAU;
-        self.roll = (self.roll + f32::consts::TAU / 500.0 * -yrel as f32).clamp(
-            -f32::consts::PI / 2.0 + 0.001,
-            f32::consts::PI / 2.0 - 0.001,
-        );
+    /// Informs the camera that the mousewheel has been scrolled, to zoom towards the point under
+    /// the cursor at the given position in a window of the given size.
+    pub fn mousewheel(&mut self, y: i32, cursor: (i32, i32), window: (u32, u32)) {
+        let dist = (self.dist - 3.0 * y as f32).clamp(MIN_ZOOM, MAX_ZOOM);
+        // Moving the target towards the point under the cursor, by the same fraction as the
+        // camera moves closer, keeps that point under the cursor.
+        let under_cursor = self.cursor_direction(cursor, window) * self.dist;
+        let offset = na::Vector3::new(under_cursor.x, under_cursor.y, 0.0);
+        self.target += self.rotation * offset * (1.0 - dist / self.dist);
+        self.dist = dist;
+    }
 
-        true
+    /// Moves the target in the view plane by the given amount of pixels, so the model follows the
+    /// cursor.
+    fn pan(&mut self, xrel: f32, yrel: f32, window_height: f32) {
+        let pixel_size = 2.0 * self.dist * (self.fov / 2.0).tan() / window_height.max(1.0);
+        let offset = na::Vector3::new(-xrel, yrel, 0.0) * pixel_size;
+        self.target += self.rotation * offset;
+    }
+
+    /// Gets the view space direction through the given cursor position, scaled to reach the plane
+    /// through the target.
+    fn cursor_direction(&self, cursor: (i32, i32), window: (u32, u32)) -> na::Vector3<f32> {
+        let (width, height) = (window.0.max(1) as f32, window.1.max(1) as f32);
+        let half_height = (self.fov / 2.0).tan();
+        let x = (2.0 * cursor.0 as f32 / width - 1.0) * half_height * width / height;
+        let y = (1.0 - 2.0 * cursor.1 as f32 / height) * half_height;
+        na::Vector3::new(x, y, -1.0)
+    }
+
+    /// Gets the camera direction as the angle around the vertical axis and the angle above the
+    /// horizontal plane, both in radians.
+    fn orientation(&self) -> (f32, f32) {
+        let direction = self.rotation * na::Vector3::z();
+        let azimuth = direction.x.atan2(direction.z);
+        (azimuth, direction.y.clamp(-1.0, 1.0).asin())
+    }
+
+    /// Manually sets the camera direction, as the angle around the vertical axis and the angle
+    /// above the horizontal plane, both in radians.
+    pub fn set_orientation(&mut self, azimuth: f32, elevation: f32) {
+        let elevation = elevation.clamp(-MAX_ELEVATION, MAX_ELEVATION);
+        self.rotation = na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), azimuth)
+            * na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), -elevation);
+    }
+
+    /// Sets how dragging rotates the camera. Switching to orbiting levels the horizon again.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == CameraMode::Orbit && self.mode != mode {
+            let (azimuth, elevation) = self.orientation();
+            self.set_orientation(azimuth, elevation);
+        }
+        self.mode = mode;
+    }
+
+    /// Gets the current direction, distance and target of the camera.
+    pub fn view(&self) -> CameraView {
+        let (azimuth, elevation) = self.orientation();
+        CameraView {
+            azimuth: azimuth.to_degrees(),
+            elevation: elevation.to_degrees(),
+            distance: Some(self.dist),
+            target: self.target.coords,
+        }
+    }
+
+    /// Sets the direction and target of the camera, and its distance if given.
+    pub fn set_view(&mut self, view: CameraView) {
+        self.set_orientation(view.azimuth.to_radians(), view.elevation.to_radians());
+        self.target = Point3::from(view.target);
+        if let Some(distance) = view.distance {
+            self.set_dist(distance);
+        }
     }
 
-    /// Informs the camera that the mousewheel has been scrolled, to enable camera zoom.
-    pub fn mousewheel(&mut self, y: i32) {
-        self.dist = (self.dist - 3.0 * y as f32).clamp(MIN_ZOOM, MAX_ZOOM);
+    /// Sets the point the camera looks at and rotates around.
+    pub fn set_target(&mut self, target: Point3<f32>) {
+        self.target = target;
     }
 
     /// Manually sets the camera distance from origin.
@@ -82,3 +239,111 @@ impl Camera {
         self.dist = dist.clamp(MIN_ZOOM, MAX_ZOOM);
     }
 }
+
+/// Projects a cursor position onto a unit sphere filling the window, or onto its outline when
+/// outside it, in view space.
+fn arcball_point(x: i32, y: i32, window: (u32, u32)) -> na::Vector3<f32> {
+    let radius = window.0.min(window.1).max(1) as f32 / 2.0;
+    let x = (x as f32 - window.0 as f32 / 2.0) / radius;
+    let y = (window.1 as f32 / 2.0 - y as f32) / radius;
+    let length_squared = x * x + y * y;
+    match length_squared <= 1.0 {
+        true => na::Vector3::new(x, y, (1.0 - length_squared).sqrt()),
+        false => na::Vector3::new(x, y, 0.0) / length_squared.sqrt(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: (u32, u32) = (800, 600);
+
+    /// Projects a world position to pixel coordinates in [WINDOW].
+    fn project(camera: &Camera, point: Point3<f32>) -> (f32, f32) {
+        let aspect = WINDOW.0 as f32 / WINDOW.1 as f32;
+        let mvp = camera.construct_mvp(aspect, na::Isometry3::identity());
+        let clip = mvp * point.to_homogeneous();
+        let ndc = clip.xyz() / clip.w;
+        (
+            (ndc.x + 1.0) / 2.0 * WINDOW.0 as f32,
+            (1.0 - ndc.y) / 2.0 * WINDOW.1 as f32,
+        )
+    }
+
+    fn assert_close(a: (f32, f32), b: (f32, f32)) {
+        assert!(
+            (a.0 - b.0).abs() < 0.05 && (a.1 - b.1).abs() < 0.05,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn orientation_matches_euler_angles() {
+        let mut camera = Camera::new();
+        camera.set_dist(10.0);
+        camera.set_orientation(0.5, 0.3);
+        let euler = na::Rotation3::from_euler_angles(-0.3, 0.5, 0.0);
+        let expected = Point3::from(euler * na::Vector3::z() * 10.0);
+        assert!((camera.position() - expected).norm() < 1e-5);
+
+        let view = camera.view();
+        assert!((view.azimuth - 0.5f32.to_degrees()).abs() < 1e-3);
+        assert!((view.elevation - 0.3f32.to_degrees()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn arcball_passes_the_poles() {
+        let mut camera = Camera::new();
+        camera.set_mode(CameraMode::Arcball);
+        camera.set_dist(10.0);
+        camera.mousedown(DragAction::Rotate);
+        for _ in 0..60 {
+            camera.mousemove(400, 310, 0, 10, WINDOW);
+        }
+        // Dragging down far enough turns the camera over the top to the other side of the model.
+        let position = camera.position();
+        assert!(position.y > 0.0 && position.x < 0.0 && position.z < 0.0);
+        assert!(((position - camera.target).norm() - 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn pan_follows_cursor() {
+        let mut camera = Camera::new();
+        camera.set_dist(10.0);
+        let point = Point3::origin();
+        let before = project(&camera, point);
+        camera.mousedown(DragAction::Pan);
+        camera.mousemove(420, 290, 20, -10, WINDOW);
+        assert_close(project(&camera, point), (before.0 + 20.0, before.1 - 10.0));
+    }
+
+    #[test]
+    fn ray_through_cursor() {
+        let mut camera = Camera::new();
+        camera.set_dist(10.0);
+        camera.set_target(Point3::new(1.0, 2.0, 3.0));
+        let ray = camera.ray_through((650, 100), WINDOW).unwrap();
+        let point = ray.at(7.0);
+        assert_close(project(&camera, point), (650.0, 100.0));
+        let centre = camera.ray_through((400, 300), WINDOW).unwrap();
+        let to_target = (camera.target - centre.origin).normalize();
+        assert!((centre.direction - to_target).norm() < 1e-4);
+    }
+
+    #[test]
+    fn zoom_towards_cursor() {
+        let mut camera = Camera::new();
+        camera.set_dist(20.0);
+        let cursor = (600, 150);
+        // The point under the cursor on the plane through the target stays under the cursor.
+        let direction = camera.cursor_direction(cursor, WINDOW) * 20.0;
+        let point = camera.position() + camera.rotation * direction;
+        assert_close(project(&camera, point), (600.0, 150.0));
+        camera.mousewheel(2, cursor, WINDOW);
+        assert_close(project(&camera, point), (600.0, 150.0));
+        assert!(((camera.position() - camera.target).norm() - 14.0).abs() < 1e-4);
+    }
+}
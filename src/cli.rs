@@ -0,0 +1,197 @@
+//! Contains the command-line interface definition.
+
+use crate::preset::Preset;
+use anyhow::{anyhow, Result};
+use clap::{Args, Parser, Subcommand};
+use nalgebra as na;
+use std::path::PathBuf;
+
+/// Medical visualization tool for rendering blood vessel meshes.
+///
+/// Without a subcommand, the model is opened in an interactive window.
+#[derive(Debug, Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Directory containing the shaders and models. Defaults to the assets directory next to the
+    /// executable.
+    #[arg(long, value_name = "DIR", global = true)]
+    pub assets: Option<PathBuf>,
+    #[command(flatten)]
+    pub window: WindowArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Renders a model to a PNG image without opening a window, using an offscreen EGL context.
+    Render(RenderArgs),
+    /// Runs a perception study described by a configuration file, showing randomised trials
+    /// full-screen and logging the answers and reaction times of a participant to CSV.
+    Study(StudyArgs),
+}
+
+/// Options for the interactive window.
+#[derive(Debug, Args)]
+pub struct WindowArgs {
+    /// Model or volume file to open, at any path. Defaults to `model.obj` in the assets directory.
+    pub model: Option<PathBuf>,
+    /// Window width in pixels.
+    #[arg(long, default_value_t = 1200)]
+    pub width: u32,
+    /// Window height in pixels.
+    #[arg(long, default_value_t = 800)]
+    pub height: u32,
+    /// Opens the window in fullscreen, covering the whole desktop.
+    #[arg(short, long)]
+    pub fullscreen: bool,
+    #[command(flatten)]
+    pub view: ViewArgs,
+}
+
+/// Options for rendering a single image offscreen.
+#[derive(Debug, Args)]
+pub struct RenderArgs {
+    /// Model file to render, in any of the supported mesh formats.
+    pub model: PathBuf,
+    /// Path of the PNG image to write.
+    #[arg(short, long)]
+    pub output: PathBuf,
+    /// Image width in pixels.
+    #[arg(long, default_value_t = 1920)]
+    pub width: u32,
+    /// Image height in pixels.
+    #[arg(long, default_value_t = 1080)]
+    pub height: u32,
+    #[command(flatten)]
+    pub view: ViewArgs,
+}
+
+/// Options for running a study.
+#[derive(Debug, Args)]
+pub struct StudyArgs {
+    /// TOML file listing the models, presets and camera views of the study.
+    pub config: PathBuf,
+    /// Participant identifier, naming the CSV file the responses are written to.
+    #[arg(short, long)]
+    pub participant: String,
+    /// Directory to write the responses to.
+    #[arg(short, long, value_name = "DIR", default_value = ".")]
+    pub output: PathBuf,
+    /// Opens a window instead of covering the whole desktop, for trying out a study.
+    #[arg(long)]
+    pub windowed: bool,
+    /// Window width in pixels, when windowed.
+    #[arg(long, default_value_t = 1200)]
+    pub width: u32,
+    /// Window height in pixels, when windowed.
+    #[arg(long, default_value_t = 800)]
+    pub height: u32,
+}
+
+/// Options for how the model is initially shown, shared by the window and offscreen rendering.
+#[derive(Debug, Args)]
+pub struct ViewArgs {
+    /// Visualization preset to start with.
+    #[arg(short, long, value_enum, default_value_t)]
+    pub preset: Preset,
+    /// Camera angle around the vertical axis, in degrees.
+    #[arg(long, default_value_t = 45.0, allow_negative_numbers = true)]
+    pub azimuth: f32,
+    /// Camera angle above the horizontal plane, in degrees.
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub elevation: f32,
+    /// Camera distance from the model centre. Defaults to fitting the whole model in view.
+    #[arg(long)]
+    pub distance: Option<f32>,
+    /// Background colour, either as hexadecimal `#rrggbb` or as comma-separated `r,g,b` values
+    /// between 0 and 1.
+    #[arg(long, value_name = "COLOR", default_value = "0.3,0.3,0.5", value_parser = parse_color)]
+    pub background: na::Vector3<f32>,
+}
+
+/// Parses a colour given as `#rrggbb` or as `r,g,b` with components between 0 and 1.
+fn parse_color(text: &str) -> Result<na::Vector3<f32>> {
+    let components: Vec<f32> = match text.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.is_ascii() => (0..3)
+            .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map(|c| c as f32 / 255.0))
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(anyhow!("Expected six hexadecimal digits after '#'.")),
+        None => text
+            .split(',')
+            .map(|c| c.trim().parse::<f32>())
+            .collect::<Result<_, _>>()?,
+    };
+    match components[..] {
+        [r, g, b] if components.iter().all(|c| (0.0..=1.0).contains(c)) => {
+            Ok(na::Vector3::new(r, g, b))
+        }
+        _ => Err(anyhow!("Expected three components between 0 and 1.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn render_arguments() {
+        Cli::command().debug_assert();
+        let cli = Cli::parse_from([
+            "ivrigst",
+            "render",
+            "vessel.stl",
+            "-o",
+            "out.png",
+            "--preset",
+            "chroma-depth",
+            "--elevation",
+            "-30",
+        ]);
+        let Some(Command::Render(args)) = cli.command else {
+            panic!("Expected the render command.");
+        };
+        assert_eq!(args.view.preset, Preset::ChromaDepth);
+        assert_eq!(args.view.elevation, -30.0);
+        assert_eq!((args.width, args.height), (1920, 1080));
+    }
+
+    #[test]
+    fn study_arguments() {
+        let cli = Cli::parse_from(["ivrigst", "study", "study.toml", "-p", "P07", "--windowed"]);
+        let Some(Command::Study(args)) = cli.command else {
+            panic!("Expected the study command.");
+        };
+        assert_eq!(args.config, PathBuf::from("study.toml"));
+        assert_eq!(args.participant, "P07");
+        assert_eq!(args.output, PathBuf::from("."));
+        assert!(args.windowed);
+    }
+
+    #[test]
+    fn window_arguments() {
+        let cli = Cli::parse_from(["ivrigst", "--assets", "shaders", "vessel.stl", "-f"]);
+        assert!(cli.command.is_none());
+        assert_eq!(cli.assets, Some(PathBuf::from("shaders")));
+        assert_eq!(cli.window.model, Some(PathBuf::from("vessel.stl")));
+        assert!(cli.window.fullscreen);
+        assert_eq!((cli.window.width, cli.window.height), (1200, 800));
+        assert_eq!(cli.window.view.background, na::Vector3::new(0.3, 0.3, 0.5));
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(
+            parse_color("#ff0033").unwrap(),
+            na::Vector3::new(1.0, 0.0, 0.2)
+        );
+        assert_eq!(
+            parse_color("0.1, 0.2,1").unwrap(),
+            na::Vector3::new(0.1, 0.2, 1.0)
+        );
+        assert!(parse_color("#ff00").is_err());
+        assert!(parse_color("0.1,0.2").is_err());
+        assert!(parse_color("0.1,0.2,2").is_err());
+    }
+}
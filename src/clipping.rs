@@ -0,0 +1,306 @@
+//! Contains [Clipping], the planes cutting away part of the model, and the gizmo for moving and
+//! rotating them in the view.
+
+use crate::geometry::project_to_window;
+use na::{Point2, Point3, Vector3, Vector4};
+use nalgebra as na;
+
+/// Colour of the gizmo of the selected plane.
+pub const GIZMO_COLOR: [f32; 3] = [0.2, 0.8, 1.0];
+/// Largest number of clipping planes, matching `MAX_CLIP_PLANES` in the vertex shaders.
+pub const MAX_CLIP_PLANES: usize = 4;
+/// Side length of the square showing the selected plane, relative to the model size.
+const GIZMO_SIZE: f32 = 0.6;
+/// Length of the arrow showing the plane normal, relative to the model size.
+const GIZMO_ARROW_LENGTH: f32 = 0.3;
+/// Pixels from the gizmo within which it can be grabbed.
+const GRAB_DISTANCE: f32 = 8.0;
+/// Degrees a plane is rotated per pixel dragged.
+const ROTATE_SPEED: f32 = 0.5;
+
+/// A plane removing everything on the side its normal points to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClipPlane {
+    pub enabled: bool,
+    /// Angle of the normal around the vertical axis, in degrees.
+    pub azimuth: f32,
+    /// Angle of the normal above the horizontal plane, in degrees.
+    pub elevation: f32,
+    /// Signed distance of the plane from the model centre, along the normal.
+    pub offset: f32,
+}
+
+impl Default for ClipPlane {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            azimuth: 0.0,
+            elevation: 0.0,
+            offset: 0.0,
+        }
+    }
+}
+
+impl ClipPlane {
+    /// Gets the unit normal, pointing towards the part that is cut away.
+    pub fn normal(&self) -> Vector3<f32> {
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        Vector3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        )
+    }
+
+    /// Gets the plane as the vector whose dot product with a homogeneous point is its distance
+    /// from the plane, positive on the side that is kept. This is passed to the shaders.
+    pub fn equation(&self) -> Vector4<f32> {
+        (-self.normal()).push(self.offset)
+    }
+
+    /// Gets the point of the plane closest to the model centre.
+    pub fn centre(&self) -> Point3<f32> {
+        Point3::from(self.normal() * self.offset)
+    }
+
+    /// Gets two perpendicular unit vectors in the plane, whose cross product is the normal.
+    pub fn basis(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let normal = self.normal();
+        let up = match normal.y.abs() < 0.9 {
+            true => Vector3::y(),
+            false => Vector3::x(),
+        };
+        let u = normal.cross(&up).normalize();
+        (u, normal.cross(&u))
+    }
+
+    /// Gets the line segments of the gizmo for a model of the given size: a square in the plane
+    /// and an arrow along the normal.
+    fn gizmo_lines(&self, model_size: f32) -> Vec<[Point3<f32>; 2]> {
+        let normal = self.normal();
+        let (u, v) = self.basis();
+        let (u, v) = (
+            u * GIZMO_SIZE * model_size / 2.0,
+            v * GIZMO_SIZE * model_size / 2.0,
+        );
+        let centre = self.centre();
+        let corners = [
+            centre + u + v,
+            centre - u + v,
+            centre - u - v,
+            centre + u - v,
+        ];
+        let mut lines: Vec<_> = (0..4).map(|i| [corners[i], corners[(i + 1) % 4]]).collect();
+
+        let tip = centre + normal * GIZMO_ARROW_LENGTH * model_size;
+        let head = normal * GIZMO_ARROW_LENGTH * model_size * 0.2;
+        let (u, v) = (u * 0.1 / GIZMO_SIZE, v * 0.1 / GIZMO_SIZE);
+        lines.push([centre, tip]);
+        for side in [u, -u, v, -v] {
+            lines.push([tip, tip - head + side]);
+        }
+        lines
+    }
+}
+
+/// Which part of the gizmo is being dragged.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GizmoDrag {
+    /// The arrow, moving the plane along its normal.
+    Move,
+    /// The square, rotating the plane.
+    Rotate,
+}
+
+/// The clipping planes, and how the cuts are shown.
+#[derive(Clone, Debug)]
+pub struct Clipping {
+    pub planes: Vec<ClipPlane>,
+    /// Whether the cut is filled with `cap_color`, rather than showing the inside of the model.
+    pub cap: bool,
+    pub cap_color: Vector3<f32>,
+    /// Plane whose gizmo is shown, and whose cross-section is shown. It can be disabled to
+    /// take a cross-section without cutting the model.
+    pub selected: Option<usize>,
+}
+
+impl Default for Clipping {
+    fn default() -> Self {
+        Self {
+            planes: Vec::new(),
+            cap: true,
+            cap_color: Vector3::new(0.85, 0.3, 0.3),
+            selected: None,
+        }
+    }
+}
+
+impl Clipping {
+    /// Gets the equations of the enabled planes, as passed to the shaders.
+    pub fn equations(&self) -> Vec<Vector4<f32>> {
+        self.planes
+            .iter()
+            .filter(|plane| plane.enabled)
+            .map(ClipPlane::equation)
+            .collect()
+    }
+
+    /// Gets the selected plane, if any.
+    pub fn selected_plane(&self) -> Option<&ClipPlane> {
+        self.planes.get(self.selected?)
+    }
+
+    /// Gets the line segments of the gizmo of the selected plane, if any.
+    pub fn gizmo_lines(&self, model_size: f32) -> Vec<[Point3<f32>; 2]> {
+        self.selected_plane()
+            .map(|plane| plane.gizmo_lines(model_size))
+            .unwrap_or_default()
+    }
+
+    /// Finds the part of the gizmo under the cursor, if any. `mvp` is the model view projection
+    /// matrix of a window of the given size.
+    pub fn grab(
+        &self,
+        model_size: f32,
+        mvp: &na::Matrix4<f32>,
+        window: (u32, u32),
+        cursor: (i32, i32),
+    ) -> Option<GizmoDrag> {
+        let lines = self.selected_plane()?.gizmo_lines(model_size);
+        let cursor = Point2::new(cursor.0 as f32, cursor.1 as f32);
+        let near = |line: &[Point3<f32>; 2]| {
+            let a = project_to_window(mvp, &line[0], window);
+            let b = project_to_window(mvp, &line[1], window);
+            a.zip(b)
+                .is_some_and(|(a, b)| distance_to_segment(cursor, a, b) < GRAB_DISTANCE)
+        };
+        // The arrow is checked first, as it can be in front of the square.
+        if near(&lines[4]) {
+            Some(GizmoDrag::Move)
+        } else if lines[..4].iter().any(near) {
+            Some(GizmoDrag::Rotate)
+        } else {
+            None
+        }
+    }
+
+    /// Moves or rotates the selected plane by the mouse movement in pixels.
+    pub fn drag(
+        &mut self,
+        drag: GizmoDrag,
+        model_size: f32,
+        mvp: &na::Matrix4<f32>,
+        window: (u32, u32),
+        movement: (i32, i32),
+    ) {
+        let Some(plane) = self.selected.and_then(|i| self.planes.get_mut(i)) else {
+            return;
+        };
+        let movement = na::Vector2::new(movement.0 as f32, movement.1 as f32);
+        match drag {
+            GizmoDrag::Move => {
+                // Follow the mouse along the arrow as it appears on screen.
+                let length = GIZMO_ARROW_LENGTH * model_size;
+                let centre = project_to_window(mvp, &plane.centre(), window);
+                let tip =
+                    project_to_window(mvp, &(plane.centre() + plane.normal() * length), window);
+                if let Some(arrow) = centre.zip(tip).map(|(centre, tip)| tip - centre) {
+                    if arrow.norm() > 1.0 {
+                        plane.offset += length * movement.dot(&arrow) / arrow.norm_squared();
+                    }
+                }
+            }
+            GizmoDrag::Rotate => {
+                plane.azimuth = (plane.azimuth + movement.x * ROTATE_SPEED + 540.0) % 360.0 - 180.0;
+                plane.elevation = (plane.elevation - movement.y * ROTATE_SPEED).clamp(-90.0, 90.0);
+            }
+        }
+    }
+}
+
+/// Gets the distance from a point to a line segment.
+fn distance_to_segment(point: Point2<f32>, a: Point2<f32>, b: Point2<f32>) -> f32 {
+    let segment = b - a;
+    let t = match segment.norm_squared() {
+        length if length > 0.0 => ((point - a).dot(&segment) / length).clamp(0.0, 1.0),
+        _ => 0.0,
+    };
+    (a + segment * t - point).norm()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plane_equation() {
+        let plane = ClipPlane {
+            azimuth: 90.0,
+            offset: 2.0,
+            ..Default::default()
+        };
+        assert!((plane.normal() - Vector3::x()).norm() < 1e-6);
+        // Points beyond the plane along the normal are cut away.
+        let distance = |x: f32| {
+            plane
+                .equation()
+                .dot(&Point3::new(x, 5.0, -3.0).to_homogeneous())
+        };
+        assert!((distance(0.0) - 2.0).abs() < 1e-6);
+        assert!((distance(3.0) + 1.0).abs() < 1e-6);
+
+        let clipping = Clipping {
+            planes: vec![
+                plane,
+                ClipPlane {
+                    enabled: false,
+                    ..plane
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(clipping.equations().len(), 1);
+    }
+
+    #[test]
+    fn gizmo_grab_and_drag() {
+        let mut clipping = Clipping {
+            planes: vec![ClipPlane::default()],
+            selected: Some(0),
+            ..Default::default()
+        };
+        // Looking at the plane from the side, with the normal pointing right on screen.
+        let window = (800, 600);
+        let view = na::Isometry3::look_at_rh(
+            &Point3::new(-10.0, 0.0, 0.0),
+            &Point3::origin(),
+            &Vector3::y(),
+        );
+        let projection = na::Perspective3::new(800.0 / 600.0, 0.8, 0.1, 100.0);
+        let mvp = projection.into_inner() * view.to_homogeneous();
+        let model_size = 10.0;
+        let arrow_tip = Point3::new(0.0, 0.0, GIZMO_ARROW_LENGTH * model_size);
+        let tip = project_to_window(&mvp, &arrow_tip, window).unwrap();
+        assert!(tip.x > 400.0);
+
+        let cursor = (tip.x as i32 - 10, tip.y as i32);
+        assert_eq!(
+            clipping.grab(model_size, &mvp, window, cursor),
+            Some(GizmoDrag::Move)
+        );
+        assert_eq!(clipping.grab(model_size, &mvp, window, (100, 300)), None);
+        let edge = project_to_window(&mvp, &Point3::new(0.0, 3.0, 0.0), window).unwrap();
+        assert_eq!(
+            clipping.grab(model_size, &mvp, window, (edge.x as i32, edge.y as i32)),
+            Some(GizmoDrag::Rotate)
+        );
+
+        // Dragging the arrow by its on-screen length moves the plane by its length.
+        let arrow = (tip.x - 400.0).round() as i32;
+        clipping.drag(GizmoDrag::Move, model_size, &mvp, window, (arrow, 0));
+        assert!((clipping.planes[0].offset - GIZMO_ARROW_LENGTH * model_size).abs() < 0.05);
+        clipping.drag(GizmoDrag::Rotate, model_size, &mvp, window, (20, 200));
+        assert_eq!(clipping.planes[0].azimuth, 10.0);
+        assert_eq!(clipping.planes[0].elevation, -90.0);
+    }
+}
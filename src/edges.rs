@@ -0,0 +1,117 @@
+//! Contains [Edges], the pass outlining silhouettes and creases found in the [GBuffer], for
+//! illustrative rendering.
+
+use crate::{
+    gbuffer::GBuffer,
+    render_gl::{self, buffer},
+    resources::Resources,
+};
+use anyhow::Result;
+use nalgebra as na;
+
+const SHADER_PATH: &str = "shaders/edges";
+const SHADER_NAME: &str = "edges";
+/// Widest line that can be drawn, in pixels. Every pixel searches this far for edges.
+pub const MAX_EDGE_WIDTH: f32 = 8.0;
+/// Distance between surfaces, relative to the model size, beyond which they are outlined
+/// separately where one passes in front of the other.
+const SEPARATION: f32 = 0.005;
+
+/// Settings of the pass, taken from the shader attributes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EdgeSettings {
+    /// Line width in pixels.
+    pub width: f32,
+    pub color: na::Vector3<f32>,
+    /// Smallest angle between neighbouring normals drawn as a crease, in degrees.
+    pub crease_angle: f32,
+    /// How much thinner and lighter the furthest lines are than the closest ones, from 0 to 1.
+    pub depth_scaling: f32,
+}
+
+/// The pass drawing the edges over the rendered model.
+pub struct Edges {
+    program: render_gl::Program,
+    /// Empty vertex array for drawing the triangle covering the viewport, whose corners are
+    /// generated in the vertex shader.
+    vao: buffer::VertexArray,
+}
+
+impl Edges {
+    /// Sets up [Edges], compiling shaders.
+    pub fn new(res: &Resources) -> Result<Self> {
+        Ok(Self {
+            program: render_gl::Program::from_res(res, SHADER_PATH)?,
+            vao: buffer::VertexArray::new(),
+        })
+    }
+
+    /// Draws the edges in the G-buffer over the viewport in use, blended with what is drawn
+    /// there. `projection_matrix` is the one the scene was drawn into the G-buffer with, and
+    /// `depth_range` the distances from the camera the lines are scaled between.
+    ///
+    /// ### Safety
+    ///
+    /// Requires a current OpenGL context. This function should only be called from
+    /// [crate::model::Model::render].
+    pub unsafe fn render(
+        &self,
+        gbuffer: &GBuffer,
+        projection_matrix: &na::Matrix4<f32>,
+        camera_position: na::Vector3<f32>,
+        model_size: f32,
+        depth_range: (f32, f32),
+        settings: EdgeSettings,
+    ) {
+        let inverse = projection_matrix
+            .try_inverse()
+            .unwrap_or_else(na::Matrix4::identity);
+        self.program.set_used();
+        self.program
+            .set_uniform_matrix4("inverse_projection_matrix", &inverse);
+        self.program
+            .set_uniform_3f_na("camera_position", camera_position);
+        self.program.set_uniform_3f_na("edge_color", settings.color);
+        self.program
+            .set_uniform_f("edge_width", settings.width.clamp(0.0, MAX_EDGE_WIDTH));
+        self.program
+            .set_uniform_f("crease_cosine", settings.crease_angle.to_radians().cos());
+        self.program
+            .set_uniform_f("depth_threshold", SEPARATION * model_size);
+        self.program
+            .set_uniform_f("depth_scaling", settings.depth_scaling);
+        self.program.set_uniform_f("near_plane", depth_range.0);
+        self.program.set_uniform_f("far_plane", depth_range.1);
+
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Disable(gl::CULL_FACE);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gbuffer.bind_to(gl::TEXTURE0, gl::TEXTURE1);
+        self.vao.bind();
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        self.vao.unbind();
+        for unit in [gl::TEXTURE1, gl::TEXTURE0] {
+            gl::ActiveTexture(unit);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        gl::Disable(gl::BLEND);
+        gl::Enable(gl::DEPTH_TEST);
+    }
+
+    /// Check if the shader has been updated.
+    pub fn check_shader_update(&mut self, path: &std::path::Path, res: &Resources) -> bool {
+        let path = path.file_stem().map(|p| p.to_string_lossy().to_string());
+        if path == Some(SHADER_NAME.to_string()) {
+            match render_gl::Program::from_res(res, SHADER_PATH) {
+                Ok(program) => {
+                    self.program.unset_used();
+                    self.program = program;
+                    return true;
+                }
+                Err(e) => eprintln!("Shader reload error: {}", e),
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,126 @@
+//! Contains [GBuffer], the normals and depth of the scene as seen by the camera, which the
+//! screen-space passes, such as ambient occlusion, work from.
+
+use crate::{
+    render_gl::{
+        self,
+        buffer::{FrameBuffer, Texture},
+    },
+    resources::Resources,
+};
+use anyhow::Result;
+use std::cell::Cell;
+
+const SHADER_PATH: &str = "shaders/gbuffer";
+const SHADER_NAME: &str = "gbuffer";
+
+/// Textures of the normals and depth of the scene, at the size of the viewport.
+pub struct GBuffer {
+    program: render_gl::Program,
+    /// Normals of the scene, with an alpha of zero where nothing was drawn.
+    normal_map: Texture,
+    depth_map: Texture,
+    fbo: FrameBuffer,
+    /// Size the textures were last allocated for.
+    size: Cell<(u32, u32)>,
+}
+
+impl GBuffer {
+    /// Sets up [GBuffer], compiling shaders and initializing textures and the framebuffer.
+    pub fn new(res: &Resources) -> Result<Self> {
+        let value = Self {
+            program: render_gl::Program::from_res(res, SHADER_PATH)?,
+            normal_map: Texture::new(gl::TEXTURE0),
+            depth_map: Texture::new(gl::TEXTURE0),
+            fbo: FrameBuffer::new(),
+            size: Cell::new((0, 0)),
+        };
+        value.resize((1, 1));
+        Ok(value)
+    }
+
+    /// Allocates the textures for a viewport of the given size, if they are of another size.
+    fn resize(&self, size: (u32, u32)) {
+        if self.size.get() == size {
+            return;
+        }
+        self.size.set(size);
+        let dimensions = (size.0.max(1) as i32, size.1.max(1) as i32);
+        self.normal_map.load_texture(
+            dimensions,
+            None,
+            gl::RGBA16F as gl::types::GLint,
+            gl::RGBA,
+            gl::FLOAT,
+            false,
+        );
+        self.normal_map.unbind();
+        self.depth_map.load_texture(
+            dimensions,
+            None,
+            gl::DEPTH_COMPONENT24 as gl::types::GLint,
+            gl::DEPTH_COMPONENT,
+            gl::FLOAT,
+            false,
+        );
+        // Points beyond the edges of the screen count as background.
+        self.depth_map.set_border_color(&[1.0, 1.0, 1.0, 1.0]);
+        self.depth_map.unbind();
+
+        self.fbo
+            .bind_texture(gl::COLOR_ATTACHMENT0, &self.normal_map);
+        self.fbo.bind_texture(gl::DEPTH_ATTACHMENT, &self.depth_map);
+        self.fbo
+            .set_type(gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT0);
+        self.fbo.unbind();
+    }
+
+    /// Prepares the textures for a viewport of the given size, clears them and binds them, and
+    /// gets the program the scene should then be drawn with. The caller sets its
+    /// `projection_matrix`, `model_matrix` and clipping plane uniforms.
+    ///
+    /// ### Safety
+    ///
+    /// Requires a current OpenGL context. This function should only be called from
+    /// [crate::model::Model::render].
+    pub unsafe fn begin(&self, size: (u32, u32)) -> &render_gl::Program {
+        self.resize(size);
+        self.fbo.bind();
+        gl::Viewport(0, 0, size.0 as i32, size.1 as i32);
+        // Cleared without changing the clear colour, which belongs to the window.
+        gl::ClearBufferfv(gl::COLOR, 0, [0.0; 4].as_ptr());
+        gl::ClearBufferfv(gl::DEPTH, 0, &1.0);
+        gl::Disable(gl::CULL_FACE);
+        gl::Disable(gl::BLEND);
+        gl::Enable(gl::DEPTH_TEST);
+        gl::DepthFunc(gl::LESS);
+        self.program.set_used();
+        &self.program
+    }
+
+    /// Binds the normal and depth textures to the given texture units.
+    ///
+    /// ### Safety
+    ///
+    /// Requires a current OpenGL context.
+    pub unsafe fn bind_to(&self, normal_unit: gl::types::GLenum, depth_unit: gl::types::GLenum) {
+        self.normal_map.bind_to(normal_unit);
+        self.depth_map.bind_to(depth_unit);
+    }
+
+    /// Check if the shader has been updated.
+    pub fn check_shader_update(&mut self, path: &std::path::Path, res: &Resources) -> bool {
+        let path = path.file_stem().map(|p| p.to_string_lossy().to_string());
+        if path == Some(SHADER_NAME.to_string()) {
+            match render_gl::Program::from_res(res, SHADER_PATH) {
+                Ok(program) => {
+                    self.program.unset_used();
+                    self.program = program;
+                    return true;
+                }
+                Err(e) => eprintln!("Shader reload error: {}", e),
+            }
+        }
+        false
+    }
+}
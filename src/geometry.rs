@@ -1,8 +1,54 @@
 //! Geometry module containing helper functions for calculating some intersections.
 
-use na::Vector3;
+use na::{Point3, Vector3};
 use nalgebra as na;
 
+/// A half-line starting at `origin`, used to find what is under the cursor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    /// Unit direction of the ray.
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Creates a ray from the near plane to the far plane at the given normalized device
+    /// coordinates, using the inverse of a model-view-projection matrix. Fails if the matrix is
+    /// not invertible.
+    pub fn unproject(mvp: &na::Matrix4<f32>, x: f32, y: f32) -> Option<Ray> {
+        let inverse = mvp.try_inverse()?;
+        let near = inverse.transform_point(&Point3::new(x, y, -1.0));
+        let far = inverse.transform_point(&Point3::new(x, y, 1.0));
+        Some(Ray {
+            origin: near,
+            direction: (far - near).try_normalize(f32::EPSILON)?,
+        })
+    }
+
+    /// Gets the point at the given distance along the ray.
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+}
+
+/// Projects a point to pixel coordinates in a window of the given size, with y pointing down.
+/// Returns none for points behind the camera.
+pub fn project_to_window(
+    mvp: &na::Matrix4<f32>,
+    point: &Point3<f32>,
+    window: (u32, u32),
+) -> Option<na::Point2<f32>> {
+    let clip = mvp * point.to_homogeneous();
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.xy() / clip.w;
+    Some(na::Point2::new(
+        (ndc.x + 1.0) / 2.0 * window.0 as f32,
+        (1.0 - ndc.y) / 2.0 * window.1 as f32,
+    ))
+}
+
 /// Finds the intersection of the given line and box, both centered at origin.
 /// The order of the two intersections are undefined.
 /// Providing zero or abnormal float values will make the function return zero
@@ -29,10 +75,7 @@ pub fn intersect_box_and_line(line_dir: Vector3<f32>, box_size: Vector3<f32>) ->
         intersections.push(intersection);
     }
 
-    intersections = intersections
-        .into_iter()
-        .filter(|&vec| vec.norm().is_finite())
-        .collect();
+    intersections.retain(|&vec| vec.norm().is_finite());
     intersections.sort_unstable_by(|&a, &b| a.norm().partial_cmp(&b.norm()).unwrap());
 
     [intersections[0], intersections[1]]
@@ -53,6 +96,33 @@ pub fn intersect_plane_and_line(
     ray_point - ray_vector.scale(prod3)
 }
 
+/// Finds the distance along the ray to where it hits the triangle, from either side, using the
+/// Möller–Trumbore algorithm. Hits behind the ray origin are ignored.
+pub fn intersect_ray_and_triangle(ray: &Ray, triangle: &[Point3<f32>; 3]) -> Option<f32> {
+    let edge1 = triangle[1] - triangle[0];
+    let edge2 = triangle[2] - triangle[0];
+    let p = ray.direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+    // The ray is parallel to the triangle, or the triangle is degenerate.
+    if determinant.abs() < f32::EPSILON * edge1.norm() * edge2.norm() {
+        return None;
+    }
+
+    let inverse_determinant = 1.0 / determinant;
+    let s = ray.origin - triangle[0];
+    let u = s.dot(&p) * inverse_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&edge1);
+    let v = ray.direction.dot(&q) * inverse_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = edge2.dot(&q) * inverse_determinant;
+    (distance >= 0.0).then_some(distance)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,6 +151,55 @@ mod tests {
         assert_eq!(result, expect);
     }
 
+    fn ray(origin: [f32; 3], direction: [f32; 3]) -> Ray {
+        Ray {
+            origin: Point3::from(origin),
+            direction: Vector3::from(direction).normalize(),
+        }
+    }
+
+    fn triangle() -> [Point3<f32>; 3] {
+        [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+        ]
+    }
+
+    #[test]
+    fn ray_hits_triangle_from_both_sides() {
+        let front =
+            intersect_ray_and_triangle(&ray([0.5, 0.5, 3.0], [0.0, 0.0, -1.0]), &triangle());
+        assert_eq!(front, Some(3.0));
+        let back = intersect_ray_and_triangle(&ray([0.5, 0.5, -1.0], [0.0, 0.0, 1.0]), &triangle());
+        assert_eq!(back, Some(1.0));
+        let slanted = ray([0.0, 0.0, 1.0], [1.0, 1.0, -1.0]);
+        let distance = intersect_ray_and_triangle(&slanted, &triangle()).unwrap();
+        assert!((slanted.at(distance) - Point3::new(1.0, 1.0, 0.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn ray_misses_triangle() {
+        // Outside the triangle, behind the ray, and parallel to it.
+        let outside = ray([1.5, 1.5, 1.0], [0.0, 0.0, -1.0]);
+        assert_eq!(intersect_ray_and_triangle(&outside, &triangle()), None);
+        let behind = ray([0.5, 0.5, 1.0], [0.0, 0.0, 1.0]);
+        assert_eq!(intersect_ray_and_triangle(&behind, &triangle()), None);
+        let parallel = ray([-1.0, 0.5, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(intersect_ray_and_triangle(&parallel, &triangle()), None);
+    }
+
+    #[test]
+    fn unproject_centre_of_view() {
+        let eye = Point3::new(0.0, 0.0, 10.0);
+        let view = na::Isometry3::look_at_rh(&eye, &Point3::origin(), &Vector3::y());
+        let projection = na::Perspective3::new(1.5, 0.8, 0.1, 1000.0);
+        let mvp = projection.into_inner() * view.to_homogeneous();
+        let ray = Ray::unproject(&mvp, 0.0, 0.0).unwrap();
+        assert!((ray.origin - Point3::new(0.0, 0.0, 9.9)).norm() < 1e-3);
+        assert!((ray.direction - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-5);
+    }
+
     #[test]
     fn handle_nan() {
         let ray_vector = Vector3::new(f32::NAN, f32::NAN, f32::NAN);
@@ -0,0 +1,122 @@
+//! Headless rendering through an offscreen EGL context, for batch rendering without a window or
+//! display server. With Mesa, this also works without a GPU through its software rasterizer.
+
+use crate::{
+    camera::Camera, cli::RenderArgs, model::Model, offscreen, resources, resources::Resources,
+};
+use anyhow::{anyhow, Context, Result};
+use khronos_egl as egl;
+use nalgebra as na;
+
+/// Platform for rendering without any window system, provided by Mesa.
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+/// An OpenGL context without any window or surface, which can only render into framebuffers.
+pub struct HeadlessContext {
+    egl: egl::DynamicInstance<egl::EGL1_5>,
+    display: egl::Display,
+    context: egl::Context,
+}
+
+impl HeadlessContext {
+    /// Creates an OpenGL 4.1 core context, makes it current and loads the OpenGL functions.
+    pub fn new() -> Result<Self> {
+        // Safety: loading libEGL runs its initialization code, which is trusted.
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required() }
+            .map_err(|e| anyhow!("Failed to load libEGL: {}", e))?;
+
+        // Prefer the surfaceless platform, falling back to the default display.
+        // Safety: the default display is always a valid native display.
+        let display = unsafe {
+            egl.get_platform_display(
+                PLATFORM_SURFACELESS_MESA,
+                egl::DEFAULT_DISPLAY,
+                &[egl::ATTRIB_NONE],
+            )
+            .ok()
+            .or_else(|| egl.get_display(egl::DEFAULT_DISPLAY))
+        }
+        .context("No EGL display available.")?;
+        egl.initialize(display)?;
+        egl.bind_api(egl::OPENGL_API)?;
+
+        // Nothing is rendered to a surface, so configs are not limited to window surfaces.
+        let attributes = [
+            egl::RENDERABLE_TYPE,
+            egl::OPENGL_BIT,
+            egl::SURFACE_TYPE,
+            egl::DONT_CARE,
+            egl::NONE,
+        ];
+        let config = egl
+            .choose_first_config(display, &attributes)?
+            .context("No EGL config supports OpenGL.")?;
+        let context = egl.create_context(
+            display,
+            config,
+            None,
+            &[
+                egl::CONTEXT_MAJOR_VERSION,
+                4,
+                egl::CONTEXT_MINOR_VERSION,
+                1,
+                egl::CONTEXT_OPENGL_PROFILE_MASK,
+                egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                egl::NONE,
+            ],
+        )?;
+        egl.make_current(display, None, None, Some(context))?;
+
+        gl::load_with(|s| {
+            egl.get_proc_address(s)
+                .map_or(std::ptr::null(), |f| f as *const std::os::raw::c_void)
+        });
+
+        Ok(Self {
+            egl,
+            display,
+            context,
+        })
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        let _ = self.egl.make_current(self.display, None, None, None);
+        let _ = self.egl.destroy_context(self.display, self.context);
+        let _ = self.egl.terminate(self.display);
+    }
+}
+
+/// Renders a model to a PNG image as described by the command-line arguments.
+pub fn render(res: &Resources, args: &RenderArgs) -> Result<()> {
+    let _context = HeadlessContext::new().context("Failed to create headless OpenGL context.")?;
+
+    let meshes = resources::load_model_file(&args.model).context("Failed to load model.")?;
+    let mut model = Model::from_meshes(res, resources::file_stem(&args.model), meshes)?;
+
+    let mut camera = Camera::new();
+    let view = &args.view;
+    camera.set_orientation(view.azimuth.to_radians(), view.elevation.to_radians());
+    camera.set_dist(
+        view.distance
+            .unwrap_or_else(|| model.get_size().magnitude() * 1.2),
+    );
+
+    let mut attributes = model.get_attributes().clone();
+    view.preset.apply(&mut attributes);
+    let c = camera.position();
+    attributes.camera_position = na::Vector3::new(c[0], c[1], c[2]);
+    model.set_attributes(attributes);
+    // Only a single frame is rendered, so every attribute is passed to the shaders up front.
+    model.reset_all_attributes();
+
+    offscreen::render_to_png(
+        &args.output,
+        (args.width, args.height),
+        &mut model,
+        &camera,
+        view.background,
+        None,
+    )
+}
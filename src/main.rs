@@ -1,20 +1,44 @@
 #![doc = include_str!("../README.md")]
 #![allow(clippy::missing_safety_doc)]
 
+mod ambient_occlusion;
+mod bvh;
 mod camera;
+mod cli;
+mod clipping;
+mod edges;
+mod gbuffer;
 mod geometry;
+mod headless;
+mod measurement;
+mod mesh;
 mod model;
+mod offscreen;
+mod overlay;
+mod preset;
 pub mod render_gl;
 pub mod resources;
+mod section;
+mod split_view;
+mod study;
 mod texture_tester;
 mod ui;
+mod volume;
 
+use clap::Parser;
 use nalgebra as na;
-use sdl2::event::Event;
+use overlay::LineOverlay;
+use sdl2::{event::Event, keyboard::Mod, mouse::MouseButton};
 use std::path::Path;
 use texture_tester::TextureTester;
 
-use crate::{model::Model, resources::Resources, ui::UI};
+use crate::{
+    camera::DragAction,
+    model::Model,
+    resources::Resources,
+    ui::UI,
+    volume::{Extraction, IsosurfaceExtractor, Label},
+};
 
 #[cfg(debug_assertions)]
 #[cfg(target_os = "linux")]
@@ -25,10 +49,36 @@ const ASSETS_PATH: &str = "..\\..\\assets";
 #[cfg(not(debug_assertions))]
 const ASSETS_PATH: &str = "assets";
 const DEFAULT_MODEL_PATH: &str = "model.obj";
+/// Pixels the mouse may move between pressing and releasing a button for it to count as a click.
+const CLICK_TOLERANCE: i32 = 4;
+/// Size of the markers on points picked for a measurement, relative to the model size.
+const MEASUREMENT_MARKER_SIZE: f32 = 0.02;
 
 fn main() {
-    let res =
-        Resources::from_relative_exe_path(Path::new(&ASSETS_PATH)).expect("Failed to find assets");
+    let args = cli::Cli::parse();
+    let res = match &args.assets {
+        Some(path) => Resources::from_path(path),
+        None => Resources::from_relative_exe_path(Path::new(&ASSETS_PATH)),
+    }
+    .expect("Failed to find assets");
+
+    match &args.command {
+        Some(cli::Command::Render(render_args)) => {
+            if let Err(e) = headless::render(&res, render_args) {
+                eprintln!("Rendering failed: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Study(study_args)) => {
+            if let Err(e) = study::run(&res, study_args) {
+                eprintln!("Study failed: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
 
     let sdl = sdl2::init().unwrap();
     let video_subsystem = sdl.video().unwrap();
@@ -38,55 +88,104 @@ fn main() {
     gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
     gl_attr.set_context_version(4, 1);
 
-    let window = video_subsystem
-        .window("IVRIGST", 1200, 800)
-        .opengl()
-        .resizable()
-        .build()
-        .unwrap();
+    let window_args = args.window;
+    let mut window_builder =
+        video_subsystem.window("IVRIGST", window_args.width, window_args.height);
+    window_builder.opengl().resizable();
+    if window_args.fullscreen {
+        window_builder.fullscreen_desktop();
+    }
+    let window = window_builder.build().unwrap();
 
     window.gl_create_context().unwrap();
     gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
 
-    let mut model = Model::new(&res, DEFAULT_MODEL_PATH).ok();
-    let mut ui = UI::new(&res).expect("Failed to set up UI.");
+    // Models given on the command line may be anywhere, so they are opened by absolute path.
+    let model_file = match &window_args.model {
+        Some(path) => std::path::absolute(path)
+            .expect("Failed to find model path")
+            .to_string_lossy()
+            .into_owned(),
+        None => DEFAULT_MODEL_PATH.to_owned(),
+    };
+    // Volumes are loaded in the main loop, as their surfaces are extracted in the background.
+    let mut model = match res.is_volume(&model_file) {
+        true => None,
+        false => Model::new(&res, &model_file)
+            .map_err(|e| eprintln!("Failed to load '{}': {:?}", model_file, e))
+            .ok(),
+    };
+    let mut ui = UI::new(&res, window_args.view.preset).expect("Failed to set up UI.");
     if let Some(model) = model.as_mut() {
         ui.apply_preset(model);
+        if window_args.model.is_some() {
+            ui.add_recent_file(&res.path_of(&model_file));
+        }
     }
 
     // set up shared state for window
     let mut viewport =
         render_gl::Viewport::for_window(window.size().0 as i32, window.size().1 as i32);
     viewport.set_used();
-    let mut color_buffer = render_gl::ColorBuffer::from_color(na::Vector3::new(0.3, 0.3, 0.5));
+    let mut color_buffer = render_gl::ColorBuffer::from_color(window_args.view.background);
 
     // Camera and projection
     let model_isometry = na::Isometry3::new(na::Vector3::zeros(), na::zero());
     let mut camera = camera::Camera::new();
-    camera.set_dist(
-        model
-            .as_ref()
-            .map(|m| m.get_size().magnitude() * 1.2)
-            .unwrap_or(100.0),
+    camera.set_orientation(
+        window_args.view.azimuth.to_radians(),
+        window_args.view.elevation.to_radians(),
     );
+    // The requested distance only applies to the first model, later ones are fit to the view.
+    let mut initial_distance = window_args.view.distance;
+    camera.set_dist(match model.as_ref() {
+        Some(model) => initial_distance
+            .take()
+            .unwrap_or(model.get_size().magnitude() * 1.2),
+        None => 100.0,
+    });
 
     render_gl::check_gl_error();
 
     let time = std::time::Instant::now();
     let mut texture_tester = TextureTester::new(&res).expect("Failed to set up texture tester.");
+    let mut line_overlay = LineOverlay::new(&res).expect("Failed to set up line overlay.");
+    let mut gizmo_overlay = LineOverlay::new(&res).expect("Failed to set up line overlay.");
 
     let mut cursor: sdl2::mouse::Cursor;
+    let mut cursor_position = (0, 0);
+    // Where the left mouse button was pressed over the model, to tell clicks from drags.
+    let mut click_start = None;
+    // Part of the clipping plane gizmo being dragged, if any.
+    let mut gizmo_drag = None;
     let ctx = egui::Context::default();
     let mut mvp_needs_update = true;
     let mut current_model_file = match model {
-        Some(_) => DEFAULT_MODEL_PATH.to_owned(),
+        Some(_) => model_file.clone(),
         None => String::new(),
     };
     let mut ui_actions = ui::UiActions {
         show_debug: false,
-        file_to_load: current_model_file.clone(),
+        file_to_load: match res.is_volume(&model_file) {
+            true => model_file,
+            false => current_model_file.clone(),
+        },
+        file_to_add: None,
         clear_color: color_buffer.color.xyz(),
+        volume: None,
+        export: Default::default(),
+        camera: camera.view(),
+        camera_to_apply: None,
+        camera_mode: Default::default(),
+        picked: None,
+        measurements: Default::default(),
+        clipping: Default::default(),
+        split_view: Default::default(),
     };
+    // Models of the panes showing another model than the main one, with their files.
+    let mut pane_models: Vec<Option<(String, Model)>> = Vec::new();
+    let mut layout = ui_actions.split_view.layout;
+    let mut isosurface_extractor: Option<IsosurfaceExtractor> = None;
 
     let mut event_pump = sdl.event_pump().unwrap();
     'main: loop {
@@ -106,10 +205,62 @@ fn main() {
                         egui::vec2(w as f32, h as f32),
                     ));
                 }
+                Event::DropFile { filename, .. } => {
+                    ui_actions.file_to_load = filename;
+                }
                 Event::MouseButtonDown {
-                    mouse_btn, x, y, ..
+                    mouse_btn,
+                    clicks,
+                    x,
+                    y,
+                    ..
                 } => {
-                    camera.mousedown();
+                    // The model is only picked and cut in the first pane.
+                    let main_pane = ui_actions.split_view.rects(viewport.size())[0];
+                    let local = main_pane.local((x, y));
+                    // Dragging the gizmo of the selected clipping plane moves or rotates the
+                    // plane instead of the camera.
+                    gizmo_drag = model
+                        .as_ref()
+                        .filter(|_| mouse_btn == MouseButton::Left && !ctx.wants_pointer_input())
+                        .zip(local)
+                        .and_then(|(model, local)| {
+                            ui_actions.clipping.grab(
+                                model.get_size().magnitude(),
+                                &model.get_attributes().projection_matrix,
+                                main_pane.size(),
+                                local,
+                            )
+                        });
+                    if mouse_btn == MouseButton::Left
+                        && gizmo_drag.is_none()
+                        && local.is_some()
+                        && !ctx.wants_pointer_input()
+                    {
+                        click_start = Some((x, y));
+                    }
+                    // Double-clicking the model makes the camera rotate around the clicked point,
+                    // unless clicks are used for measuring.
+                    if clicks == 2
+                        && click_start.is_some()
+                        && ui_actions.measurements.active.is_none()
+                    {
+                        let hit = local
+                            .and_then(|local| camera.ray_through(local, main_pane.size()))
+                            .zip(model.as_ref())
+                            .and_then(|(ray, model)| model.pick(&ray));
+                        if let Some(hit) = hit {
+                            camera.set_target(hit.point);
+                            ui_actions.picked = Some(hit);
+                            mvp_needs_update = true;
+                        }
+                    }
+                    if gizmo_drag.is_none() {
+                        camera.mousedown(match mouse_btn {
+                            MouseButton::Right | MouseButton::Middle => DragAction::Pan,
+                            _ => DragAction::Rotate,
+                        });
+                    }
                     raw_input.events.push(egui::Event::PointerButton {
                         pos: egui::pos2(x as f32, y as f32),
                         button: ui::sdl2_to_egui_pointerbutton(mouse_btn),
@@ -121,6 +272,25 @@ fn main() {
                     mouse_btn, x, y, ..
                 } => {
                     camera.mouseup();
+                    gizmo_drag = None;
+                    // Clicking the model without dragging picks a point to measure from.
+                    let clicked = click_start
+                        .take()
+                        .filter(|_| mouse_btn == MouseButton::Left)
+                        .is_some_and(|(start_x, start_y): (i32, i32)| {
+                            (x - start_x).abs() + (y - start_y).abs() <= CLICK_TOLERANCE
+                        });
+                    if clicked && ui_actions.measurements.active.is_some() {
+                        let main_pane = ui_actions.split_view.rects(viewport.size())[0];
+                        let hit = main_pane
+                            .local((x, y))
+                            .and_then(|local| camera.ray_through(local, main_pane.size()))
+                            .zip(model.as_ref())
+                            .and_then(|(ray, model)| model.pick(&ray));
+                        if let Some(hit) = hit {
+                            ui_actions.measurements.add_point(hit.point);
+                        }
+                    }
                     raw_input.events.push(egui::Event::PointerButton {
                         pos: egui::pos2(x as f32, y as f32),
                         button: ui::sdl2_to_egui_pointerbutton(mouse_btn),
@@ -134,14 +304,29 @@ fn main() {
                     raw_input
                         .events
                         .push(egui::Event::PointerMoved(egui::pos2(x as f32, y as f32)));
+                    cursor_position = (x, y);
 
-                    if !ctx.wants_pointer_input() {
-                        let view_updated = camera.mousemove(xrel, yrel);
+                    if let (Some(drag), Some(model)) = (gizmo_drag, model.as_ref()) {
+                        ui_actions.clipping.drag(
+                            drag,
+                            model.get_size().magnitude(),
+                            &model.get_attributes().projection_matrix,
+                            ui_actions.split_view.rects(viewport.size())[0].size(),
+                            (xrel, yrel),
+                        );
+                    } else if !ctx.wants_pointer_input() {
+                        let view_updated = camera.mousemove(x, y, xrel, yrel, viewport.size());
                         mvp_needs_update = mvp_needs_update || view_updated;
                     }
                 }
                 Event::MouseWheel { y, .. } => {
-                    camera.mousewheel(y);
+                    // Zoom towards the cursor in whichever pane it is over.
+                    let (cursor, size) = ui_actions
+                        .split_view
+                        .pane_at(viewport.size(), cursor_position)
+                        .and_then(|(_, pane)| Some((pane.local(cursor_position)?, pane.size())))
+                        .unwrap_or((cursor_position, viewport.size()));
+                    camera.mousewheel(y, cursor, size);
                     raw_input.events.push(egui::Event::Scroll(egui::Vec2 {
                         x: 0.0,
                         y: y as f32,
@@ -153,6 +338,20 @@ fn main() {
                     keymod,
                     ..
                 } => {
+                    if keycode == sdl2::keyboard::Keycode::F12 && !ctx.wants_keyboard_input() {
+                        ui_actions.export.requested = true;
+                    }
+                    if keycode == sdl2::keyboard::Keycode::Z
+                        && keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD)
+                        && !ctx.wants_keyboard_input()
+                    {
+                        if let Some(model) = model.as_mut() {
+                            match keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                                true => ui.redo(model, &mut ui_actions),
+                                false => ui.undo(model, &mut ui_actions),
+                            }
+                        }
+                    }
                     if let Some(event) = ui::sdl2_to_egui_key(keycode, keymod, true) {
                         raw_input.events.push(event);
                     }
@@ -175,7 +374,17 @@ fn main() {
 
         // UI handling
         ctx.begin_frame(raw_input);
+        ui_actions.camera = camera.view();
         ui.build_ui(&ctx, &mut model, &mut ui_actions);
+        if let Some(view) = ui_actions.camera_to_apply.take() {
+            camera.set_view(view);
+            mvp_needs_update = true;
+        }
+        camera.set_mode(ui_actions.camera_mode);
+        if ui_actions.split_view.layout != layout {
+            layout = ui_actions.split_view.layout;
+            mvp_needs_update = true;
+        }
         let full_output = ctx.end_frame();
         let clipped_primitives: Vec<egui::ClippedPrimitive> = ctx.tessellate(full_output.shapes);
         ui.renderer.egui_texture_delta(full_output.textures_delta);
@@ -194,13 +403,14 @@ fn main() {
         }
 
         color_buffer.clear();
+        let panes = ui_actions.split_view.rects(viewport.size());
+        let main_viewport = panes[0].viewport(viewport.size().1);
 
         // Update camera if necessary.
-        if mvp_needs_update && model.is_some() {
-            let model = model.as_mut().unwrap();
+        if let Some(model) = model.as_mut().filter(|_| mvp_needs_update) {
             let mut attr = model.get_attributes().clone();
 
-            let aspect = viewport.size().0 as f32 / viewport.size().1 as f32;
+            let aspect = main_viewport.w as f32 / main_viewport.h.max(1) as f32;
             let model_view_projection = camera.construct_mvp(aspect, model_isometry);
             let c = camera.position();
             attr.camera_position = na::Vector3::new(c[0], c[1], c[2]);
@@ -215,47 +425,234 @@ fn main() {
             let mut attr = model.get_attributes().clone();
             attr.elapsed = elapsed.as_millis() as f32;
             model.set_attributes(attr);
-            model.render(&viewport);
+            model.set_clipping(&ui_actions.clipping);
+            model.render(&main_viewport);
+
+            let marker_size = model.get_size().magnitude() * MEASUREMENT_MARKER_SIZE;
+            line_overlay.set_lines(&ui_actions.measurements.lines(marker_size));
+            line_overlay.render(
+                &main_viewport,
+                &model.get_attributes().projection_matrix,
+                ui::MEASUREMENT_COLOR,
+            );
+            gizmo_overlay.set_lines(
+                &ui_actions
+                    .clipping
+                    .gizmo_lines(model.get_size().magnitude()),
+            );
+            gizmo_overlay.render(
+                &main_viewport,
+                &model.get_attributes().projection_matrix,
+                clipping::GIZMO_COLOR,
+            );
         }
 
-        // Render the UI
-        for egui::ClippedPrimitive {
-            clip_rect,
-            primitive,
-        } in clipped_primitives.into_iter()
-        {
-            let mesh = match primitive {
-                egui::epaint::Primitive::Mesh(mesh) => mesh,
-                _ => panic!("Custom paint callbacks and other primitives are not supported."),
+        // Render the other panes from the same camera, with their own settings and models.
+        let split_panes = ui_actions.split_view.panes.iter_mut();
+        pane_models.resize_with(split_panes.len(), || None);
+        for ((pane, pane_model), rect) in split_panes.zip(&mut pane_models).zip(&panes[1..]) {
+            if pane.file.as_ref() != pane_model.as_ref().map(|(file, _)| file) {
+                *pane_model = pane
+                    .file
+                    .as_ref()
+                    .and_then(|file| match Model::new(&res, file) {
+                        Ok(model) => Some((file.clone(), model)),
+                        Err(e) => {
+                            eprintln!("Failed to load '{}': {:?}", file, e);
+                            None
+                        }
+                    });
+                // Show the main model rather than retrying a file that failed.
+                if pane_model.is_none() {
+                    pane.file = None;
+                }
+            }
+            let target = match pane_model {
+                Some((_, pane_model)) => {
+                    pane_model.set_clipping(&ui_actions.clipping);
+                    pane_model
+                }
+                None => match model.as_mut() {
+                    Some(model) => model,
+                    None => continue,
+                },
             };
-            debug_assert!(mesh.is_valid());
-
-            ui.renderer
-                .render(&mesh.vertices, &mesh.indices, clip_rect, viewport.size());
+            split_view::render_pane(
+                target,
+                &pane.attributes,
+                &camera,
+                time.elapsed().as_millis() as f32,
+                &rect.viewport(viewport.size().1),
+            );
         }
+        viewport.set_used();
+
+        // Render the UI
+        ui.renderer
+            .render_primitives(&clipped_primitives, viewport.size());
 
         // Render debug textures if chosen
         if let Some(model) = model.as_mut() {
             if ui_actions.show_debug {
                 texture_tester.render(
                     &viewport,
-                    model.get_hatch_texture(),
-                    model.get_shadow_texture(),
+                    &[
+                        model.get_hatch_texture(),
+                        model.get_shadow_texture(),
+                        model.get_occlusion_texture(),
+                    ],
+                );
+            }
+        }
+
+        // Export the current view if requested, before the window is updated.
+        if ui_actions.export.requested {
+            ui_actions.export.requested = false;
+            if let Some(model) = model.as_mut() {
+                let export = &mut ui_actions.export;
+                let overlay = match export.include_ui {
+                    true => Some((&ui.renderer, &clipped_primitives[..], viewport.size())),
+                    false => None,
+                };
+                let result = offscreen::render_to_png(
+                    Path::new(&export.path),
+                    (export.width, export.height),
+                    model,
+                    &camera,
+                    color_buffer.color.xyz(),
+                    overlay,
                 );
+                export.status = match result {
+                    Ok(()) => format!("Saved '{}'.", export.path),
+                    Err(e) => format!("Export failed: {}", e),
+                };
+                eprintln!("{}", export.status);
+                viewport.set_used();
+                mvp_needs_update = true;
             }
         }
 
         // Check if model should be reloaded
         if ui_actions.file_to_load != current_model_file {
-            let mut path = ui_actions.file_to_load.clone();
-            path.push_str(".obj");
-            if let Ok(mut new_model) = Model::new(&res, &path) {
-                camera.set_dist(new_model.get_size().magnitude() * 1.2);
-                ui.apply_preset(&mut new_model);
-                model = Some(new_model);
+            if res.is_volume(&ui_actions.file_to_load) {
+                // The model is replaced once the first isosurface has been extracted.
+                match res.load_volume(&ui_actions.file_to_load) {
+                    Ok(volume) => {
+                        let (min, max) = volume.range();
+                        let name = resources::file_stem(Path::new(&ui_actions.file_to_load));
+                        let label_values = volume.label_values();
+                        let color_table = res
+                            .load_color_table(&ui_actions.file_to_load)
+                            .unwrap_or_else(|e| {
+                                eprintln!("Failed to load colour table: {}", e);
+                                Vec::new()
+                            });
+                        let labels = label_values
+                            .iter()
+                            .flatten()
+                            .map(|&value| Label::from_table(value, &color_table))
+                            .collect();
+                        isosurface_extractor = Some(IsosurfaceExtractor::new(volume, name, labels));
+                        ui_actions.volume = Some(ui::VolumeSettings {
+                            iso_value: min + (max - min) / 2.0,
+                            value_range: (min, max),
+                            extracting: true,
+                            is_label_map: label_values.is_some(),
+                            show_labels: label_values.is_some(),
+                        });
+                        model = None;
+                        current_model_file = ui_actions.file_to_load.clone();
+                        ui_actions.picked = None;
+                        ui_actions.measurements.clear();
+                    }
+                    Err(e) => eprintln!("Failed to load '{}': {:?}", ui_actions.file_to_load, e),
+                }
+            } else {
+                match Model::new(&res, &ui_actions.file_to_load) {
+                    Ok(mut new_model) => {
+                        camera.set_dist(
+                            initial_distance
+                                .take()
+                                .unwrap_or(new_model.get_size().magnitude() * 1.2),
+                        );
+                        camera.set_target(na::Point3::origin());
+                        ui.apply_preset(&mut new_model);
+                        model = Some(new_model);
+                        mvp_needs_update = true;
+                        current_model_file = ui_actions.file_to_load.clone();
+                        isosurface_extractor = None;
+                        ui_actions.picked = None;
+                        ui_actions.measurements.clear();
+                        ui_actions.volume = None;
+                    }
+                    Err(e) => eprintln!("Failed to load '{}': {:?}", ui_actions.file_to_load, e),
+                }
+            }
+
+            // Keep showing the current file if the new one failed, rather than retrying it.
+            if ui_actions.file_to_load == current_model_file {
+                ui.add_recent_file(&res.path_of(&current_model_file));
+            } else {
+                ui_actions.file_to_load = current_model_file.clone();
+            }
+        }
+
+        // Add a model to the scene, or open it if there is nothing to add it to.
+        if let Some(file) = ui_actions.file_to_add.take() {
+            match model.as_mut() {
+                Some(_) if res.is_volume(&file) => {
+                    eprintln!(
+                        "Failed to add '{}': volumes can only be opened alone.",
+                        file
+                    )
+                }
+                Some(model) => match model.add(&res, &file) {
+                    Ok(()) => {
+                        ui.add_recent_file(&res.path_of(&file));
+                        mvp_needs_update = true;
+                    }
+                    Err(e) => eprintln!("Failed to add '{}': {:?}", file, e),
+                },
+                None => ui_actions.file_to_load = file,
+            }
+        }
+
+        // Extract new surfaces if the volume settings changed, and show them when ready.
+        if let (Some(extractor), Some(volume)) =
+            (isosurface_extractor.as_mut(), ui_actions.volume.as_mut())
+        {
+            extractor.request(match volume.show_labels {
+                true => Extraction::Labels,
+                false => Extraction::Isosurface(volume.iso_value),
+            });
+            if let Some(meshes) = extractor.poll() {
+                match model.as_mut() {
+                    Some(model) => {
+                        if let Err(e) = model.set_meshes(meshes) {
+                            eprintln!("Isosurface error: {}", e);
+                        }
+                    }
+                    None => match Model::from_meshes(
+                        &res,
+                        resources::file_stem(Path::new(&current_model_file)),
+                        meshes,
+                    ) {
+                        Ok(mut new_model) => {
+                            camera.set_dist(
+                                initial_distance
+                                    .take()
+                                    .unwrap_or(new_model.get_size().magnitude() * 1.2),
+                            );
+                            camera.set_target(na::Point3::origin());
+                            ui.apply_preset(&mut new_model);
+                            model = Some(new_model);
+                        }
+                        Err(e) => eprintln!("Isosurface error: {}", e),
+                    },
+                }
                 mvp_needs_update = true;
-                current_model_file = ui_actions.file_to_load.clone();
             }
+            volume.extracting = extractor.is_extracting();
         }
 
         // Check the if clear color needs updating
@@ -271,7 +668,12 @@ fn main() {
             if let Some(model) = model.as_mut() {
                 model.check_shader_update(&path, &res);
             }
+            for (_, pane_model) in pane_models.iter_mut().flatten() {
+                pane_model.check_shader_update(&path, &res);
+            }
             texture_tester.check_shader_update(&path, &res);
+            line_overlay.check_shader_update(&path, &res);
+            gizmo_overlay.check_shader_update(&path, &res);
         }
     }
 }
@@ -0,0 +1,374 @@
+//! Contains [Measurements], the distances and angles measured between points picked on the model,
+//! and the [LengthUnit]s they are shown in.
+
+use anyhow::{Context, Result};
+use na::{Point3, Vector3};
+use nalgebra as na;
+use std::{fmt::Write, path::Path};
+
+/// Number of line segments the arc of an angle measurement is drawn with.
+const ARC_SEGMENTS: usize = 24;
+/// Radius of the arc of an angle measurement, relative to its shortest leg.
+const ARC_RADIUS: f32 = 0.3;
+
+/// A unit of length, either of the model coordinates or for showing measurements in.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum LengthUnit {
+    Micrometre,
+    #[default]
+    Millimetre,
+    Centimetre,
+    Metre,
+    Inch,
+}
+
+impl LengthUnit {
+    pub const ALL: [LengthUnit; 5] = [
+        LengthUnit::Micrometre,
+        LengthUnit::Millimetre,
+        LengthUnit::Centimetre,
+        LengthUnit::Metre,
+        LengthUnit::Inch,
+    ];
+
+    /// Gets the abbreviation of the unit, such as "mm".
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            LengthUnit::Micrometre => "µm",
+            LengthUnit::Millimetre => "mm",
+            LengthUnit::Centimetre => "cm",
+            LengthUnit::Metre => "m",
+            LengthUnit::Inch => "in",
+        }
+    }
+
+    /// Gets the length of the unit in millimetres.
+    fn millimetres(&self) -> f32 {
+        match self {
+            LengthUnit::Micrometre => 0.001,
+            LengthUnit::Millimetre => 1.0,
+            LengthUnit::Centimetre => 10.0,
+            LengthUnit::Metre => 1000.0,
+            LengthUnit::Inch => 25.4,
+        }
+    }
+}
+
+impl std::fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            LengthUnit::Micrometre => write!(f, "Micrometres"),
+            LengthUnit::Millimetre => write!(f, "Millimetres"),
+            LengthUnit::Centimetre => write!(f, "Centimetres"),
+            LengthUnit::Metre => write!(f, "Metres"),
+            LengthUnit::Inch => write!(f, "Inches"),
+        }
+    }
+}
+
+/// What is measured from the points picked next.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MeasurementKind {
+    /// Distance between two points.
+    Distance,
+    /// Angle at the second of three points.
+    Angle,
+}
+
+impl MeasurementKind {
+    /// Gets the number of points needed for a measurement.
+    fn points(&self) -> usize {
+        match self {
+            MeasurementKind::Distance => 2,
+            MeasurementKind::Angle => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for MeasurementKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            MeasurementKind::Distance => write!(f, "Distance"),
+            MeasurementKind::Angle => write!(f, "Angle"),
+        }
+    }
+}
+
+/// A distance or angle between points in model coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Measurement {
+    Distance([Point3<f32>; 2]),
+    /// Angle at the middle point.
+    Angle([Point3<f32>; 3]),
+}
+
+impl Measurement {
+    pub fn kind(&self) -> MeasurementKind {
+        match self {
+            Measurement::Distance(_) => MeasurementKind::Distance,
+            Measurement::Angle(_) => MeasurementKind::Angle,
+        }
+    }
+
+    pub fn points(&self) -> &[Point3<f32>] {
+        match self {
+            Measurement::Distance(points) => points,
+            Measurement::Angle(points) => points,
+        }
+    }
+
+    /// Gets the distance in model units, or the angle in degrees.
+    fn value(&self) -> f32 {
+        match self {
+            Measurement::Distance([a, b]) => (b - a).norm(),
+            Measurement::Angle([a, vertex, c]) => (a - vertex).angle(&(c - vertex)).to_degrees(),
+        }
+    }
+
+    /// Gets where the label of the measurement is shown.
+    pub fn label_position(&self) -> Point3<f32> {
+        match self {
+            Measurement::Distance([a, b]) => na::center(a, b),
+            Measurement::Angle([_, vertex, _]) => *vertex,
+        }
+    }
+
+    /// Gets the line segments showing the measurement: the legs, and an arc for angles.
+    fn lines(&self) -> Vec<[Point3<f32>; 2]> {
+        match self {
+            Measurement::Distance([a, b]) => vec![[*a, *b]],
+            Measurement::Angle([a, vertex, c]) => {
+                let mut lines = vec![[*a, *vertex], [*vertex, *c]];
+                let (first, second) = (a - vertex, c - vertex);
+                let radius = ARC_RADIUS * first.norm().min(second.norm());
+                let arc: Vec<Point3<f32>> = (0..=ARC_SEGMENTS)
+                    .filter_map(|i| {
+                        let t = i as f32 / ARC_SEGMENTS as f32;
+                        let direction = slerp(&first, &second, t)?;
+                        Some(vertex + direction * radius)
+                    })
+                    .collect();
+                lines.extend(arc.windows(2).map(|pair| [pair[0], pair[1]]));
+                lines
+            }
+        }
+    }
+}
+
+/// Interpolates the direction between two vectors, or returns none if either has no length.
+fn slerp(a: &Vector3<f32>, b: &Vector3<f32>, t: f32) -> Option<Vector3<f32>> {
+    let a = na::Unit::try_new(*a, f32::EPSILON)?;
+    let b = na::Unit::try_new(*b, f32::EPSILON)?;
+    // Opposite directions have no unique arc, so any perpendicular one is taken.
+    Some(match a.try_slerp(&b, t, 1.0e-6) {
+        Some(direction) => direction.into_inner(),
+        None => {
+            let axis = na::Unit::new_normalize(match a.cross(&Vector3::x()).norm() > 0.1 {
+                true => a.cross(&Vector3::x()),
+                false => a.cross(&Vector3::y()),
+            });
+            na::Rotation3::from_axis_angle(&axis, t * std::f32::consts::PI) * a.into_inner()
+        }
+    })
+}
+
+/// The measurements made on the current model, and the points picked for the next one.
+#[derive(Clone, Debug, Default)]
+pub struct Measurements {
+    /// What clicking the model measures, or none if clicking does not pick points.
+    pub active: Option<MeasurementKind>,
+    /// Unit of the model coordinates.
+    pub model_unit: LengthUnit,
+    /// Unit distances and coordinates are shown and exported in.
+    pub display_unit: LengthUnit,
+    pending: Vec<Point3<f32>>,
+    list: Vec<Measurement>,
+}
+
+impl Measurements {
+    /// Adds a picked point to the next measurement, which is finished once it has enough points.
+    pub fn add_point(&mut self, point: Point3<f32>) {
+        let Some(kind) = self.active else {
+            return;
+        };
+        self.pending.push(point);
+        if self.pending.len() >= kind.points() {
+            let points = std::mem::take(&mut self.pending);
+            self.list.push(match kind {
+                MeasurementKind::Distance => Measurement::Distance([points[0], points[1]]),
+                MeasurementKind::Angle => Measurement::Angle([points[0], points[1], points[2]]),
+            });
+        }
+    }
+
+    /// Forgets the points picked for the next measurement.
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.list.remove(index);
+    }
+
+    /// Forgets every measurement, for example when another model is loaded.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.list.clear();
+    }
+
+    pub fn list(&self) -> &[Measurement] {
+        &self.list
+    }
+
+    /// Converts a length in model units to the display unit.
+    fn convert(&self, length: f32) -> f32 {
+        length * self.model_unit.millimetres() / self.display_unit.millimetres()
+    }
+
+    /// Formats a length in model units in the display unit, such as "12.30 mm".
+    pub fn format_length(&self, length: f32) -> String {
+        format!("{:.2} {}", self.convert(length), self.display_unit.symbol())
+    }
+
+    /// Formats an area in square model units in the display unit, such as "4.50 mm²".
+    pub fn format_area(&self, area: f32) -> String {
+        let scale = self.convert(1.0);
+        format!(
+            "{:.2} {}²",
+            area * scale * scale,
+            self.display_unit.symbol()
+        )
+    }
+
+    /// Gets the value of a measurement with its unit, such as "12.30 mm" or "45.0°".
+    pub fn label(&self, measurement: &Measurement) -> String {
+        match measurement.kind() {
+            MeasurementKind::Distance => self.format_length(measurement.value()),
+            MeasurementKind::Angle => format!("{:.1}°", measurement.value()),
+        }
+    }
+
+    /// Gets the line segments showing every measurement, and the points picked so far with a
+    /// cross of the given size around each.
+    pub fn lines(&self, marker_size: f32) -> Vec<[Point3<f32>; 2]> {
+        let mut lines: Vec<_> = self.list.iter().flat_map(Measurement::lines).collect();
+        lines.extend(self.pending.windows(2).map(|pair| [pair[0], pair[1]]));
+        for point in &self.pending {
+            for axis in [Vector3::x(), Vector3::y(), Vector3::z()] {
+                let offset = axis * marker_size / 2.0;
+                lines.push([point - offset, point + offset]);
+            }
+        }
+        lines
+    }
+
+    /// Formats the measurements as CSV, with one row per measurement. Distances and coordinates
+    /// are in the display unit, and angles in degrees. Points are moved to the coordinates of the
+    /// model file with `to_file`, as the model is re-centred when loaded.
+    pub fn to_csv(&self, to_file: &na::Isometry3<f32>) -> String {
+        let mut csv = "index,type,value,unit,x1,y1,z1,x2,y2,z2,x3,y3,z3\n".to_owned();
+        for (index, measurement) in self.list.iter().enumerate() {
+            let (value, unit) = match measurement.kind() {
+                MeasurementKind::Distance => (
+                    self.convert(measurement.value()),
+                    self.display_unit.symbol(),
+                ),
+                MeasurementKind::Angle => (measurement.value(), "deg"),
+            };
+            let _ = write!(
+                csv,
+                "{},{},{},{}",
+                index + 1,
+                measurement.kind(),
+                value,
+                unit
+            );
+            for point in measurement.points() {
+                let point = to_file.transform_point(point).map(|c| self.convert(c));
+                let _ = write!(csv, ",{},{},{}", point.x, point.y, point.z);
+            }
+            if measurement.points().len() < 3 {
+                csv.push_str(",,,");
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Saves the measurements to a CSV file, in the coordinates of the model file.
+    pub fn save_csv(&self, path: &Path, to_file: &na::Isometry3<f32>) -> Result<()> {
+        std::fs::write(path, self.to_csv(to_file)).context("Failed to write measurements")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurements(kind: MeasurementKind, points: &[[f32; 3]]) -> Measurements {
+        let mut measurements = Measurements {
+            active: Some(kind),
+            ..Default::default()
+        };
+        for point in points {
+            measurements.add_point(Point3::from(*point));
+        }
+        measurements
+    }
+
+    #[test]
+    fn distance_and_angle() {
+        let distance = measurements(
+            MeasurementKind::Distance,
+            &[[1.0, 1.0, 1.0], [4.0, 5.0, 1.0], [0.0, 0.0, 0.0]],
+        );
+        assert_eq!(distance.list().len(), 1);
+        assert_eq!(distance.pending.len(), 1);
+        assert_eq!(distance.label(&distance.list()[0]), "5.00 mm");
+
+        let angle = measurements(
+            MeasurementKind::Angle,
+            &[[2.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 3.0]],
+        );
+        assert_eq!(angle.label(&angle.list()[0]), "90.0°");
+        assert_eq!(angle.list()[0].label_position(), Point3::origin());
+        // The arc ends at 0.3 times the shorter leg along both legs.
+        let lines = angle.list()[0].lines();
+        assert_eq!(lines.len(), 2 + ARC_SEGMENTS);
+        assert!((lines[2][0] - Point3::new(0.6, 0.0, 0.0)).norm() < 1e-5);
+        assert!((lines[lines.len() - 1][1] - Point3::new(0.0, 0.0, 0.6)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn units_converted() {
+        let mut distance = measurements(MeasurementKind::Distance, &[[0.0; 3], [0.0, 2.54, 0.0]]);
+        distance.model_unit = LengthUnit::Centimetre;
+        distance.display_unit = LengthUnit::Inch;
+        assert_eq!(distance.label(&distance.list()[0]), "1.00 in");
+        distance.display_unit = LengthUnit::Millimetre;
+        assert_eq!(distance.label(&distance.list()[0]), "25.40 mm");
+        assert_eq!(distance.format_area(2.0), "200.00 mm²");
+    }
+
+    #[test]
+    fn csv_export() {
+        let mut measured = measurements(MeasurementKind::Distance, &[[0.0; 3], [1.5, 0.0, 0.0]]);
+        measured.active = Some(MeasurementKind::Angle);
+        for point in [[1.0, 0.0, 0.0], [0.0; 3], [-1.0, 0.0, 0.0]] {
+            measured.add_point(Point3::from(point));
+        }
+        measured.display_unit = LengthUnit::Centimetre;
+        // The model was centred on (10, 0, -5) in its file.
+        let to_file = na::Isometry3::translation(10.0, 0.0, -5.0);
+        assert_eq!(
+            measured.to_csv(&to_file),
+            "index,type,value,unit,x1,y1,z1,x2,y2,z2,x3,y3,z3\n\
+             1,Distance,0.15,cm,1,0,-0.5,1.15,0,-0.5,,,\n\
+             2,Angle,180,deg,1.1,0,-0.5,1,0,-0.5,0.9,0,-0.5\n"
+        );
+        // Opposite legs still get an arc, perpendicular to them.
+        let arc_middle = measured.list()[1].lines()[2 + ARC_SEGMENTS / 2][0];
+        assert!((arc_middle.coords.norm() - 0.3).abs() < 1e-5);
+        assert!(arc_middle.x.abs() < 1e-5);
+    }
+}
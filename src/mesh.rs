@@ -0,0 +1,356 @@
+//! Contains the [Mesh] struct, an in-memory representation of a single object in a model file,
+//! independent of the file format it was loaded from, and the preparation steps needed before it
+//! can be rendered.
+
+use nalgebra as na;
+use std::collections::HashMap;
+
+/// Normals shorter than this are considered degenerate.
+const MIN_NORMAL_LENGTH: f32 = 1e-6;
+/// Generated normals closer than this are shared by the same vertex.
+const NORMAL_WELD_DISTANCE: f32 = 1e-4;
+
+/// A named per-vertex scalar value, such as wall shear stress or wall thickness.
+#[derive(Debug, Clone, Default)]
+pub struct ScalarField {
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+/// A single named object of a model file, with flattened vertex data ready for rendering.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    /// Name of the object, as given in the model file.
+    pub name: String,
+    /// Flattened 3 component vertex positions.
+    pub positions: Vec<f32>,
+    /// Flattened 3 component vertex normals.
+    pub normals: Vec<f32>,
+    /// Flattened 3 component vertex colors.
+    pub vertex_color: Vec<f32>,
+    /// Diffuse colour of the material assigned to the object, if any.
+    pub material_color: Option<[f32; 3]>,
+    /// Triangle indices into the vertex data.
+    pub indices: Vec<u32>,
+    /// Additional per-vertex scalar values, each with one value per vertex.
+    pub scalars: Vec<ScalarField>,
+}
+
+impl Mesh {
+    /// Converts an object loaded by [tobj], resolving its material to a diffuse colour. If the
+    /// object has no vertex colours, the material colour is used as vertex colour instead.
+    pub fn from_obj(model: tobj::Model, materials: &[tobj::Material]) -> Self {
+        let material_color = model
+            .mesh
+            .material_id
+            .and_then(|id| materials.get(id))
+            .map(|material| material.diffuse);
+
+        let mut vertex_color = model.mesh.vertex_color;
+        if vertex_color.is_empty() {
+            if let Some(color) = material_color {
+                let vertex_count = model.mesh.positions.len() / 3;
+                vertex_color = color.repeat(vertex_count);
+            }
+        }
+
+        Self {
+            name: model.name,
+            positions: model.mesh.positions,
+            normals: model.mesh.normals,
+            vertex_color,
+            material_color,
+            indices: model.mesh.indices,
+            scalars: Vec::new(),
+        }
+    }
+
+    /// Builds an indexed mesh from a list of triangle corners, welding corners with identical
+    /// positions into shared vertices.
+    pub fn from_triangle_soup(name: String, corners: &[[f32; 3]]) -> Self {
+        let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+        let mut positions = Vec::new();
+        let mut indices = Vec::with_capacity(corners.len());
+        for corner in corners {
+            let key = corner.map(f32::to_bits);
+            let index = *welded.entry(key).or_insert_with(|| {
+                positions.extend_from_slice(corner);
+                (positions.len() / 3 - 1) as u32
+            });
+            indices.push(index);
+        }
+
+        Self {
+            name,
+            positions,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    /// Prepares the mesh for rendering. Vertex colours are set to `default_color` if missing, and
+    /// smooth normals are generated if missing or degenerate. See [Mesh::generate_normals] for the
+    /// meaning of `crease_angle`.
+    pub fn prepare(&mut self, crease_angle: Option<f32>, default_color: [f32; 3]) {
+        if self.vertex_color.len() != self.positions.len() {
+            self.vertex_color = default_color.repeat(self.vertex_count());
+        }
+        if !self.has_valid_normals() {
+            self.generate_normals(crease_angle);
+        }
+    }
+
+    /// Number of vertices in the mesh.
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
+    }
+
+    /// Checks that every vertex has a normal of non-zero, finite length.
+    fn has_valid_normals(&self) -> bool {
+        self.normals.len() == self.positions.len()
+            && self.normals.chunks_exact(3).all(|n| {
+                let length = na::Vector3::new(n[0], n[1], n[2]).norm();
+                length.is_finite() && length > MIN_NORMAL_LENGTH
+            })
+    }
+
+    /// Gets the position of the vertex at the given index.
+    fn position(&self, index: u32) -> na::Vector3<f32> {
+        let i = index as usize * 3;
+        na::Vector3::new(
+            self.positions[i],
+            self.positions[i + 1],
+            self.positions[i + 2],
+        )
+    }
+
+    /// Replaces the normals of the mesh with smooth normals, weighting the normal of each face by
+    /// its area. If `crease_angle` is given, faces meeting at a larger angle (in radians) are not
+    /// smoothed together, and vertices along such creases are split.
+    pub fn generate_normals(&mut self, crease_angle: Option<f32>) {
+        // Unnormalized face normals, with length proportional to the area of the face.
+        let face_normals: Vec<na::Vector3<f32>> = self
+            .indices
+            .chunks_exact(3)
+            .map(|t| {
+                let (a, b, c) = (
+                    self.position(t[0]),
+                    self.position(t[1]),
+                    self.position(t[2]),
+                );
+                (b - a).cross(&(c - a))
+            })
+            .collect();
+
+        let vertex_count = self.vertex_count();
+        let mut vertex_faces = vec![Vec::new(); vertex_count];
+        for (face, triangle) in self.indices.chunks_exact(3).enumerate() {
+            for &index in triangle {
+                vertex_faces[index as usize].push(face);
+            }
+        }
+
+        let crease_cos = match crease_angle {
+            Some(angle) => angle.cos(),
+            None => {
+                self.normals = vertex_faces
+                    .iter()
+                    .flat_map(|faces| {
+                        let normal = faces.iter().map(|&face| face_normals[face]).sum();
+                        let normal = normalize_or_default(normal);
+                        [normal.x, normal.y, normal.z]
+                    })
+                    .collect();
+                return;
+            }
+        };
+
+        // With a crease angle, each corner of each face only smooths with the faces around its
+        // vertex that are close enough in direction. Corners ending up with different normals
+        // get their own copy of the vertex.
+        let unit_normals: Vec<Option<na::Vector3<f32>>> = face_normals
+            .iter()
+            .map(|normal| normal.try_normalize(0.0))
+            .collect();
+        let mut normals = vec![na::Vector3::z(); vertex_count];
+        let mut splits: Vec<Vec<(na::Vector3<f32>, u32)>> = vec![Vec::new(); vertex_count];
+        let triangle_count = self.indices.len() / 3;
+        for corner in 0..triangle_count * 3 {
+            let face = corner / 3;
+            let vertex = self.indices[corner] as usize;
+            let normal = vertex_faces[vertex]
+                .iter()
+                .filter(|&&other| match (unit_normals[face], unit_normals[other]) {
+                    (Some(a), Some(b)) => a.dot(&b) >= crease_cos,
+                    // Degenerate faces have no direction of their own, so smooth with all.
+                    (None, _) => true,
+                    (Some(_), None) => false,
+                })
+                .map(|&other| face_normals[other])
+                .sum();
+            let normal = normalize_or_default(normal);
+
+            let shared = splits[vertex]
+                .iter()
+                .find(|(existing, _)| (existing - normal).norm() < NORMAL_WELD_DISTANCE)
+                .map(|&(_, index)| index);
+            let index = match shared {
+                Some(index) => index,
+                None if splits[vertex].is_empty() => {
+                    normals[vertex] = normal;
+                    vertex as u32
+                }
+                None => {
+                    let range = vertex * 3..vertex * 3 + 3;
+                    self.positions.extend_from_within(range.clone());
+                    self.vertex_color.extend_from_within(range);
+                    for field in self.scalars.iter_mut() {
+                        field.values.push(field.values[vertex]);
+                    }
+                    normals.push(normal);
+                    (normals.len() - 1) as u32
+                }
+            };
+            splits[vertex].push((normal, index));
+            self.indices[corner] = index;
+        }
+
+        self.normals = normals.iter().flat_map(|n| [n.x, n.y, n.z]).collect();
+    }
+}
+
+/// Normalizes the given vector, or returns the unit Z vector if it has no length.
+fn normalize_or_default(vector: na::Vector3<f32>) -> na::Vector3<f32> {
+    vector
+        .try_normalize(MIN_NORMAL_LENGTH)
+        .unwrap_or_else(na::Vector3::z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two unit right triangles sharing the edge along the X axis, folded 90 degrees: the first
+    /// lies in the XY plane facing +Z, the second in the XZ plane facing +Y.
+    fn folded_quad() -> Mesh {
+        Mesh {
+            positions: vec![
+                0.0, 0.0, 0.0, //
+                1.0, 0.0, 0.0, //
+                0.0, 1.0, 0.0, //
+                0.0, 0.0, -1.0, //
+            ],
+            indices: vec![0, 1, 2, 0, 1, 3],
+            ..Default::default()
+        }
+    }
+
+    fn normal(mesh: &Mesh, index: u32) -> na::Vector3<f32> {
+        let i = index as usize * 3;
+        na::Vector3::new(mesh.normals[i], mesh.normals[i + 1], mesh.normals[i + 2])
+    }
+
+    fn assert_close(a: na::Vector3<f32>, b: na::Vector3<f32>) {
+        assert!((a - b).norm() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn single_triangle_normal() {
+        let mut mesh = Mesh {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        mesh.prepare(None, [1.0, 1.0, 1.0]);
+        for i in 0..3 {
+            assert_close(normal(&mesh, i), na::Vector3::z());
+        }
+    }
+
+    #[test]
+    fn smooth_normals_average_faces() {
+        let mut mesh = folded_quad();
+        mesh.prepare(None, [1.0, 1.0, 1.0]);
+        assert_eq!(mesh.vertex_count(), 4);
+        let shared = na::Vector3::new(0.0, 1.0, 1.0).normalize();
+        assert_close(normal(&mesh, 0), shared);
+        assert_close(normal(&mesh, 1), shared);
+        assert_close(normal(&mesh, 2), na::Vector3::z());
+        assert_close(normal(&mesh, 3), na::Vector3::y());
+    }
+
+    #[test]
+    fn smooth_normals_weighted_by_area() {
+        // Same fold, but the face in the XZ plane is four times larger.
+        let mut mesh = folded_quad();
+        mesh.positions.extend_from_slice(&[2.0, 0.0, 0.0]);
+        mesh.positions[11] = -4.0;
+        mesh.indices = vec![0, 1, 2, 0, 4, 3];
+        mesh.generate_normals(None);
+        let expected = na::Vector3::new(0.0, 8.0, 1.0).normalize();
+        assert_close(normal(&mesh, 0), expected);
+    }
+
+    #[test]
+    fn crease_angle_splits_vertices() {
+        let mut mesh = folded_quad();
+        mesh.prepare(Some(std::f32::consts::FRAC_PI_4), [1.0, 1.0, 1.0]);
+
+        // The two vertices on the crease are split, giving each face flat normals.
+        assert_eq!(mesh.vertex_count(), 6);
+        assert_eq!(mesh.vertex_color.len(), mesh.positions.len());
+        assert_eq!(mesh.normals.len(), mesh.positions.len());
+        for &index in &mesh.indices[0..3] {
+            assert_close(normal(&mesh, index), na::Vector3::z());
+        }
+        for &index in &mesh.indices[3..6] {
+            assert_close(normal(&mesh, index), na::Vector3::y());
+        }
+        assert_eq!(mesh.position(mesh.indices[3]), mesh.position(0));
+    }
+
+    #[test]
+    fn crease_angle_splits_scalars() {
+        let mut mesh = folded_quad();
+        mesh.scalars.push(ScalarField {
+            name: "thickness".to_owned(),
+            values: vec![1.0, 2.0, 3.0, 4.0],
+        });
+        mesh.prepare(Some(std::f32::consts::FRAC_PI_4), [1.0, 1.0, 1.0]);
+        assert_eq!(mesh.scalars[0].values, vec![1.0, 2.0, 3.0, 4.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn crease_angle_keeps_smooth_vertices_shared() {
+        let mut mesh = folded_quad();
+        mesh.prepare(Some(std::f32::consts::FRAC_PI_2 + 0.1), [1.0, 1.0, 1.0]);
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_close(
+            normal(&mesh, 0),
+            na::Vector3::new(0.0, 1.0, 1.0).normalize(),
+        );
+    }
+
+    #[test]
+    fn degenerate_normals_are_replaced() {
+        let mut mesh = folded_quad();
+        mesh.normals = vec![0.0; 12];
+        mesh.prepare(None, [1.0, 1.0, 1.0]);
+        assert_close(normal(&mesh, 2), na::Vector3::z());
+    }
+
+    #[test]
+    fn valid_normals_are_kept() {
+        let mut mesh = folded_quad();
+        mesh.normals = [1.0, 0.0, 0.0].repeat(4);
+        mesh.prepare(None, [1.0, 1.0, 1.0]);
+        assert_eq!(mesh.normals, [1.0, 0.0, 0.0].repeat(4));
+    }
+
+    #[test]
+    fn missing_colors_are_filled() {
+        let mut mesh = folded_quad();
+        mesh.prepare(None, [0.5, 0.25, 1.0]);
+        assert_eq!(mesh.vertex_color, [0.5, 0.25, 1.0].repeat(4));
+    }
+}
@@ -3,18 +3,27 @@
 //! future.
 
 use crate::{
-    geometry::intersect_box_and_line,
+    ambient_occlusion::{self, AmbientOcclusion, OcclusionSettings},
+    bvh::{Bvh, Hit},
+    clipping::{ClipPlane, Clipping, MAX_CLIP_PLANES},
+    edges::{EdgeSettings, Edges},
+    gbuffer::GBuffer,
+    geometry::{intersect_box_and_line, Ray},
+    mesh::Mesh,
     render_gl::{
         self,
         buffer::{self, FrameBuffer, Texture},
         data::{self, f32_f32_f32},
         Viewport,
     },
-    resources::Resources,
+    resources::{self, Resources},
+    section::CrossSection,
 };
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use nalgebra as na;
 use render_gl_derive::VertexAttribPointers;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 const MAIN_SHADER_PATH: &str = "shaders/model";
 const MAIN_SHADER_NAME: &str = "model";
@@ -23,10 +32,14 @@ const SHADOW_SHADER_NAME: &str = "shadow";
 const HATCHING_SHADER_PATH: &str = "shaders/hatching";
 const HATCHING_SHADER_NAME: &str = "hatching";
 const HATCHING_FAR_PLANE: f32 = 1000.0;
+/// Widest field of view of the hatching map, used when the camera is inside the scene.
+const MAX_HATCHING_FOV: f32 = 2.0 * std::f32::consts::FRAC_PI_3;
 const SHADOW_WIDTH: gl::types::GLsizei = 2048;
 const SHADOW_HEIGHT: gl::types::GLsizei = 2048;
 const TEXTURE_UNIT_SHADOW: gl::types::GLenum = gl::TEXTURE0;
 const TEXTURE_UNIT_HATCH: gl::types::GLenum = gl::TEXTURE1;
+/// Faces meeting at a sharper angle than this keep separate normals when normals are generated.
+const NORMAL_CREASE_ANGLE: Option<f32> = Some(std::f32::consts::FRAC_PI_3);
 
 #[derive(Copy, Clone, Debug, VertexAttribPointers)]
 #[repr(C, packed)]
@@ -37,24 +50,21 @@ pub struct Vertex {
     pub normal: data::f32_f32_f32,
     #[location = 2]
     pub color: data::f32_f32_f32,
+    #[location = 3]
+    pub material_color: data::f32_f32_f32,
 }
 
 /// Represents which color channel the distance shading shader should use.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[repr(C)]
 pub enum DistanceShadingChannel {
+    #[default]
     None = 0,
     Hue = 1,
     Saturation = 2,
     Value = 3,
 }
 
-impl Default for DistanceShadingChannel {
-    fn default() -> Self {
-        DistanceShadingChannel::None
-    }
-}
-
 impl std::fmt::Display for DistanceShadingChannel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         match self {
@@ -66,13 +76,39 @@ impl std::fmt::Display for DistanceShadingChannel {
     }
 }
 
-/// Represents shader attributes in use.
-#[derive(Debug, Clone)]
+/// Represents which source the shader should take the surface colour from.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[repr(C)]
+pub enum ColorSource {
+    #[default]
+    Vertex = 0,
+    Material = 1,
+    Flat = 2,
+}
+
+impl std::fmt::Display for ColorSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            ColorSource::Vertex => write!(f, "Vertex colour"),
+            ColorSource::Material => write!(f, "Material colour"),
+            ColorSource::Flat => write!(f, "Flat base colour"),
+        }
+    }
+}
+
+/// Represents shader attributes in use. Attributes that depend on the camera, model or time are
+/// not serialized, so the rest can be saved as a preset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Attributes {
+    #[serde(skip)]
     pub projection_matrix: na::Matrix4<f32>,
+    #[serde(skip)]
     pub camera_position: na::Vector3<f32>,
     pub light_position: na::Vector3<f32>,
     pub color: na::Vector3<f32>,
+    pub color_source: ColorSource,
+    #[serde(skip)]
     pub model_size: f32,
     pub distance_shading_power: f32,
     pub toon_factor: f32,
@@ -80,6 +116,7 @@ pub struct Attributes {
     pub shadow_intensity: f32,
     pub shadows_follow: bool,
     pub shadows_orbit_radius: f32,
+    #[serde(skip)]
     pub elapsed: f32,
     pub vertex_color_mix: f32,
     pub hatching_depth: f32,
@@ -87,6 +124,18 @@ pub struct Attributes {
     pub hatching_steps: u32,
     pub hatching_intensity: f32,
     pub replace_shadows_with_hatching: bool,
+    /// How much ambient occlusion darkens the model, where 0 skips the pass.
+    pub ambient_occlusion_intensity: f32,
+    /// Radius checked for occluding geometry, relative to the model size.
+    pub ambient_occlusion_radius: f32,
+    pub ambient_occlusion_samples: u32,
+    /// Width of the outlines of silhouettes and creases in pixels, where 0 skips the pass.
+    pub edge_width: f32,
+    pub edge_color: na::Vector3<f32>,
+    /// Smallest angle between neighbouring surfaces outlined as a crease, in degrees.
+    pub edge_crease_angle: f32,
+    /// How much thinner and lighter distant outlines are, from 0 to 1.
+    pub edge_depth_scaling: f32,
 }
 
 impl Default for Attributes {
@@ -96,6 +145,7 @@ impl Default for Attributes {
             camera_position: Default::default(),
             light_position: na::Vector3::new(0.45, 0.25, 0.6),
             color: na::Vector3::new(1.0, 0.56, 0.72),
+            color_source: ColorSource::Vertex,
             model_size: Default::default(),
             distance_shading_power: 0.4,
             toon_factor: 0.7,
@@ -110,80 +160,292 @@ impl Default for Attributes {
             hatching_frequency: 4,
             hatching_intensity: 0.5,
             replace_shadows_with_hatching: true,
+            ambient_occlusion_intensity: 0.0,
+            ambient_occlusion_radius: 0.03,
+            ambient_occlusion_samples: 16,
+            edge_width: 0.0,
+            edge_color: na::Vector3::new(0.0, 0.0, 0.0),
+            edge_crease_angle: 45.0,
+            edge_depth_scaling: 0.0,
         }
     }
 }
 
+/// A single object of a [Model], drawn as its own range of the shared index buffer.
+#[derive(Debug, Clone)]
+pub struct SubMesh {
+    pub name: String,
+    pub visible: bool,
+    pub color: na::Vector3<f32>,
+    min: na::Vector3<f32>,
+    max: na::Vector3<f32>,
+    first_index: usize,
+    index_count: i32,
+}
+
+impl SubMesh {
+    /// Gets the bounding box size of the object.
+    pub fn get_size(&self) -> na::Vector3<f32> {
+        self.max - self.min
+    }
+}
+
+/// A model file in the scene of a [Model], with its own buffers and placement. Its vertices are
+/// centred on its bounding box.
+pub struct SceneModel {
+    pub name: String,
+    /// Colour the colours of its objects are multiplied with.
+    pub color: na::Vector3<f32>,
+    /// Whether the model is shown, set through [Model::set_visible].
+    visible: bool,
+    /// Placement of the model in the scene, set through [Model::set_placement].
+    isometry: na::Isometry3<f32>,
+    vao: buffer::VertexArray,
+    vbo: buffer::ArrayBuffer,
+    ibo: buffer::ElementArrayBuffer,
+    objects: Vec<SubMesh>,
+    /// Triangles of all objects, for picking.
+    bvh: Bvh,
+    size: na::Vector3<f32>,
+    /// Centre of the bounding box in the coordinates of the file, subtracted from the vertices.
+    origin: na::Vector3<f32>,
+}
+
+impl SceneModel {
+    /// Initializes buffers for the given meshes, placed at the origin.
+    fn new(name: String, meshes: Vec<Mesh>) -> Result<Self> {
+        let mut value = Self {
+            name,
+            visible: true,
+            color: na::Vector3::from_element(1.0),
+            isometry: na::Isometry3::identity(),
+            vbo: buffer::ArrayBuffer::new(),
+            vao: buffer::VertexArray::new(),
+            ibo: buffer::ElementArrayBuffer::new(),
+            objects: Vec::new(),
+            bvh: Bvh::default(),
+            size: na::Vector3::zeros(),
+            origin: na::Vector3::zeros(),
+        };
+        value.set_meshes(meshes)?;
+        Ok(value)
+    }
+
+    /// Replaces the geometry of the model. Objects keep their visibility and colour if an object
+    /// of the same name existed before.
+    fn set_meshes(&mut self, mut meshes: Vec<Mesh>) -> Result<()> {
+        meshes.retain(|mesh| !mesh.indices.is_empty());
+        if meshes.is_empty() {
+            return Err(anyhow!("Model has no triangles!"));
+        }
+        let default_color = Attributes::default().color;
+        for mesh in meshes.iter_mut() {
+            mesh.prepare(NORMAL_CREASE_ANGLE, default_color.into());
+        }
+
+        let bounds: Vec<_> = meshes
+            .iter()
+            .map(|mesh| bounding_box(&mesh.positions))
+            .collect();
+        let (min, max) = bounds.iter().fold(
+            (
+                na::Vector3::from_element(f32::MAX),
+                na::Vector3::from_element(f32::MIN),
+            ),
+            |(min, max), (mesh_min, mesh_max)| (min.inf(mesh_min), max.sup(mesh_max)),
+        );
+        let center = min + (max - min) / 2.0;
+
+        // All objects share one vertex and index buffer, and are drawn as separate index ranges.
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        let mut objects = Vec::with_capacity(meshes.len());
+        for (mesh, (mesh_min, mesh_max)) in meshes.into_iter().zip(bounds) {
+            let base_vertex = vertices.len() as u32;
+            let first_index = indices.len();
+            let material_color = mesh.material_color;
+            vertices.extend(
+                mesh.positions
+                    .chunks_exact(3)
+                    .zip(mesh.normals.chunks_exact(3))
+                    .zip(mesh.vertex_color.chunks_exact(3))
+                    .map(|((p, n), c)| {
+                        (
+                            f32_f32_f32::from((
+                                p[0] - center[0],
+                                p[1] - center[1],
+                                p[2] - center[2],
+                            )),
+                            f32_f32_f32::from((n[0], n[1], n[2])),
+                            f32_f32_f32::from((c[0], c[1], c[2])),
+                            f32_f32_f32::from(
+                                material_color.map_or((c[0], c[1], c[2]), |m| (m[0], m[1], m[2])),
+                            ),
+                        )
+                    })
+                    .map(|(pos, normal, color, material_color)| Vertex {
+                        pos,
+                        normal,
+                        color,
+                        material_color,
+                    }),
+            );
+            indices.extend(mesh.indices.iter().map(|i| i + base_vertex));
+            let previous = self.objects.iter().find(|object| object.name == mesh.name);
+            objects.push(SubMesh {
+                visible: previous.is_none_or(|object| object.visible),
+                color: previous.map_or(na::Vector3::from_element(1.0), |object| object.color),
+                name: mesh.name,
+                min: mesh_min - center,
+                max: mesh_max - center,
+                first_index,
+                index_count: mesh.indices.len() as i32,
+            });
+        }
+        self.vbo.bind();
+        self.vbo.static_draw_data(&vertices);
+
+        // set up vertex array object
+        self.vao.bind();
+        Vertex::vertex_attrib_pointers();
+
+        // indices buffer
+        self.ibo.bind();
+        self.ibo.static_draw_data(&indices);
+        self.ibo.unbind();
+        self.vbo.unbind();
+        self.vao.unbind();
+
+        let positions: Vec<na::Point3<f32>> = vertices
+            .iter()
+            .map(|vertex| {
+                let pos = vertex.pos;
+                na::Point3::new(pos.d0, pos.d1, pos.d2)
+            })
+            .collect();
+        self.bvh = Bvh::new(&positions, &indices);
+        self.objects = objects;
+        self.size = max - min;
+        self.origin = center;
+        Ok(())
+    }
+
+    /// Get the objects the model consists of, allowing their visibility and colour to be changed.
+    pub fn get_objects_mut(&mut self) -> &mut [SubMesh] {
+        &mut self.objects
+    }
+
+    /// Gets the bounding box size of the model, before it is placed in the scene.
+    pub fn get_size(&self) -> &na::Vector3<f32> {
+        &self.size
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Gets the placement of the model in the scene.
+    pub fn get_isometry(&self) -> &na::Isometry3<f32> {
+        &self.isometry
+    }
+
+    /// Gets the corners of the axis-aligned box around the model as placed in the scene.
+    fn bounds(&self) -> (na::Point3<f32>, na::Point3<f32>) {
+        let half = self.size / 2.0;
+        let corners = (0..8).map(|i| {
+            let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+            let corner = na::Point3::new(sign(1) * half.x, sign(2) * half.y, sign(4) * half.z);
+            self.isometry.transform_point(&corner)
+        });
+        corners.fold(
+            (
+                na::Point3::from(na::Vector3::from_element(f32::MAX)),
+                na::Point3::from(na::Vector3::from_element(f32::MIN)),
+            ),
+            |(min, max), corner| (min.inf(&corner), max.sup(&corner)),
+        )
+    }
+
+    /// Checks if the triangle with the given index belongs to a visible object.
+    fn is_triangle_visible(&self, triangle: usize) -> bool {
+        let index = triangle * 3;
+        self.objects.iter().any(|object| {
+            object.visible
+                && (object.first_index..object.first_index + object.index_count as usize)
+                    .contains(&index)
+        })
+    }
+
+    /// Finds the closest point where the ray, in scene coordinates, hits a visible object on the
+    /// kept side of every clipping plane. The hit is in scene coordinates.
+    fn pick(&self, ray: &Ray, clip_planes: &[na::Vector4<f32>]) -> Option<Hit> {
+        let local = Ray {
+            origin: self.isometry.inverse_transform_point(&ray.origin),
+            direction: self.isometry.inverse_transform_vector(&ray.direction),
+        };
+        let hit = self.bvh.intersect(&local, |triangle, point| {
+            let point = self.isometry.transform_point(point).to_homogeneous();
+            clip_planes.iter().all(|plane| plane.dot(&point) >= 0.0)
+                && self.is_triangle_visible(triangle)
+        })?;
+        Some(Hit {
+            point: self.isometry.transform_point(&hit.point),
+            normal: self.isometry.transform_vector(&hit.normal),
+            ..hit
+        })
+    }
+
+    /// Finds the triangles of visible objects near the plane, given by its equation in scene
+    /// coordinates, with their corners in scene coordinates.
+    fn triangles_near_plane(&self, plane: &na::Vector4<f32>) -> Vec<[na::Point3<f32>; 3]> {
+        let local_plane = self.isometry.to_homogeneous().transpose() * plane;
+        self.bvh
+            .triangles_near_plane(&local_plane)
+            .into_iter()
+            .filter(|(triangle, _)| self.is_triangle_visible(*triangle))
+            .map(|(_, triangle)| triangle.map(|p| self.isometry.transform_point(&p)))
+            .collect()
+    }
+}
+
 /// [Model] is where the main logic for the mesh rendering happens. Ideally, shadow- and hatching
 /// texture rendering should be refactored to it's own module in the future.
+///
+/// It renders a scene of one or more [SceneModel]s, which share the shaders, attributes, clipping
+/// planes and shadow and hatching maps.
 pub struct Model {
     program: render_gl::Program,
     shadow_program: render_gl::Program,
     hatching_program: render_gl::Program,
-    vao: buffer::VertexArray,
-    _vbo: buffer::ArrayBuffer,
-    ibo: buffer::ElementArrayBuffer,
-    indices: i32,
-    size: na::Vector3<f32>,
+    /// The model first opened, followed by those added to the scene.
+    models: Vec<SceneModel>,
     attributes: Attributes,
+    /// Equations of the enabled clipping planes.
+    clip_planes: Vec<na::Vector4<f32>>,
+    /// Colour the cuts of the clipping planes are filled with, if they are filled.
+    cap_color: Option<na::Vector3<f32>>,
     depth_map: Texture,
     depth_map_fbo: FrameBuffer,
     hatch_map: Texture,
     hatch_map_fbo: FrameBuffer,
+    gbuffer: GBuffer,
+    ambient_occlusion: AmbientOcclusion,
+    edges: Edges,
 }
 
 impl Model {
     /// Set up [Model], compiling shaders, initializing buffers, and parsing a model.
     pub fn new(res: &Resources, filename: &str) -> Result<Self> {
+        let meshes = res.load_model(filename).context("Failed to load model.")?;
+        Self::from_meshes(res, resources::file_stem(Path::new(filename)), meshes)
+    }
+
+    /// Set up [Model], compiling shaders and initializing buffers for the given meshes, which
+    /// make up a model of the given name.
+    pub fn from_meshes(res: &Resources, name: String, meshes: Vec<Mesh>) -> Result<Self> {
         // set up shader program
         let program = render_gl::Program::from_res(res, MAIN_SHADER_PATH)?;
 
-        let model = res.load_model(filename).context("Failed to load model.")?;
-
-        let mut min = na::Vector3::from_element(f32::MAX);
-        let mut max = na::Vector3::from_element(f32::MIN);
-        for pos in model.positions.chunks_exact(3) {
-            min[0] = min[0].min(pos[0]);
-            max[0] = max[0].max(pos[0]);
-            min[1] = min[1].min(pos[1]);
-            max[1] = max[1].max(pos[1]);
-            min[2] = min[2].min(pos[2]);
-            max[2] = max[2].max(pos[2]);
-        }
-        let center = min + (max - min) / 2.0;
-        let model_size = (max - min).magnitude();
-
-        let vertices: Vec<Vertex> = model
-            .positions
-            .chunks_exact(3)
-            .zip(model.normals.chunks_exact(3))
-            .zip(model.vertex_color.chunks_exact(3))
-            .map(|((p, n), c)| {
-                (
-                    f32_f32_f32::from((p[0] - center[0], p[1] - center[1], p[2] - center[2])),
-                    f32_f32_f32::from((n[0], n[1], n[2])),
-                    f32_f32_f32::from((c[0], c[1], c[2])),
-                )
-            })
-            .map(|(pos, normal, color)| Vertex { pos, normal, color })
-            .collect();
-        let vbo = buffer::ArrayBuffer::new();
-        vbo.bind();
-        vbo.static_draw_data(&vertices);
-
-        // set up vertex array object
-        let vao = buffer::VertexArray::new();
-        vao.bind();
-        Vertex::vertex_attrib_pointers();
-
-        // indices buffer
-        let ibo = buffer::ElementArrayBuffer::new();
-        ibo.bind();
-        ibo.static_draw_data(&model.indices);
-        ibo.unbind();
-        vbo.unbind();
-        vao.unbind();
-
         // Shadowstuff
         let shadow_program = render_gl::Program::from_res(res, SHADOW_SHADER_PATH)?;
         shadow_program.set_used();
@@ -205,10 +467,7 @@ impl Model {
         depth_map_fbo.bind_texture(gl::DEPTH_ATTACHMENT, &depth_map);
         depth_map_fbo.unbind();
 
-        let attributes = Attributes {
-            model_size,
-            ..Default::default()
-        };
+        let attributes = Attributes::default();
 
         let hatching_program = render_gl::Program::from_res(res, HATCHING_SHADER_PATH)?;
         let hatch_map = Texture::new(TEXTURE_UNIT_HATCH);
@@ -228,30 +487,130 @@ impl Model {
         hatch_map_fbo.bind_texture(gl::DEPTH_ATTACHMENT, &hatch_map);
         hatch_map_fbo.unbind();
 
-        let value = Self {
+        let mut value = Self {
             program,
             shadow_program,
             hatching_program,
-            _vbo: vbo,
-            vao,
-            ibo,
-            indices: model.indices.len() as i32,
-            size: max - min,
+            models: vec![SceneModel::new(name, meshes)?],
             attributes,
+            clip_planes: Vec::new(),
+            cap_color: None,
             depth_map,
             depth_map_fbo,
             hatch_map,
             hatch_map_fbo,
+            gbuffer: GBuffer::new(res)?,
+            ambient_occlusion: AmbientOcclusion::new(res)?,
+            edges: Edges::new(res)?,
         };
+        value.update_size();
         value.reset_all_attributes();
         Ok(value)
     }
 
+    /// Replaces the geometry of the model first opened, keeping the shaders and attributes.
+    /// Objects keep their visibility and colour if an object of the same name existed before.
+    pub fn set_meshes(&mut self, meshes: Vec<Mesh>) -> Result<()> {
+        self.models[0].set_meshes(meshes)?;
+        self.update_size();
+        Ok(())
+    }
+
+    /// Adds the model in the given file to the scene. It is placed where it is relative to the
+    /// model first opened in the coordinates of their files, so registered models line up.
+    pub fn add(&mut self, res: &Resources, filename: &str) -> Result<()> {
+        let meshes = res.load_model(filename).context("Failed to load model.")?;
+        let mut model = SceneModel::new(resources::file_stem(Path::new(filename)), meshes)?;
+        let first = &self.models[0];
+        model.isometry = first.isometry * na::Translation3::from(model.origin - first.origin);
+        self.models.push(model);
+        self.update_size();
+        Ok(())
+    }
+
+    /// Gets the transformation from scene coordinates to the coordinates of the file first opened,
+    /// undoing its placement and the centring of its vertices.
+    pub fn file_transform(&self) -> na::Isometry3<f32> {
+        let first = &self.models[0];
+        na::Translation3::from(first.origin) * first.isometry.inverse()
+    }
+
+    /// Removes the model with the given index from the scene, unless it is the only one.
+    pub fn remove(&mut self, index: usize) {
+        if self.models.len() > 1 && index < self.models.len() {
+            self.models.remove(index);
+            self.update_size();
+        }
+    }
+
+    /// Shows or hides the model with the given index, updating the size of the scene.
+    pub fn set_visible(&mut self, index: usize, visible: bool) {
+        if let Some(model) = self.models.get_mut(index) {
+            model.visible = visible;
+            self.update_size();
+        }
+    }
+
+    /// Places the model with the given index in the scene, updating the size of the scene.
+    pub fn set_placement(&mut self, index: usize, isometry: na::Isometry3<f32>) {
+        if let Some(model) = self.models.get_mut(index) {
+            model.isometry = isometry;
+            self.update_size();
+        }
+    }
+
+    /// Passes the size of the scene to the main shader.
+    fn update_size(&mut self) {
+        self.attributes.model_size = self.get_size().magnitude();
+        self.program.set_used();
+        // Safety: data passed to buffers must be of appropriate type and size.
+        unsafe {
+            self.program
+                .set_uniform_f("model_size", self.attributes.model_size);
+        }
+        self.program.unset_used();
+    }
+
     /// Get the shader attributes.
     pub fn get_attributes(&self) -> &Attributes {
         &self.attributes
     }
 
+    /// Get the models in the scene, allowing their placement, visibility and colour to be
+    /// changed.
+    pub fn get_models_mut(&mut self) -> &mut [SceneModel] {
+        &mut self.models
+    }
+
+    /// Sets the clipping planes cutting away part of every object, and how the cuts are shown.
+    pub fn set_clipping(&mut self, clipping: &Clipping) {
+        self.clip_planes = clipping.equations();
+        self.clip_planes.truncate(MAX_CLIP_PLANES);
+        self.cap_color = clipping.cap.then_some(clipping.cap_color);
+    }
+
+    /// Finds the closest point where the ray hits a visible object, ignoring the parts cut away
+    /// by the clipping planes. The triangle index of the hit is within the model it belongs to.
+    pub fn pick(&self, ray: &Ray) -> Option<Hit> {
+        self.visible_models()
+            .filter_map(|model| model.pick(ray, &self.clip_planes))
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Finds the contours where the plane cuts the visible objects.
+    pub fn cross_section(&self, plane: &ClipPlane) -> CrossSection {
+        let equation = plane.equation();
+        let triangles = self
+            .visible_models()
+            .flat_map(|model| model.triangles_near_plane(&equation));
+        CrossSection::new(triangles, plane)
+    }
+
+    /// Gets the models that are shown.
+    fn visible_models(&self) -> impl Iterator<Item = &SceneModel> {
+        self.models.iter().filter(|model| model.visible)
+    }
+
     /// Get the hatching texture.
     pub fn get_hatch_texture(&self) -> &Texture {
         &self.hatch_map
@@ -262,6 +621,11 @@ impl Model {
         &self.depth_map
     }
 
+    /// Get the ambient occlusion texture.
+    pub fn get_occlusion_texture(&self) -> &Texture {
+        self.ambient_occlusion.get_texture()
+    }
+
     /// Compares given [Attributes] struct to the currently applied attributes and updated any
     /// changed values in the shader.
     pub fn set_attributes(&mut self, new: Attributes) {
@@ -284,6 +648,10 @@ impl Model {
             if new.color != old.color {
                 self.program.set_uniform_3f_na("color", new.color);
             }
+            if new.color_source != old.color_source {
+                self.program
+                    .set_uniform_ui("color_source", new.color_source as u32);
+            }
             if (new.model_size - old.model_size).abs() < f32::EPSILON {
                 self.program.set_uniform_f("model_size", new.model_size);
             }
@@ -322,6 +690,12 @@ impl Model {
                     new.replace_shadows_with_hatching as u32,
                 )
             }
+            if new.ambient_occlusion_intensity != old.ambient_occlusion_intensity {
+                self.program.set_uniform_f(
+                    "ambient_occlusion_intensity",
+                    new.ambient_occlusion_intensity,
+                )
+            }
         }
         self.program.unset_used();
         self.attributes = new;
@@ -339,6 +713,8 @@ impl Model {
             self.program
                 .set_uniform_3f_na("camera_position", att.camera_position);
             self.program.set_uniform_3f_na("color", att.color);
+            self.program
+                .set_uniform_ui("color_source", att.color_source as u32);
             self.program.set_uniform_f("model_size", att.model_size);
             self.program
                 .set_uniform_f("distance_shading_power", att.distance_shading_power);
@@ -359,13 +735,27 @@ impl Model {
                 "replace_shadows_with_hatching",
                 att.replace_shadows_with_hatching as u32,
             );
+            self.program.set_uniform_f(
+                "ambient_occlusion_intensity",
+                att.ambient_occlusion_intensity,
+            );
         }
         self.program.unset_used();
     }
 
-    /// Gets the bounding box size of the loaded model.
-    pub fn get_size(&self) -> &na::Vector3<f32> {
-        &self.size
+    /// Gets the size of the axis-aligned box around the visible models.
+    pub fn get_size(&self) -> na::Vector3<f32> {
+        let (min, max) = self.bounds();
+        max - min
+    }
+
+    /// Gets the corners of the axis-aligned box around the visible models, or the origin if
+    /// none are visible.
+    fn bounds(&self) -> (na::Point3<f32>, na::Point3<f32>) {
+        self.visible_models()
+            .map(SceneModel::bounds)
+            .reduce(|(min, max), (model_min, model_max)| (min.inf(&model_min), max.sup(&model_max)))
+            .unwrap_or_default()
     }
 
     /// The main rendering function for the program.
@@ -374,12 +764,38 @@ impl Model {
         // around OpenGL (which even `glium` eventually had to give up on), this will likely never
         // be entirely safe.
         unsafe {
+            // The shadow and hatching passes bind their own framebuffers, so the one bound by the
+            // caller is restored for the main pass. This allows rendering offscreen.
+            let mut target_framebuffer = 0;
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut target_framebuffer);
             let (light_vector, light_space_matrix) = self.render_shadowmap();
             let hatch_space_matrix = self.render_hatchmap(viewport);
+            let occlusion = self.attributes.ambient_occlusion_intensity > 0.0;
+            let edges = self.attributes.edge_width > 0.0;
+            if occlusion || edges {
+                self.render_gbuffer(viewport);
+            }
+            if occlusion {
+                self.ambient_occlusion.render(
+                    &self.gbuffer,
+                    viewport.size(),
+                    &self.attributes.projection_matrix,
+                    self.attributes.camera_position,
+                    OcclusionSettings {
+                        radius: self.attributes.ambient_occlusion_radius
+                            * self.attributes.model_size,
+                        samples: self.attributes.ambient_occlusion_samples,
+                    },
+                );
+            }
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target_framebuffer as gl::types::GLuint);
 
-            // Calculate distance shading planes
+            // Calculate distance shading planes, from a box around the origin covering the
+            // visible models.
             let cam = self.attributes.camera_position;
-            let mut intersections = intersect_box_and_line(cam, self.size).to_vec();
+            let (min, max) = self.bounds();
+            let size = min.coords.abs().sup(&max.coords.abs()) * 2.0;
+            let mut intersections = intersect_box_and_line(cam, size).to_vec();
             intersections.sort_unstable_by(|&a, &b| {
                 (cam - a)
                     .norm()
@@ -411,11 +827,22 @@ impl Model {
             );
             self.program
                 .set_uniform_f("hatching_far_plane", HATCHING_FAR_PLANE);
-            gl::Enable(gl::CULL_FACE);
-            gl::CullFace(gl::BACK);
+            self.program
+                .set_uniform_ui("cap_enabled", self.cap_color.is_some() as u32);
+            self.program
+                .set_uniform_3f_na("cap_color", self.cap_color.unwrap_or_default());
+            // The occlusion texture covers the viewport, which may not start at the window corner.
+            self.program
+                .set_uniform_2f("viewport_origin", (viewport.x as f32, viewport.y as f32));
+            self.set_clip_planes(&self.program);
+            // The back faces are drawn where the model is cut, to show its inside.
+            if self.clip_planes.is_empty() {
+                gl::Enable(gl::CULL_FACE);
+                gl::CullFace(gl::BACK);
+            } else {
+                gl::Disable(gl::CULL_FACE);
+            }
             viewport.set_used();
-            self.vao.bind();
-            self.ibo.bind();
             self.depth_map.bind_to(gl::TEXTURE0);
             self.hatch_map.bind_to(gl::TEXTURE0 + 1);
             if self.attributes.replace_shadows_with_hatching {
@@ -424,17 +851,34 @@ impl Model {
             } else {
                 self.hatch_map.set_texture_compare_mode(gl::NONE);
             }
-            gl::DrawElements(
-                gl::TRIANGLES,
-                self.indices,
-                gl::UNSIGNED_INT,
-                std::ptr::null::<std::ffi::c_void>(),
-            );
+            if occlusion {
+                self.get_occlusion_texture()
+                    .bind_to(ambient_occlusion::TEXTURE_UNIT_OCCLUSION);
+            }
+            self.draw_models(&self.program, true);
+            disable_clip_planes();
+
+            // Outlines are drawn over the model, scaled between the same planes as the distance
+            // shading.
+            if edges {
+                self.edges.render(
+                    &self.gbuffer,
+                    &self.attributes.projection_matrix,
+                    cam,
+                    self.attributes.model_size,
+                    ((cam - closest).norm(), (cam - furthest).norm()),
+                    EdgeSettings {
+                        width: self.attributes.edge_width,
+                        color: self.attributes.edge_color,
+                        crease_angle: self.attributes.edge_crease_angle,
+                        depth_scaling: self.attributes.edge_depth_scaling,
+                    },
+                );
+            }
         }
+        self.get_occlusion_texture().unbind();
         self.hatch_map.unbind();
         self.depth_map.unbind();
-        self.ibo.unbind();
-        self.vao.unbind();
     }
 
     /// Renders the shadowmap to the shadows framebuffer.
@@ -449,11 +893,10 @@ impl Model {
         gl::Enable(gl::DEPTH_TEST);
         gl::DepthFunc(gl::LESS);
         self.shadow_program.set_used();
-        let near_plane = 1.0;
-        let far_plane = 500.0;
-        let bound = 250.0;
-        let light_projection =
-            na::Orthographic3::new(-bound, bound, -bound, bound, near_plane, far_plane);
+        // The light frustum is fitted around a sphere enclosing the visible models.
+        let (min, max) = self.bounds();
+        let center = na::center(&min, &max);
+        let radius = (na::distance(&min, &max) / 2.0).max(1.0);
         let light_pos = match self.attributes.shadows_follow {
             true => self.attributes.camera_position,
             false => self.attributes.light_position,
@@ -467,27 +910,27 @@ impl Model {
         let horizontal = na::Vector3::new(0.0, 1.0, 0.0).cross(&light);
         let up_vector = horizontal.cross(&light).normalize() * self.attributes.shadows_orbit_radius;
         let light = (rotation * (light + up_vector).to_homogeneous()).xyz();
-        let center = na::Point3::new(0.0, 0.0, 0.0);
-        let light_view = na::Matrix4::look_at_rh(
-            &na::Point3::from(light),
-            &center,
-            &na::Vector3::new(0.0, 1.0, 0.0),
+        let distance = light.magnitude();
+        let light_projection = na::Orthographic3::new(
+            -radius,
+            radius,
+            -radius,
+            radius,
+            distance - radius,
+            distance + radius,
         );
-        let light_vector = center - light;
+        let light = center + light;
+        let light_view = na::Matrix4::look_at_rh(&light, &center, &na::Vector3::new(0.0, 1.0, 0.0));
+        let light_vector = na::Point3::from(center - light);
         let light_space_matrix = light_projection.to_homogeneous() * light_view;
         self.shadow_program
             .set_uniform_matrix4("lightSpaceMatrix", &light_space_matrix);
+        self.set_clip_planes(&self.shadow_program);
         gl::Viewport(0, 0, SHADOW_WIDTH, SHADOW_HEIGHT);
         self.depth_map_fbo.bind();
         gl::Clear(gl::DEPTH_BUFFER_BIT);
-        self.vao.bind();
-        self.ibo.bind();
-        gl::DrawElements(
-            gl::TRIANGLES,
-            self.indices,
-            gl::UNSIGNED_INT,
-            std::ptr::null::<std::ffi::c_void>(),
-        );
+        self.draw_models(&self.shadow_program, false);
+        disable_clip_planes();
         self.depth_map_fbo.unbind();
         (light_vector, light_space_matrix)
     }
@@ -506,27 +949,36 @@ impl Model {
             .set_uniform_ui("steps", self.attributes.hatching_steps);
         self.hatch_map_fbo.bind();
 
+        // The view is aimed from the camera at a sphere enclosing the visible models, with the
+        // field of view fitted around it, or wide if the camera is inside it.
+        let (min, max) = self.bounds();
+        let center = na::center(&min, &max);
+        let radius = (na::distance(&min, &max) / 2.0).max(1.0);
+        let hatch_pos = na::Point3::from(self.attributes.camera_position);
+        let distance = na::distance(&hatch_pos, &center);
         let near_plane = 0.1;
-        let aspect = viewport.size().0 as f32 / viewport.size().1 as f32;
+        let aspect = viewport.size().0 as f32 / viewport.size().1.max(1) as f32;
+        let half_angle = match distance > radius {
+            true => (radius / distance).asin(),
+            false => MAX_HATCHING_FOV / 2.0,
+        };
+        // The sphere must also fit horizontally when the viewport is taller than it is wide.
+        let fov = 2.0 * (half_angle.tan() / aspect.min(1.0)).atan();
         let hatch_projection = na::Perspective3::new(
             aspect,
-            std::f32::consts::PI / 4.0,
+            fov.min(MAX_HATCHING_FOV),
             near_plane,
             HATCHING_FAR_PLANE,
         );
-        let hatch_pos = self.attributes.camera_position;
-        let center = na::Point3::new(0.0, 0.0, 0.0);
-        let hatch_view = na::Matrix4::look_at_rh(
-            &na::Point3::from(hatch_pos),
-            &center,
-            &na::Vector3::new(0.0, 1.0, 0.0),
-        );
+        let hatch_view =
+            na::Matrix4::look_at_rh(&hatch_pos, &center, &na::Vector3::new(0.0, 1.0, 0.0));
 
         let hatch_space_matrix = hatch_projection.to_homogeneous() * hatch_view;
         self.hatching_program
             .set_uniform_matrix4("projection_matrix", &hatch_space_matrix);
         self.hatching_program
             .set_uniform_f("far_plane", HATCHING_FAR_PLANE);
+        self.set_clip_planes(&self.hatching_program);
 
         gl::Disable(gl::CULL_FACE);
         gl::Disable(gl::BLEND);
@@ -534,22 +986,76 @@ impl Model {
         gl::DepthFunc(gl::LESS);
         gl::Viewport(0, 0, SHADOW_WIDTH, SHADOW_HEIGHT);
         gl::Clear(gl::DEPTH_BUFFER_BIT);
-        self.vao.bind();
-        self.ibo.bind();
-        gl::DrawElements(
-            gl::TRIANGLES,
-            self.indices,
-            gl::UNSIGNED_INT,
-            std::ptr::null::<std::ffi::c_void>(),
-        );
+        self.draw_models(&self.hatching_program, false);
+        disable_clip_planes();
         self.hatch_map_fbo.unbind();
         hatch_space_matrix
     }
 
+    /// Renders the normals and depth of the scene as seen by the camera to the G-buffer, for the
+    /// screen-space passes.
+    ///
+    /// ### Safety
+    ///
+    /// Requires buffers and data in the struct to be appropriately set.
+    /// This function should only be called from [Model::render].
+    unsafe fn render_gbuffer(&self, viewport: &Viewport) {
+        let program = self.gbuffer.begin(viewport.size());
+        program.set_uniform_matrix4("projection_matrix", &self.attributes.projection_matrix);
+        self.set_clip_planes(program);
+        self.draw_models(program, false);
+        disable_clip_planes();
+    }
+
+    /// Passes the clipping planes to the given shader program, which must be in use, and enables
+    /// as many clip distances as there are planes.
+    ///
+    /// ### Safety
+    ///
+    /// The program must declare the `clip_planes` and `clip_plane_count` uniforms.
+    unsafe fn set_clip_planes(&self, program: &render_gl::Program) {
+        program.set_uniform_ui("clip_plane_count", self.clip_planes.len() as u32);
+        for (i, plane) in self.clip_planes.iter().enumerate() {
+            program.set_uniform_4f_na(&format!("clip_planes[{}]", i), *plane);
+            gl::Enable(gl::CLIP_DISTANCE0 + i as gl::types::GLenum);
+        }
+    }
+
+    /// Draws the index range of every visible object of every visible model, passing the
+    /// placement of each model to the given shader program. If `set_color` is true, the object
+    /// colour is passed to the main shader program before each draw call.
+    ///
+    /// ### Safety
+    ///
+    /// Requires the shader program to be in use, and to declare the `model_matrix` uniform.
+    unsafe fn draw_models(&self, program: &render_gl::Program, set_color: bool) {
+        for model in self.visible_models() {
+            program.set_uniform_matrix4("model_matrix", &model.isometry.to_homogeneous());
+            model.vao.bind();
+            model.ibo.bind();
+            for object in model.objects.iter().filter(|object| object.visible) {
+                if set_color {
+                    self.program.set_uniform_3f_na(
+                        "object_color",
+                        model.color.component_mul(&object.color),
+                    );
+                }
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    object.index_count,
+                    gl::UNSIGNED_INT,
+                    (object.first_index * std::mem::size_of::<u32>()) as *const std::ffi::c_void,
+                );
+            }
+            model.ibo.unbind();
+            model.vao.unbind();
+        }
+    }
+
     /// Check if any of the shaders have been updated.
     pub fn check_shader_update(&mut self, path: &std::path::Path, res: &Resources) -> bool {
-        let path = path.file_stem().map(|p| p.to_string_lossy().to_string());
-        if path == Some(MAIN_SHADER_NAME.to_string()) {
+        let name = path.file_stem().map(|p| p.to_string_lossy().to_string());
+        if name == Some(MAIN_SHADER_NAME.to_string()) {
             match render_gl::Program::from_res(res, MAIN_SHADER_PATH) {
                 Ok(program) => {
                     self.program.unset_used();
@@ -559,7 +1065,7 @@ impl Model {
                 }
                 Err(e) => eprintln!("Shader reload error: {}", e),
             }
-        } else if path == Some(SHADOW_SHADER_NAME.to_string()) {
+        } else if name == Some(SHADOW_SHADER_NAME.to_string()) {
             match render_gl::Program::from_res(res, SHADOW_SHADER_PATH) {
                 Ok(program) => {
                     self.shadow_program.unset_used();
@@ -569,7 +1075,7 @@ impl Model {
                 }
                 Err(e) => eprintln!("Shader reload error: {}", e),
             }
-        } else if path == Some(HATCHING_SHADER_NAME.to_string()) {
+        } else if name == Some(HATCHING_SHADER_NAME.to_string()) {
             match render_gl::Program::from_res(res, HATCHING_SHADER_PATH) {
                 Ok(program) => {
                     self.hatching_program.unset_used();
@@ -580,6 +1086,34 @@ impl Model {
                 Err(e) => eprintln!("Shader reload error: {}", e),
             }
         }
-        false
+        self.gbuffer.check_shader_update(path, res)
+            || self.ambient_occlusion.check_shader_update(path, res)
+            || self.edges.check_shader_update(path, res)
+    }
+}
+
+/// Disables the clip distances enabled by [Model::set_clip_planes].
+///
+/// ### Safety
+///
+/// Requires a current OpenGL context.
+unsafe fn disable_clip_planes() {
+    for i in 0..MAX_CLIP_PLANES {
+        gl::Disable(gl::CLIP_DISTANCE0 + i as gl::types::GLenum);
+    }
+}
+
+/// Finds the minimum and maximum corners of the bounding box of flattened vertex positions.
+fn bounding_box(positions: &[f32]) -> (na::Vector3<f32>, na::Vector3<f32>) {
+    let mut min = na::Vector3::from_element(f32::MAX);
+    let mut max = na::Vector3::from_element(f32::MIN);
+    for pos in positions.chunks_exact(3) {
+        min[0] = min[0].min(pos[0]);
+        max[0] = max[0].max(pos[0]);
+        min[1] = min[1].min(pos[1]);
+        max[1] = max[1].max(pos[1]);
+        min[2] = min[2].min(pos[2]);
+        max[2] = max[2].max(pos[2]);
     }
+    (min, max)
 }
@@ -0,0 +1,166 @@
+//! Contains [OffscreenTarget], a framebuffer to render the model into at a resolution
+//! independent of the window, and functions to save what is rendered as a PNG image.
+
+use crate::{
+    camera::Camera,
+    model::Model,
+    render_gl::{
+        buffer::{FrameBuffer, Texture},
+        ColorBuffer, Viewport,
+    },
+    ui::UIRenderer,
+};
+use anyhow::{anyhow, Context, Result};
+use nalgebra as na;
+use std::{fs, io::BufWriter, path::Path};
+
+/// A framebuffer with a colour and depth attachment, to render images larger than the window.
+pub struct OffscreenTarget {
+    framebuffer: FrameBuffer,
+    _color: Texture,
+    _depth: Texture,
+    viewport: Viewport,
+}
+
+impl OffscreenTarget {
+    /// Creates a render target of the given size, failing if it exceeds what the GPU supports.
+    pub fn new(size: (u32, u32)) -> Result<Self> {
+        let mut max_size = 0;
+        // Safety: `MAX_TEXTURE_SIZE` is a single integer.
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size);
+        }
+        if size.0 == 0 || size.1 == 0 || size.0.max(size.1) > max_size as u32 {
+            return Err(anyhow!(
+                "Image size must be between 1 and {} pixels.",
+                max_size
+            ));
+        }
+        let dimensions = (size.0 as i32, size.1 as i32);
+
+        let color = Texture::new(gl::TEXTURE0);
+        color.load_texture(
+            dimensions,
+            None,
+            gl::RGBA8 as gl::types::GLint,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            false,
+        );
+        color.unbind();
+        let depth = Texture::new(gl::TEXTURE0);
+        depth.load_texture(
+            dimensions,
+            None,
+            gl::DEPTH_COMPONENT24 as gl::types::GLint,
+            gl::DEPTH_COMPONENT,
+            gl::FLOAT,
+            false,
+        );
+        depth.unbind();
+
+        let framebuffer = FrameBuffer::new();
+        framebuffer.bind_texture(gl::COLOR_ATTACHMENT0, &color);
+        framebuffer.bind_texture(gl::DEPTH_ATTACHMENT, &depth);
+        framebuffer.set_type(gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT0);
+        let complete = framebuffer.is_complete();
+        framebuffer.unbind();
+        if !complete {
+            return Err(anyhow!("Offscreen framebuffer is incomplete."));
+        }
+
+        Ok(Self {
+            framebuffer,
+            _color: color,
+            _depth: depth,
+            viewport: Viewport::for_window(dimensions.0, dimensions.1),
+        })
+    }
+
+    /// Binds the target, so that following draw calls render into it.
+    pub fn bind(&self) {
+        self.framebuffer.bind();
+        self.viewport.set_used();
+    }
+
+    /// Binds the window framebuffer again.
+    pub fn unbind(&self) {
+        self.framebuffer.unbind();
+    }
+
+    /// Gets the viewport covering the whole target.
+    pub fn viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+
+    /// Reads back the rendered RGBA pixels, top row first.
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let (width, height) = self.viewport.size();
+        let pixels = self.framebuffer.read_pixels((width, height));
+        pixels
+            .chunks_exact(width as usize * 4)
+            .rev()
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+/// Renders the model as seen from the camera into an image of the given size, optionally with
+/// the user interface drawn on top, and saves it as a PNG. The user interface primitives are
+/// laid out for a window of `ui_size`, so they are only drawn if the image has the same aspect
+/// ratio, scaled up uniformly to the image size. Otherwise they are left out, as the layout would
+/// be stretched.
+///
+/// The projection matrix of the model is left set for the image, so the caller should update
+/// it again before rendering to the window.
+pub fn render_to_png(
+    path: &Path,
+    size: (u32, u32),
+    model: &mut Model,
+    camera: &Camera,
+    clear_color: na::Vector3<f32>,
+    ui: Option<(&UIRenderer, &[egui::ClippedPrimitive], (u32, u32))>,
+) -> Result<()> {
+    let target = OffscreenTarget::new(size)?;
+    target.bind();
+    ColorBuffer::from_color(clear_color);
+
+    let mut attr = model.get_attributes().clone();
+    let aspect = size.0 as f32 / size.1 as f32;
+    let model_isometry = na::Isometry3::identity();
+    attr.projection_matrix = camera.construct_mvp(aspect, model_isometry);
+    model.set_attributes(attr);
+    model.render(target.viewport());
+
+    if let Some((renderer, primitives, ui_size)) = ui.filter(|ui| same_aspect(size, ui.2)) {
+        target.viewport().set_used();
+        renderer.render_primitives(primitives, ui_size);
+    }
+
+    let mut pixels = target.read_pixels();
+    target.unbind();
+    // The window ignores alpha, so the image does as well.
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel[3] = u8::MAX;
+    }
+    save_png(path, size, &pixels)
+}
+
+/// Checks if two sizes have the same aspect ratio, to within a pixel.
+fn same_aspect(a: (u32, u32), b: (u32, u32)) -> bool {
+    let scaled_width = a.1 as f32 * b.0 as f32 / b.1.max(1) as f32;
+    (a.0 as f32 - scaled_width).abs() < 1.0
+}
+
+/// Saves RGBA pixels, top row first, as a PNG image.
+pub fn save_png(path: &Path, size: (u32, u32), pixels: &[u8]) -> Result<()> {
+    let file = fs::File::create(path).context("Failed to create image file.")?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), size.0, size.1);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! Contains [LineOverlay], which draws line segments in model space on top of the model, such as
+//! measurements.
+
+use crate::{
+    render_gl::{
+        self,
+        buffer::{self},
+        data::{self, f32_f32_f32},
+        Viewport,
+    },
+    resources::Resources,
+};
+use anyhow::Result;
+use nalgebra as na;
+use render_gl_derive::VertexAttribPointers;
+
+const SHADER_PATH: &str = "shaders/overlay";
+const SHADER_NAME: &str = "overlay";
+
+#[derive(Copy, Clone, Debug, VertexAttribPointers)]
+#[repr(C, packed)]
+struct Vertex {
+    #[location = 0]
+    pub pos: data::f32_f32_f32,
+}
+
+/// Draws line segments in a single colour, ignoring depth so they stay visible through the model.
+pub struct LineOverlay {
+    program: render_gl::Program,
+    vao: buffer::VertexArray,
+    vbo: buffer::ArrayBuffer,
+    vertices: i32,
+}
+
+impl LineOverlay {
+    /// Set up [LineOverlay], compiling shaders and initializing an empty vertex buffer.
+    pub fn new(res: &Resources) -> Result<Self> {
+        let program = render_gl::Program::from_res(res, SHADER_PATH)?;
+        let vbo = buffer::ArrayBuffer::new();
+        vbo.bind();
+        vbo.dynamic_draw_data::<Vertex>(&[]);
+        let vao = buffer::VertexArray::new();
+        vao.bind();
+        Vertex::vertex_attrib_pointers();
+        vbo.unbind();
+        vao.unbind();
+        Ok(Self {
+            program,
+            vao,
+            vbo,
+            vertices: 0,
+        })
+    }
+
+    /// Replaces the line segments to draw.
+    pub fn set_lines(&mut self, lines: &[[na::Point3<f32>; 2]]) {
+        let vertices: Vec<Vertex> = lines
+            .iter()
+            .flatten()
+            .map(|p| Vertex {
+                pos: f32_f32_f32::from((p.x, p.y, p.z)),
+            })
+            .collect();
+        self.vbo.dynamic_draw_data(&vertices);
+        self.vbo.unbind();
+        self.vertices = vertices.len() as i32;
+    }
+
+    /// Draws the lines with the given model view projection matrix.
+    pub fn render(
+        &self,
+        viewport: &Viewport,
+        projection_matrix: &na::Matrix4<f32>,
+        color: [f32; 3],
+    ) {
+        if self.vertices == 0 {
+            return;
+        }
+        self.program.set_used();
+        viewport.set_used();
+        self.vao.bind();
+        // Safety: the vertex array holds `vertices` vertices of the layout set up in `new`.
+        unsafe {
+            self.program
+                .set_uniform_matrix4("projection_matrix", projection_matrix);
+            self.program.set_uniform_3f_arr("color", color);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::DrawArrays(gl::LINES, 0, self.vertices);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+        self.vao.unbind();
+        self.program.unset_used();
+    }
+
+    /// Check if the shader has been updated.
+    pub fn check_shader_update(&mut self, path: &std::path::Path, res: &Resources) -> bool {
+        let path = path.file_stem().map(|p| p.to_string_lossy().to_string());
+        if path == Some(SHADER_NAME.to_string()) {
+            match render_gl::Program::from_res(res, SHADER_PATH) {
+                Ok(program) => {
+                    self.program.unset_used();
+                    self.program = program;
+                    return true;
+                }
+                Err(e) => eprintln!("Shader reload error: {}", e),
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,258 @@
+//! Contains [Preset], the built-in combinations of shader attributes for common visualizations,
+//! and [PresetFile], the exact attributes, background colour and camera saved by the user.
+
+use crate::{
+    camera::CameraView,
+    model::{Attributes, DistanceShadingChannel},
+    resources,
+};
+use anyhow::{anyhow, Context, Result};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Describes visualization presets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Preset {
+    Aerial,
+    ChromaDepth,
+    #[default]
+    Plain,
+}
+
+impl Preset {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Preset::Plain => "Plain",
+            Preset::Aerial => "Aerial",
+            Preset::ChromaDepth => "Colored depth",
+        }
+    }
+
+    /// Applies the preset to the given attributes, leaving attributes it does not use as is.
+    pub fn apply(&self, preset: &mut Attributes) {
+        match self {
+            Preset::Plain => {
+                preset.toon_factor = 0.0;
+                preset.vertex_color_mix = 1.0;
+                preset.distance_shading_channel = DistanceShadingChannel::None;
+
+                preset.replace_shadows_with_hatching = true;
+                preset.hatching_depth = 0.75;
+                preset.hatching_steps = 150;
+                preset.hatching_frequency = 4;
+                preset.hatching_intensity = 0.75;
+            }
+            Preset::Aerial => {
+                preset.toon_factor = 0.0;
+                preset.vertex_color_mix = 1.0;
+                preset.distance_shading_channel = DistanceShadingChannel::Saturation;
+                preset.distance_shading_power = 0.6;
+
+                preset.replace_shadows_with_hatching = true;
+                preset.hatching_depth = 0.75;
+                preset.hatching_steps = 150;
+                preset.hatching_frequency = 4;
+                preset.hatching_intensity = 0.75;
+            }
+            Preset::ChromaDepth => {
+                preset.toon_factor = 0.0;
+                preset.distance_shading_channel = DistanceShadingChannel::Hue;
+                preset.distance_shading_power = 0.6;
+
+                preset.replace_shadows_with_hatching = true;
+                preset.hatching_depth = 0.75;
+                preset.hatching_steps = 150;
+                preset.hatching_frequency = 4;
+                preset.hatching_intensity = 0.75;
+            }
+        };
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_str(self.description())
+    }
+}
+
+/// A preset saved by the user, stored as TOML or, if the file has a `json` extension, as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresetFile {
+    /// Name shown in the user interface, taken from the file name.
+    #[serde(skip)]
+    pub name: String,
+    pub background: na::Vector3<f32>,
+    pub camera: CameraView,
+    pub attributes: Attributes,
+}
+
+impl Default for PresetFile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            background: na::Vector3::new(0.3, 0.3, 0.5),
+            camera: Default::default(),
+            attributes: Default::default(),
+        }
+    }
+}
+
+impl PresetFile {
+    /// Loads a preset file. Settings missing from the file keep their default values, while a
+    /// background colour outside 0..=1 is an error.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).context("Failed to read preset file.")?;
+        let mut preset: PresetFile = match is_json(path) {
+            true => serde_json::from_str(&text)?,
+            false => toml::from_str(&text)?,
+        };
+        if preset.background.iter().any(|c| !(0.0..=1.0).contains(c)) {
+            return Err(anyhow!(
+                "Background colour components must be between 0 and 1."
+            ));
+        }
+        preset.name = resources::file_stem(path);
+        Ok(preset)
+    }
+
+    /// Saves the preset, creating the directory if needed.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = match is_json(path) {
+            true => serde_json::to_string_pretty(self)?,
+            false => toml::to_string_pretty(self)?,
+        };
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).context("Failed to create preset directory.")?;
+        }
+        fs::write(path, text).context("Failed to write preset file.")
+    }
+
+    /// Applies the saved attributes, keeping those that depend on the camera, model or time.
+    pub fn apply(&self, attributes: &mut Attributes) {
+        *attributes = Attributes {
+            projection_matrix: attributes.projection_matrix,
+            camera_position: attributes.camera_position,
+            model_size: attributes.model_size,
+            elapsed: attributes.elapsed,
+            ..self.attributes.clone()
+        };
+    }
+}
+
+/// Checks if a preset file should be stored as JSON rather than TOML.
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+/// Gets the directory user presets are saved to and listed from.
+pub fn presets_dir() -> Option<PathBuf> {
+    resources::config_dir().map(|dir| dir.join("presets"))
+}
+
+/// Loads every preset in the presets directory, sorted by name. Files that fail to load are
+/// reported and skipped.
+pub fn load_user_presets() -> Vec<PresetFile> {
+    let entries = match presets_dir().map(fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        _ => return Vec::new(),
+    };
+    let mut presets: Vec<PresetFile> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("toml") || e.eq_ignore_ascii_case("json"))
+        })
+        .filter_map(|path| match PresetFile::load(&path) {
+            Ok(preset) => Some(preset),
+            Err(e) => {
+                eprintln!("Failed to load preset '{}': {:#}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    presets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ColorSource;
+
+    #[test]
+    fn preset_file_round_trip() {
+        let mut preset = PresetFile {
+            background: na::Vector3::new(0.1, 0.2, 0.3),
+            camera: CameraView {
+                azimuth: 30.0,
+                elevation: -10.0,
+                distance: Some(80.0),
+                target: na::Vector3::new(1.0, -2.0, 0.5),
+            },
+            ..Default::default()
+        };
+        Preset::ChromaDepth.apply(&mut preset.attributes);
+        preset.attributes.color_source = ColorSource::Flat;
+
+        let dir = std::env::temp_dir().join(format!("ivrigst_presets_{}", std::process::id()));
+        for file in ["vessels.toml", "vessels.json"] {
+            let path = dir.join(file);
+            preset.save(&path).unwrap();
+            let loaded = PresetFile::load(&path).unwrap();
+            assert_eq!(loaded.name, "vessels");
+            assert_eq!(loaded.background, preset.background);
+            assert_eq!(loaded.camera, preset.camera);
+            assert_eq!(
+                loaded.attributes.distance_shading_channel,
+                DistanceShadingChannel::Hue
+            );
+            assert_eq!(loaded.attributes.color_source, ColorSource::Flat);
+            assert_eq!(
+                loaded.attributes.light_position,
+                preset.attributes.light_position
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn partial_preset_file() {
+        let preset: PresetFile = toml::from_str(
+            "[attributes]\ntoon_factor = 0.25\ndistance_shading_channel = \"Value\"\n",
+        )
+        .unwrap();
+        assert_eq!(preset.attributes.toon_factor, 0.25);
+        assert_eq!(
+            preset.attributes.distance_shading_channel,
+            DistanceShadingChannel::Value
+        );
+        assert_eq!(preset.attributes.hatching_steps, 150);
+        assert_eq!(preset.camera, CameraView::default());
+
+        let mut attributes = Attributes {
+            model_size: 12.0,
+            ..Default::default()
+        };
+        preset.apply(&mut attributes);
+        assert_eq!(attributes.toon_factor, 0.25);
+        assert_eq!(attributes.model_size, 12.0);
+    }
+
+    #[test]
+    fn background_out_of_range() {
+        let dir = std::env::temp_dir().join(format!("ivrigst_bad_preset_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bright.toml");
+        fs::write(&path, "background = [0.5, 1.5, 0.5]\n").unwrap();
+        assert!(PresetFile::load(&path).is_err());
+        fs::write(&path, "background = [0.5, 1.0, 0.0]\n").unwrap();
+        assert!(PresetFile::load(&path).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -55,7 +55,7 @@ impl<const T: gl::types::GLuint> Buffer<T> {
         unsafe {
             gl::BufferData(
                 T,
-                (data.len() * ::std::mem::size_of::<S>()) as gl::types::GLsizeiptr,
+                ::std::mem::size_of_val(data) as gl::types::GLsizeiptr,
                 data.as_ptr() as *const gl::types::GLvoid,
                 usage,
             );
@@ -158,7 +158,7 @@ impl Texture {
 
             pixels.as_ptr() as *const std::ffi::c_void
         } else {
-            std::ptr::null() as *const std::ffi::c_void
+            std::ptr::null()
         };
 
         // Safety: the size of the data **MUST** be correct, as checked above.
@@ -219,7 +219,7 @@ impl Texture {
 
             pixels.as_ptr() as *const std::ffi::c_void
         } else {
-            std::ptr::null() as *const std::ffi::c_void
+            std::ptr::null()
         };
 
         // Safety: this isn't. The size of the data must be correct, and yet, we're writing to
@@ -330,6 +330,33 @@ impl FrameBuffer {
         }
     }
 
+    /// Checks if the framebuffer is complete, meaning it can be rendered to.
+    pub fn is_complete(&self) -> bool {
+        self.bind();
+        unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE }
+    }
+
+    /// Reads RGBA pixels from the read buffer of this framebuffer, bottom row first.
+    pub fn read_pixels(&self, dimensions: (u32, u32)) -> Vec<u8> {
+        let mut pixels = vec![0; dimensions.0 as usize * dimensions.1 as usize * 4];
+        self.bind();
+        // Safety: the buffer is allocated to fit the requested area with four bytes per pixel,
+        // and the pack alignment of four bytes always fits rows of RGBA pixels.
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+            gl::ReadPixels(
+                0,
+                0,
+                dimensions.0 as i32,
+                dimensions.1 as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut std::ffi::c_void,
+            );
+        }
+        pixels
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
@@ -77,6 +77,11 @@ impl Program {
         gl::UniformMatrix4fv(uniform_location, 1, gl::FALSE, data.as_ptr());
     }
 
+    pub unsafe fn set_uniform_4f_na(&self, uniform_id: &str, v: na::Vector4<f32>) {
+        let uniform_location = self.get_uniform_location(uniform_id);
+        gl::Uniform4f(uniform_location, v[0], v[1], v[2], v[3]);
+    }
+
     pub unsafe fn set_uniform_3f_na(&self, uniform_id: &str, v: na::Vector3<f32>) {
         self.set_uniform_3f(uniform_id, (v[0], v[1], v[2]))
     }
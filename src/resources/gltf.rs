@@ -0,0 +1,252 @@
+//! Loader for glTF 2.0 files, both `.gltf` with external or embedded buffers and binary `.glb`.
+//! Parsing is left to the [gltf] crate.
+
+use crate::mesh::Mesh;
+use anyhow::{anyhow, Context, Result};
+use gltf::mesh::Mode;
+use gltf::{buffer, Document, Gltf, Node};
+use nalgebra as na;
+use std::path::Path;
+
+/// Loads a glTF file, giving one mesh per primitive of every mesh instance in the scene.
+pub fn load_gltf(path: &Path, name: &str) -> Result<Vec<Mesh>> {
+    let gltf = Gltf::open(path).context("Failed to read glTF file.")?;
+    let buffers = gltf::import_buffers(&gltf.document, path.parent(), gltf.blob.clone())
+        .context("Failed to read glTF buffers.")?;
+    gltf_to_meshes(&gltf.document, &buffers, name)
+}
+
+/// Converts the triangle primitives of every node in the default scene to meshes, with the node
+/// transforms applied. Documents without a scene have their meshes loaded untransformed.
+fn gltf_to_meshes(document: &Document, buffers: &[buffer::Data], name: &str) -> Result<Vec<Mesh>> {
+    let mut meshes = Vec::new();
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => {
+            for node in scene.nodes() {
+                add_node(&mut meshes, node, na::Matrix4::identity(), buffers, name)?;
+            }
+        }
+        None => {
+            for mesh in document.meshes() {
+                let mesh_name = mesh.name().unwrap_or(name);
+                add_mesh(
+                    &mut meshes,
+                    mesh,
+                    na::Matrix4::identity(),
+                    buffers,
+                    mesh_name,
+                )?;
+            }
+        }
+    }
+
+    if meshes.is_empty() {
+        return Err(anyhow!("glTF file has no triangles!"));
+    }
+    Ok(meshes)
+}
+
+/// Adds the mesh of a node and all its children, accumulating their transforms.
+fn add_node(
+    meshes: &mut Vec<Mesh>,
+    node: Node,
+    parent_transform: na::Matrix4<f32>,
+    buffers: &[buffer::Data],
+    name: &str,
+) -> Result<()> {
+    let transform = parent_transform * na::Matrix4::from(node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        let mesh_name = node.name().or_else(|| mesh.name()).unwrap_or(name);
+        add_mesh(meshes, mesh, transform, buffers, mesh_name)?;
+    }
+    for child in node.children() {
+        add_node(meshes, child, transform, buffers, name)?;
+    }
+    Ok(())
+}
+
+/// Adds every triangle primitive of a mesh, transformed to scene space. Points and lines are
+/// not rendered and are ignored.
+fn add_mesh(
+    meshes: &mut Vec<Mesh>,
+    mesh: gltf::Mesh,
+    transform: na::Matrix4<f32>,
+    buffers: &[buffer::Data],
+    name: &str,
+) -> Result<()> {
+    let linear = transform.fixed_slice::<3, 3>(0, 0).into_owned();
+    let normal_matrix = linear
+        .try_inverse()
+        .map(|inverse| inverse.transpose())
+        .unwrap_or(linear);
+    // Mirroring transforms flip the winding of every triangle.
+    let mirrored = linear.determinant() < 0.0;
+
+    let primitive_count = mesh.primitives().len();
+    for (i, primitive) in mesh.primitives().enumerate() {
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|d| &d.0[..]));
+        let positions: Vec<[f32; 3]> = reader
+            .read_positions()
+            .ok_or_else(|| anyhow!("glTF primitive has no positions."))?
+            .collect();
+        let vertex_count = positions.len();
+
+        let corners: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..vertex_count as u32).collect(),
+        };
+        let mut indices = match primitive.mode() {
+            Mode::Triangles => corners,
+            Mode::TriangleStrip => (0..corners.len().saturating_sub(2))
+                .flat_map(|i| match i % 2 {
+                    0 => [corners[i], corners[i + 1], corners[i + 2]],
+                    _ => [corners[i + 1], corners[i], corners[i + 2]],
+                })
+                .collect(),
+            Mode::TriangleFan => (1..corners.len().saturating_sub(1))
+                .flat_map(|i| [corners[0], corners[i], corners[i + 1]])
+                .collect(),
+            _ => continue,
+        };
+        indices.truncate(indices.len() / 3 * 3);
+        if indices.iter().any(|&i| i as usize >= vertex_count) {
+            return Err(anyhow!("glTF primitive has an index out of bounds."));
+        }
+        if mirrored {
+            for triangle in indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+
+        let positions = positions
+            .into_iter()
+            .flat_map(|p| {
+                let p = transform.transform_point(&na::Point3::from(p));
+                [p.x, p.y, p.z]
+            })
+            .collect();
+        let normals = reader
+            .read_normals()
+            .map(|normals| {
+                normals
+                    .flat_map(|n| {
+                        let n = normal_matrix * na::Vector3::from(n);
+                        [n.x, n.y, n.z]
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let vertex_color = reader
+            .read_colors(0)
+            .map(|colors| colors.into_rgb_f32().flatten().collect())
+            .unwrap_or_default();
+        let material = primitive.material();
+        let material_color = material.index().map(|_| {
+            let [r, g, b, _] = material.pbr_metallic_roughness().base_color_factor();
+            [r, g, b]
+        });
+
+        meshes.push(Mesh {
+            name: match primitive_count {
+                1 => name.to_owned(),
+                _ => format!("{} ({})", name, i + 1),
+            },
+            positions,
+            normals,
+            vertex_color,
+            material_color,
+            indices,
+            scalars: Vec::new(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a GLB file of a single coloured triangle with indices, whose node is the child of
+    /// a translated node and is itself scaled.
+    fn triangle_glb() -> Vec<u8> {
+        let mut bin = Vec::new();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [1.0f32, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0] {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in [0u16, 1, 2, 0] {
+            bin.extend_from_slice(&index.to_le_bytes());
+        }
+
+        let mut json = format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "scene": 0,
+                "scenes": [{{"nodes": [0]}}],
+                "nodes": [
+                    {{"name": "parent", "translation": [1, 0, 0], "children": [1]}},
+                    {{"name": "triangle", "scale": [2, 2, 2], "mesh": 0}}
+                ],
+                "meshes": [{{"primitives": [{{
+                    "attributes": {{"POSITION": 0, "COLOR_0": 1}},
+                    "indices": 2
+                }}]}}],
+                "accessors": [
+                    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                      "min": [0, 0, 0], "max": [1, 1, 0]}},
+                    {{"bufferView": 0, "byteOffset": 36, "componentType": 5126, "count": 3,
+                      "type": "VEC3"}},
+                    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+                ],
+                "bufferViews": [
+                    {{"buffer": 0, "byteLength": 72}},
+                    {{"buffer": 0, "byteOffset": 72, "byteLength": 6}}
+                ],
+                "buffers": [{{"byteLength": {}}}]
+            }}"#,
+            bin.len()
+        );
+        while json.len() % 4 != 0 {
+            json.push(' ');
+        }
+
+        let mut glb = Vec::new();
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(json.as_bytes());
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    #[test]
+    fn glb_with_node_transforms() {
+        let gltf = Gltf::from_slice(&triangle_glb()).unwrap();
+        let buffers = gltf::import_buffers(&gltf.document, None, gltf.blob.clone()).unwrap();
+        let meshes = gltf_to_meshes(&gltf.document, &buffers, "default").unwrap();
+        assert_eq!(meshes.len(), 1);
+        let mesh = &meshes[0];
+        assert_eq!(mesh.name, "triangle");
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        assert_eq!(
+            mesh.positions,
+            vec![1.0, 0.0, 0.0, 3.0, 0.0, 0.0, 1.0, 2.0, 0.0]
+        );
+        assert_eq!(
+            mesh.vertex_color,
+            vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]
+        );
+        assert!(mesh.normals.is_empty());
+        assert_eq!(mesh.material_color, None);
+    }
+}
@@ -0,0 +1,245 @@
+//! This module contains the [Resources] struct, which finds and watches the resources directory
+//! containing models and shaders and provides functions to easily parse them into memory.
+
+mod gltf;
+mod ply;
+mod stl;
+mod volume;
+mod vtk;
+
+use crate::{
+    mesh::Mesh,
+    volume::{Label, Volume},
+};
+use anyhow::{anyhow, Context, Result};
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+use std::{
+    ffi, fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// File extensions of the supported model formats.
+const MODEL_EXTENSIONS: [&str; 7] = ["obj", "stl", "ply", "vtk", "vtp", "gltf", "glb"];
+/// File name endings of the supported volume formats.
+const VOLUME_EXTENSIONS: [&str; 3] = [".nii", ".nii.gz", ".raw"];
+/// File extensions of label map lookup tables, which are looked for next to their volume.
+const COLOR_TABLE_EXTENSIONS: [&str; 2] = ["ctbl", "txt"];
+
+/// Find and watches the resources directory containing models and shaders.
+pub struct Resources {
+    root_path: PathBuf,
+    _watcher: notify::RecommendedWatcher,
+    rx: Receiver<DebouncedEvent>,
+}
+
+impl Resources {
+    /// Initializes a [Resources] struct, attempting to find the resources directory and beginning
+    /// to watch for file changes.
+    pub fn from_relative_exe_path(rel_path: &Path) -> Result<Resources> {
+        let exe_file_name = ::std::env::current_exe().context("No exe filename")?;
+        let exe_path = exe_file_name.parent().context("No exe parent")?;
+        Self::from_path(&exe_path.join(rel_path))
+    }
+
+    /// Initializes a [Resources] struct for the given resources directory, relative to the
+    /// working directory, and begins to watch for file changes.
+    pub fn from_path(path: &Path) -> Result<Resources> {
+        let root_path = fs::canonicalize(path)
+            .with_context(|| format!("Resources directory '{}' not found", path.display()))?;
+
+        let (tx, rx) = channel();
+        let mut watcher = watcher(tx, Duration::from_millis(250))?;
+        watcher.watch(&root_path, RecursiveMode::Recursive)?;
+
+        Ok(Resources {
+            root_path,
+            _watcher: watcher,
+            rx,
+        })
+    }
+
+    /// Lists all changed files since last check.
+    pub fn updated_paths(&self) -> Vec<PathBuf> {
+        let mut events = Vec::new();
+        match self.rx.try_recv() {
+            Ok(DebouncedEvent::Write(path)) => events.push(path),
+            Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            Err(e) => eprintln!("File watch error: {:?}", e),
+            _ => {}
+        }
+        events
+    }
+
+    /// Attempts to load the given text file.
+    pub fn load_cstring(&self, resource_name: &str) -> Result<ffi::CString> {
+        let mut file = fs::File::open(resource_name_to_path(&self.root_path, resource_name))
+            .context("Failed to open resource file")?;
+
+        // allocate buffer of the same size as file
+        let mut buffer: Vec<u8> = Vec::with_capacity(file.metadata()?.len() as usize + 1);
+        file.read_to_end(&mut buffer)?;
+
+        // check for nul byte
+        if buffer.contains(&0) {
+            return Err(anyhow!("Resource contains NUL byte."));
+        }
+
+        Ok(unsafe { ffi::CString::from_vec_unchecked(buffer) })
+    }
+
+    /// Attempts to load the given model file, returning every object found in it. The file format
+    /// is chosen by file extension.
+    pub fn load_model(&self, resource_name: &str) -> Result<Vec<Mesh>> {
+        load_model_file(&resource_name_to_path(&self.root_path, resource_name))
+    }
+
+    /// Gets the path of the given resource, which is either absolute or relative to the resources
+    /// directory.
+    pub fn path_of(&self, resource_name: &str) -> PathBuf {
+        resource_name_to_path(&self.root_path, resource_name)
+    }
+
+    /// Checks if the given resource is a volume file, to be loaded with [Resources::load_volume]
+    /// rather than [Resources::load_model].
+    pub fn is_volume(&self, resource_name: &str) -> bool {
+        let name = resource_name.to_lowercase();
+        VOLUME_EXTENSIONS.iter().any(|e| name.ends_with(e))
+    }
+
+    /// Attempts to load the given volume file. The file format is chosen by file extension.
+    pub fn load_volume(&self, resource_name: &str) -> Result<Volume> {
+        let path = resource_name_to_path(&self.root_path, resource_name);
+        let data = fs::read(&path).context("Failed to read volume file.")?;
+        match lowercase_extension(&path).as_str() {
+            "raw" => volume::parse_raw(&data, &file_stem(&path)),
+            _ => volume::parse_nifti(&data),
+        }
+    }
+
+    /// Attempts to load the colour lookup table of the given label map volume, which must have
+    /// the same name as the volume with a `.ctbl` or `.txt` extension. Gives an empty table if no
+    /// such file exists.
+    pub fn load_color_table(&self, volume_resource_name: &str) -> Result<Vec<Label>> {
+        let path = resource_name_to_path(&self.root_path, volume_resource_name);
+        let stem = file_stem(&path);
+        for extension in COLOR_TABLE_EXTENSIONS {
+            let table_path = path.with_file_name(format!("{}.{}", stem, extension));
+            if table_path.is_file() {
+                let text =
+                    fs::read_to_string(&table_path).context("Failed to read colour table file.")?;
+                return volume::parse_color_table(&text);
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// Lists all models and volumes found in the root resource directory.
+    pub fn list_models(&self) -> Vec<String> {
+        std::fs::read_dir(&self.root_path)
+            .and_then(|readdir| {
+                readdir
+                    .map(|entry| entry.map(|d| d.file_name().to_string_lossy().into_owned()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|entry| is_supported_file(Path::new(entry)))
+            .collect()
+    }
+}
+
+/// Checks if the given file is a model or volume in one of the supported formats, judging by its
+/// file extension.
+pub fn is_supported_file(path: &Path) -> bool {
+    let name = path.to_string_lossy().to_lowercase();
+    MODEL_EXTENSIONS.contains(&lowercase_extension(path).as_str())
+        || VOLUME_EXTENSIONS.iter().any(|e| name.ends_with(e))
+}
+
+/// Gets the directory for user settings that persist between sessions, such as
+/// `~/.config/ivrigst` on Linux. The directory is not created.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    base.map(|base| base.join("ivrigst"))
+}
+
+/// Attempts to load a model file at any path, returning every object found in it. The file format
+/// is chosen by file extension.
+pub fn load_model_file(path: &Path) -> Result<Vec<Mesh>> {
+    let extension = lowercase_extension(path);
+    match extension.as_str() {
+        "obj" => load_obj(path),
+        "stl" => stl::parse_stl(&fs::read(path)?, &file_stem(path)),
+        "ply" => Ok(vec![ply::parse_ply(&fs::read(path)?, &file_stem(path))?]),
+        "vtk" | "vtp" => vtk::load_vtk(path, &file_stem(path)),
+        "gltf" | "glb" => gltf::load_gltf(path, &file_stem(path)),
+        _ => Err(anyhow!("Unsupported model format '{}'.", extension)),
+    }
+}
+
+/// Attempts to load the given obj file, returning every object found in it.
+fn load_obj(path: &Path) -> Result<Vec<Mesh>> {
+    let settings = tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
+        ..Default::default()
+    };
+    let (models, materials) = tobj::load_obj(path, &settings)?;
+    if models.is_empty() {
+        return Err(anyhow!("Obj file has no model!"));
+    }
+    let materials = materials.unwrap_or_else(|e| {
+        eprintln!("Failed to load materials: {}", e);
+        Vec::new()
+    });
+
+    Ok(models
+        .into_iter()
+        .map(|model| Mesh::from_obj(model, &materials))
+        .collect())
+}
+
+/// Gets the file extension in lowercase, or an empty string if there is none.
+fn lowercase_extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Gets the file name without extension, used to name objects in formats without object names.
+/// The `.nii` of gzipped NIfTI files is removed as well.
+pub fn file_stem(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match stem.to_lowercase().strip_suffix(".nii") {
+        Some(nifti_stem) => stem[..nifti_stem.len()].to_owned(),
+        None => stem,
+    }
+}
+
+/// Joins a location string to a root directory path.
+fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
+    // Files outside the resources directory are given by absolute path.
+    if Path::new(location).is_absolute() {
+        return location.into();
+    }
+
+    let mut path: PathBuf = root_dir.into();
+
+    for part in location.split('/') {
+        path = path.join(part);
+    }
+
+    path
+}
@@ -0,0 +1,452 @@
+//! Loader for PLY files, in the ASCII, binary little endian and binary big endian variants.
+
+use crate::mesh::{Mesh, ScalarField};
+use anyhow::{anyhow, Context, Result};
+
+/// Vertex properties read into the [Mesh] vertex data rather than kept as scalar fields.
+const POSITION_PROPERTIES: [&str; 3] = ["x", "y", "z"];
+const NORMAL_PROPERTIES: [&str; 3] = ["nx", "ny", "nz"];
+const COLOR_PROPERTIES: [[&str; 3]; 3] = [
+    ["red", "green", "blue"],
+    ["r", "g", "b"],
+    ["diffuse_red", "diffuse_green", "diffuse_blue"],
+];
+const IGNORED_PROPERTIES: [&str; 3] = ["alpha", "a", "diffuse_alpha"];
+/// Names used for the vertex index list of a face.
+const FACE_INDEX_PROPERTIES: [&str; 2] = ["vertex_indices", "vertex_index"];
+
+/// Encoding of the data following the header.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Data types a property value may be stored as.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            _ => return Err(anyhow!("Unknown PLY property type '{}'.", name)),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    /// Value representing full intensity for colours stored as this type.
+    fn color_max(self) -> f32 {
+        match self {
+            ScalarType::U8 => u8::MAX as f32,
+            ScalarType::U16 => u16::MAX as f32,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PropertyKind {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads property values from the body of the file, in either encoding.
+enum Body<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary {
+        data: &'a [u8],
+        offset: usize,
+        big_endian: bool,
+    },
+}
+
+impl<'a> Body<'a> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens
+                    .next()
+                    .ok_or_else(|| anyhow!("PLY file ended unexpectedly."))?;
+                token
+                    .parse()
+                    .with_context(|| format!("Invalid PLY value '{}'.", token))
+            }
+            Body::Binary {
+                data,
+                offset,
+                big_endian,
+            } => {
+                let bytes = data
+                    .get(*offset..*offset + ty.size())
+                    .ok_or_else(|| anyhow!("PLY file ended unexpectedly."))?;
+                *offset += ty.size();
+                let mut buffer = [0; 8];
+                buffer[..bytes.len()].copy_from_slice(bytes);
+                if *big_endian {
+                    buffer[..bytes.len()].reverse();
+                }
+                let [b0, b1, b2, b3, ..] = buffer;
+                Ok(match ty {
+                    ScalarType::I8 => b0 as i8 as f64,
+                    ScalarType::U8 => b0 as f64,
+                    ScalarType::I16 => i16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(buffer),
+                })
+            }
+        }
+    }
+
+    fn read_property(&mut self, kind: &PropertyKind, values: &mut Vec<f64>) -> Result<()> {
+        values.clear();
+        match *kind {
+            PropertyKind::Scalar(ty) => values.push(self.read(ty)?),
+            PropertyKind::List { count, item } => {
+                let count = self.read(count)? as usize;
+                for _ in 0..count {
+                    values.push(self.read(item)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a PLY file into a single mesh named `name`. Vertex positions, normals and colours are
+/// read into the vertex data, while any other scalar vertex property is kept as a
+/// [ScalarField]. Faces with more than three corners are triangulated as fans.
+pub fn parse_ply(data: &[u8], name: &str) -> Result<Mesh> {
+    let (format, elements, header_length) = parse_header(data)?;
+    let mut body = match format {
+        Format::Ascii => Body::Ascii(
+            std::str::from_utf8(&data[header_length..])
+                .context("ASCII PLY file is not valid text.")?
+                .split_ascii_whitespace(),
+        ),
+        Format::BinaryLittleEndian | Format::BinaryBigEndian => Body::Binary {
+            data,
+            offset: header_length,
+            big_endian: format == Format::BinaryBigEndian,
+        },
+    };
+
+    let mut mesh = Mesh {
+        name: name.to_owned(),
+        ..Default::default()
+    };
+    let mut values = Vec::new();
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(&mut body, element, &mut mesh)?,
+            "face" => {
+                let indices_property = element
+                    .properties
+                    .iter()
+                    .position(|p| FACE_INDEX_PROPERTIES.contains(&p.name.as_str()));
+                for _ in 0..element.count {
+                    for (i, property) in element.properties.iter().enumerate() {
+                        body.read_property(&property.kind, &mut values)?;
+                        if Some(i) == indices_property {
+                            for corner in 1..values.len().saturating_sub(1) {
+                                mesh.indices.extend([
+                                    values[0] as u32,
+                                    values[corner] as u32,
+                                    values[corner + 1] as u32,
+                                ]);
+                            }
+                        }
+                    }
+                }
+            }
+            // Other elements, such as edges, are skipped.
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        body.read_property(&property.kind, &mut values)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if mesh.indices.is_empty() {
+        return Err(anyhow!("PLY file has no faces!"));
+    }
+    let vertex_count = mesh.vertex_count() as u32;
+    if mesh.indices.iter().any(|&i| i >= vertex_count) {
+        return Err(anyhow!("PLY file has a face index out of bounds."));
+    }
+    Ok(mesh)
+}
+
+/// Reads the vertex element into the mesh.
+fn read_vertices(body: &mut Body, element: &Element, mesh: &mut Mesh) -> Result<()> {
+    let find = |name: &str| element.properties.iter().position(|p| p.name == name);
+    let find_all = |names: [&str; 3]| -> Option<[usize; 3]> {
+        Some([find(names[0])?, find(names[1])?, find(names[2])?])
+    };
+
+    let position = find_all(POSITION_PROPERTIES)
+        .ok_or_else(|| anyhow!("PLY file has no vertex positions."))?;
+    let normal = find_all(NORMAL_PROPERTIES);
+    let color = COLOR_PROPERTIES.into_iter().find_map(find_all);
+    let color_max = match color.map(|c| &element.properties[c[0]].kind) {
+        Some(PropertyKind::Scalar(ty)) => ty.color_max(),
+        _ => 1.0,
+    };
+
+    // Every remaining scalar property becomes a scalar field.
+    let mut scalar_properties = Vec::new();
+    for (i, property) in element.properties.iter().enumerate() {
+        let used = position.contains(&i)
+            || normal.is_some_and(|n| n.contains(&i))
+            || color.is_some_and(|c| c.contains(&i))
+            || IGNORED_PROPERTIES.contains(&property.name.as_str());
+        if !used && matches!(property.kind, PropertyKind::Scalar(_)) {
+            scalar_properties.push(i);
+            mesh.scalars.push(ScalarField {
+                name: property.name.clone(),
+                values: Vec::new(),
+            });
+        }
+    }
+
+    let mut row = vec![0.0; element.properties.len()];
+    let mut values = Vec::new();
+    for _ in 0..element.count {
+        for (value, property) in row.iter_mut().zip(&element.properties) {
+            body.read_property(&property.kind, &mut values)?;
+            *value = values.first().copied().unwrap_or_default();
+        }
+        mesh.positions.extend(position.map(|i| row[i] as f32));
+        if let Some(normal) = normal {
+            mesh.normals.extend(normal.map(|i| row[i] as f32));
+        }
+        if let Some(color) = color {
+            mesh.vertex_color
+                .extend(color.map(|i| row[i] as f32 / color_max));
+        }
+        for (field, &i) in mesh.scalars.iter_mut().zip(&scalar_properties) {
+            field.values.push(row[i] as f32);
+        }
+    }
+    Ok(())
+}
+
+/// Parses the header, returning the body format, the elements and the length of the header in
+/// bytes.
+fn parse_header(data: &[u8]) -> Result<(Format, Vec<Element>, usize)> {
+    const END_HEADER: &[u8] = b"end_header";
+    let end = data
+        .windows(END_HEADER.len())
+        .position(|window| window == END_HEADER)
+        .ok_or_else(|| anyhow!("PLY file has no end_header."))?;
+    // The body starts after the line break following `end_header`.
+    let header_length = data[end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|i| end + i + 1)
+        .unwrap_or(data.len());
+    let header = std::str::from_utf8(&data[..end]).context("PLY header is not valid text.")?;
+
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(anyhow!("Not a PLY file."));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(anyhow!("Unknown PLY format '{}'.", name)),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .with_context(|| format!("Invalid PLY element count '{}'.", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("PLY property before any element."))?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::List {
+                        count: ScalarType::parse(count)?,
+                        item: ScalarType::parse(item)?,
+                    },
+                });
+            }
+            ["property", ty, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("PLY property before any element."))?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::Scalar(ScalarType::parse(ty)?),
+                });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(anyhow!("Invalid PLY header line '{}'.", line)),
+        }
+    }
+
+    let format = format.ok_or_else(|| anyhow!("PLY file has no format."))?;
+    Ok((format, elements, header_length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float thickness
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    fn binary_square(big_endian: bool) -> Vec<u8> {
+        let format = match big_endian {
+            true => "binary_big_endian",
+            false => "binary_little_endian",
+        };
+        let mut data = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+        let f32_bytes = |v: f32| match big_endian {
+            true => v.to_be_bytes(),
+            false => v.to_le_bytes(),
+        };
+        for (i, [x, y]) in [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]
+            .into_iter()
+            .enumerate()
+        {
+            for v in [x, y, 0.0] {
+                data.extend(f32_bytes(v));
+            }
+            data.extend([255, 0, 51]);
+            data.extend(f32_bytes(i as f32 * 0.5));
+        }
+        data.push(4);
+        for i in 0..4i32 {
+            data.extend(match big_endian {
+                true => i.to_be_bytes(),
+                false => i.to_le_bytes(),
+            });
+        }
+        data
+    }
+
+    fn assert_square(mesh: &Mesh) {
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.positions[6..9], [1.0, 1.0, 0.0]);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertex_color[0..3], [1.0, 0.0, 0.2]);
+        assert!(mesh.normals.is_empty());
+        assert_eq!(mesh.scalars.len(), 1);
+        assert_eq!(mesh.scalars[0].name, "thickness");
+        assert_eq!(mesh.scalars[0].values, vec![0.0, 0.5, 1.0, 1.5]);
+    }
+
+    #[test]
+    fn ascii() {
+        let text = format!(
+            "ply\nformat ascii 1.0\ncomment test\n{}{}",
+            HEADER,
+            "0 0 0 255 0 51 0\n1 0 0 255 0 51 0.5\n1 1 0 255 0 51 1\n0 1 0 255 0 51 1.5\n4 0 1 2 3\n"
+        );
+        assert_square(&parse_ply(text.as_bytes(), "square").unwrap());
+    }
+
+    #[test]
+    fn binary_little_endian() {
+        assert_square(&parse_ply(&binary_square(false), "square").unwrap());
+    }
+
+    #[test]
+    fn binary_big_endian() {
+        assert_square(&parse_ply(&binary_square(true), "square").unwrap());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut data = binary_square(false);
+        data.truncate(data.len() - 2);
+        assert!(parse_ply(&data, "square").is_err());
+    }
+
+    #[test]
+    fn vertex_count_beyond_body() {
+        let text = format!(
+            "ply\nformat ascii 1.0\n{}{}",
+            HEADER.replace("element vertex 4", "element vertex 4000000000"),
+            "0 0 0 0 0 0 0\n"
+        );
+        assert!(parse_ply(text.as_bytes(), "square").is_err());
+    }
+
+    #[test]
+    fn index_out_of_bounds() {
+        let text = format!(
+            "ply\nformat ascii 1.0\n{}{}",
+            HEADER, "0 0 0 0 0 0 0\n1 0 0 0 0 0 0\n1 1 0 0 0 0 0\n0 1 0 0 0 0 0\n3 0 1 4\n"
+        );
+        assert!(parse_ply(text.as_bytes(), "square").is_err());
+    }
+}
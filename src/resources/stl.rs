@@ -0,0 +1,197 @@
+//! Loader for STL files, in both the binary and the ASCII variant.
+
+use crate::mesh::Mesh;
+use anyhow::{anyhow, Context, Result};
+
+/// Size of the binary STL header, including the triangle count.
+const BINARY_HEADER_SIZE: usize = 84;
+/// Size of a single triangle record in a binary STL file.
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+/// Parses an STL file, welding duplicate vertices into indexed meshes. An ASCII file gives one
+/// mesh per `solid` block, a binary file always gives a single mesh named `name`.
+pub fn parse_stl(data: &[u8], name: &str) -> Result<Vec<Mesh>> {
+    if is_binary(data) {
+        let corners = parse_binary(data)?;
+        Ok(vec![Mesh::from_triangle_soup(name.to_owned(), &corners)])
+    } else {
+        let text = std::str::from_utf8(data).context("STL file is neither binary nor text.")?;
+        parse_ascii(text, name)
+    }
+}
+
+/// Binary files may also start with `solid`, so the file size is checked against the triangle
+/// count first.
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() >= BINARY_HEADER_SIZE {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if data.len() == BINARY_HEADER_SIZE + count * BINARY_TRIANGLE_SIZE {
+            return true;
+        }
+    }
+    !data.trim_ascii_start().starts_with(b"solid")
+}
+
+/// Reads the triangle corners of a binary STL file.
+fn parse_binary(data: &[u8]) -> Result<Vec<[f32; 3]>> {
+    if data.len() < BINARY_HEADER_SIZE {
+        return Err(anyhow!("Binary STL file is missing its header."));
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    let triangles = &data[BINARY_HEADER_SIZE..];
+    if triangles.len() < count * BINARY_TRIANGLE_SIZE {
+        return Err(anyhow!(
+            "Binary STL file is truncated: expected {} triangles.",
+            count
+        ));
+    }
+
+    let read_f32 = |bytes: &[u8]| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let mut corners = Vec::with_capacity(count * 3);
+    for triangle in triangles.chunks_exact(BINARY_TRIANGLE_SIZE).take(count) {
+        // Each record is a facet normal, three corners and a two byte attribute count. The
+        // facet normal is ignored, as smooth normals are generated for the welded mesh.
+        for corner in triangle[12..48].chunks_exact(12) {
+            corners.push([
+                read_f32(&corner[0..4]),
+                read_f32(&corner[4..8]),
+                read_f32(&corner[8..12]),
+            ]);
+        }
+    }
+    Ok(corners)
+}
+
+/// Reads every `solid` of an ASCII STL file into its own mesh.
+fn parse_ascii(text: &str, default_name: &str) -> Result<Vec<Mesh>> {
+    let mut meshes = Vec::new();
+    let mut solid: Option<(String, Vec<[f32; 3]>)> = None;
+
+    for (line_number, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("solid") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                let name = match name.is_empty() {
+                    true => default_name.to_owned(),
+                    false => name,
+                };
+                solid = Some((name, Vec::new()));
+            }
+            Some("vertex") => {
+                let (_, corners) = solid.as_mut().ok_or_else(|| {
+                    anyhow!("Vertex outside of solid on line {}.", line_number + 1)
+                })?;
+                let mut corner = [0.0; 3];
+                for value in corner.iter_mut() {
+                    *value = tokens
+                        .next()
+                        .and_then(|t| t.parse().ok())
+                        .ok_or_else(|| anyhow!("Invalid vertex on line {}.", line_number + 1))?;
+                }
+                corners.push(corner);
+            }
+            Some("endsolid") => {
+                if let Some((name, corners)) = solid.take() {
+                    meshes.push(Mesh::from_triangle_soup(name, &corners));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Be lenient towards files missing the final `endsolid`.
+    if let Some((name, corners)) = solid {
+        meshes.push(Mesh::from_triangle_soup(name, &corners));
+    }
+
+    meshes.retain(|mesh| !mesh.indices.is_empty());
+    if meshes.is_empty() {
+        return Err(anyhow!("STL file has no triangles!"));
+    }
+    if meshes.iter().any(|mesh| mesh.indices.len() % 3 != 0) {
+        return Err(anyhow!("STL file has an incomplete facet."));
+    }
+    Ok(meshes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TETRAHEDRON: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+    ];
+    const FACES: [[usize; 3]; 4] = [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]];
+
+    fn binary_tetrahedron(header: &[u8]) -> Vec<u8> {
+        let mut data = header.to_vec();
+        data.resize(80, 0);
+        data.extend_from_slice(&(FACES.len() as u32).to_le_bytes());
+        for face in FACES {
+            data.extend_from_slice(&[0; 12]);
+            for corner in face {
+                for value in TETRAHEDRON[corner] {
+                    data.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            data.extend_from_slice(&[0; 2]);
+        }
+        data
+    }
+
+    #[test]
+    fn binary_welds_vertices() {
+        let meshes = parse_stl(&binary_tetrahedron(b"binary"), "tetrahedron").unwrap();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].name, "tetrahedron");
+        assert_eq!(meshes[0].vertex_count(), 4);
+        assert_eq!(meshes[0].indices.len(), 12);
+    }
+
+    #[test]
+    fn binary_with_solid_header() {
+        let meshes = parse_stl(&binary_tetrahedron(b"solid exported"), "tetrahedron").unwrap();
+        assert_eq!(meshes[0].vertex_count(), 4);
+    }
+
+    #[test]
+    fn binary_truncated() {
+        let mut data = binary_tetrahedron(b"binary");
+        data.truncate(data.len() - 10);
+        assert!(parse_binary(&data).is_err());
+    }
+
+    #[test]
+    fn ascii_multiple_solids() {
+        let mut text = String::new();
+        for name in ["artery", "vein"] {
+            text.push_str(&format!("solid {}\n", name));
+            for face in FACES {
+                text.push_str("  facet normal 0 0 0\n    outer loop\n");
+                for corner in face {
+                    let [x, y, z] = TETRAHEDRON[corner];
+                    text.push_str(&format!("      vertex {} {} {}\n", x, y, z));
+                }
+                text.push_str("    endloop\n  endfacet\n");
+            }
+            text.push_str(&format!("endsolid {}\n", name));
+        }
+
+        let meshes = parse_stl(text.as_bytes(), "default").unwrap();
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].name, "artery");
+        assert_eq!(meshes[1].name, "vein");
+        assert_eq!(meshes[1].vertex_count(), 4);
+        assert_eq!(meshes[1].indices, meshes[0].indices);
+    }
+
+    #[test]
+    fn ascii_invalid_vertex() {
+        let text = "solid\nfacet normal 0 0 0\nouter loop\nvertex 0 zero 0\n";
+        assert!(parse_stl(text.as_bytes(), "default").is_err());
+    }
+}
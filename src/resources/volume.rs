@@ -0,0 +1,296 @@
+//! Loaders for scalar volumes, either NIfTI-1 files (`.nii`, optionally gzipped) or headerless
+//! raw files whose dimensions and sample type are given in the file name, and for the colour
+//! lookup tables naming the labels of label map volumes.
+
+use crate::volume::{Label, Volume};
+use anyhow::{anyhow, Context, Result};
+use std::io::Read;
+
+/// Size of the NIfTI-1 header.
+const NIFTI_HEADER_SIZE: usize = 348;
+/// The first two bytes of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Types of the samples stored in a volume file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SampleType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    F64,
+}
+
+impl SampleType {
+    /// Size of a single sample in bytes.
+    fn size(&self) -> usize {
+        match self {
+            SampleType::U8 | SampleType::I8 => 1,
+            SampleType::U16 | SampleType::I16 => 2,
+            SampleType::U32 | SampleType::I32 | SampleType::F32 => 4,
+            SampleType::F64 => 8,
+        }
+    }
+
+    /// Reads a single sample from the start of `bytes`.
+    fn read(&self, bytes: &[u8], big_endian: bool) -> f32 {
+        macro_rules! read {
+            ($t:ty, $n:literal) => {{
+                let mut array = [0; $n];
+                array.copy_from_slice(&bytes[..$n]);
+                match big_endian {
+                    true => <$t>::from_be_bytes(array) as f32,
+                    false => <$t>::from_le_bytes(array) as f32,
+                }
+            }};
+        }
+        match self {
+            SampleType::U8 => bytes[0] as f32,
+            SampleType::I8 => bytes[0] as i8 as f32,
+            SampleType::U16 => read!(u16, 2),
+            SampleType::I16 => read!(i16, 2),
+            SampleType::U32 => read!(u32, 4),
+            SampleType::I32 => read!(i32, 4),
+            SampleType::F32 => read!(f32, 4),
+            SampleType::F64 => read!(f64, 8),
+        }
+    }
+}
+
+/// Parses a single-file NIfTI-1 volume, decompressing it first if it is gzipped. Only the first
+/// 3D volume of a time series is read, and the orientation in the header is ignored.
+pub fn parse_nifti(data: &[u8]) -> Result<Volume> {
+    let mut decompressed = Vec::new();
+    let data = match data.starts_with(&GZIP_MAGIC) {
+        true => {
+            flate2::read::MultiGzDecoder::new(data)
+                .read_to_end(&mut decompressed)
+                .context("Failed to decompress NIfTI file.")?;
+            &decompressed[..]
+        }
+        false => data,
+    };
+    if data.len() < NIFTI_HEADER_SIZE {
+        return Err(anyhow!("NIfTI file is missing its header."));
+    }
+
+    // The header size field doubles as a byte order marker.
+    let big_endian = match data[0..4].try_into().unwrap() {
+        size if i32::from_le_bytes(size) == NIFTI_HEADER_SIZE as i32 => false,
+        size if i32::from_be_bytes(size) == NIFTI_HEADER_SIZE as i32 => true,
+        _ => return Err(anyhow!("Not a NIfTI-1 file.")),
+    };
+    if &data[344..347] != b"n+1" {
+        return Err(anyhow!("Only single-file NIfTI-1 volumes are supported."));
+    }
+    let read_i16 = |offset: usize| SampleType::I16.read(&data[offset..], big_endian) as i32;
+    let read_f32 = |offset: usize| SampleType::F32.read(&data[offset..], big_endian);
+
+    let dimensions = read_i16(40);
+    if !(3..=7).contains(&dimensions) {
+        return Err(anyhow!("NIfTI file is not a 3D volume."));
+    }
+    let mut dims = [0; 3];
+    let mut spacing = [1.0; 3];
+    for axis in 0..3 {
+        dims[axis] = read_i16(42 + axis * 2).max(0) as usize;
+        let pixdim = read_f32(80 + axis * 4).abs();
+        if pixdim.is_normal() {
+            spacing[axis] = pixdim;
+        }
+    }
+
+    let sample_type = match read_i16(70) {
+        2 => SampleType::U8,
+        4 => SampleType::I16,
+        8 => SampleType::I32,
+        16 => SampleType::F32,
+        64 => SampleType::F64,
+        256 => SampleType::I8,
+        512 => SampleType::U16,
+        768 => SampleType::U32,
+        other => return Err(anyhow!("Unsupported NIfTI data type {}.", other)),
+    };
+    let offset = (read_f32(108) as usize).max(NIFTI_HEADER_SIZE);
+    let mut volume = read_samples(
+        &data[offset.min(data.len())..],
+        dims,
+        sample_type,
+        big_endian,
+    )?;
+    volume.spacing = spacing;
+
+    // Stored values are scaled to their real values, unless the slope is zero.
+    let (slope, intercept) = (read_f32(112), read_f32(116));
+    if slope != 0.0 && slope.is_finite() && intercept.is_finite() {
+        for value in volume.data.iter_mut() {
+            *value = *value * slope + intercept;
+        }
+    }
+    Ok(volume)
+}
+
+/// Parses a headerless little-endian raw volume. The file name must end in the dimensions and
+/// sample type, as in `head_256x256x128_uint16.raw`.
+pub fn parse_raw(data: &[u8], file_stem: &str) -> Result<Volume> {
+    let error = || {
+        anyhow!(
+            "Raw volume names must end with dimensions and type, like 'name_256x256x128_uint8'."
+        )
+    };
+    let mut parts = file_stem.rsplit('_');
+    let sample_type = match parts.next().ok_or_else(error)? {
+        "uint8" => SampleType::U8,
+        "int8" => SampleType::I8,
+        "uint16" => SampleType::U16,
+        "int16" => SampleType::I16,
+        "uint32" => SampleType::U32,
+        "int32" => SampleType::I32,
+        "float32" | "float" => SampleType::F32,
+        "float64" | "double" => SampleType::F64,
+        _ => return Err(error()),
+    };
+    let dims: Vec<usize> = parts
+        .next()
+        .ok_or_else(error)?
+        .split('x')
+        .map(|d| d.parse().map_err(|_| error()))
+        .collect::<Result<_>>()?;
+    let dims: [usize; 3] = dims.try_into().map_err(|_| error())?;
+    read_samples(data, dims, sample_type, false)
+}
+
+/// Parses a colour lookup table in the format used by 3D Slicer, where each line holds a label
+/// value, its name and an RGBA colour in the 0-255 range. Lines starting with `#` are comments.
+pub fn parse_color_table(text: &str) -> Result<Vec<Label>> {
+    let mut labels = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = || anyhow!("Invalid colour table entry on line {}.", line_number + 1);
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        // Names may contain spaces, so the colour is read from the end of the line.
+        let color_start = match tokens.len() {
+            0..=4 => return Err(error()),
+            5 => 2,
+            n => n - 4,
+        };
+        let mut color = [0.0; 3];
+        for (channel, token) in color.iter_mut().zip(&tokens[color_start..]) {
+            *channel = token.parse::<f32>().map_err(|_| error())? / u8::MAX as f32;
+        }
+        labels.push(Label {
+            value: tokens[0].parse().map_err(|_| error())?,
+            name: tokens[1..color_start].join(" "),
+            color,
+        });
+    }
+    Ok(labels)
+}
+
+/// Reads the samples of a volume of the given dimensions, ignoring any trailing data.
+fn read_samples(
+    data: &[u8],
+    dims: [usize; 3],
+    sample_type: SampleType,
+    big_endian: bool,
+) -> Result<Volume> {
+    let count = dims[0] * dims[1] * dims[2];
+    if count == 0 {
+        return Err(anyhow!("Volume is empty."));
+    }
+    if data.len() < count * sample_type.size() {
+        return Err(anyhow!(
+            "Volume data is truncated: expected {}x{}x{} samples.",
+            dims[0],
+            dims[1],
+            dims[2]
+        ));
+    }
+    let data = data
+        .chunks_exact(sample_type.size())
+        .take(count)
+        .map(|sample| sample_type.read(sample, big_endian))
+        .collect();
+    Ok(Volume {
+        dims,
+        spacing: [1.0; 3],
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a little-endian NIfTI-1 file of 16-bit samples counting up from zero.
+    fn nifti(dims: [i16; 3], slope: f32) -> Vec<u8> {
+        let mut data = vec![0; NIFTI_HEADER_SIZE + 4];
+        data[0..4].copy_from_slice(&(NIFTI_HEADER_SIZE as i32).to_le_bytes());
+        data[40..42].copy_from_slice(&3i16.to_le_bytes());
+        for (axis, dim) in dims.iter().enumerate() {
+            data[42 + axis * 2..44 + axis * 2].copy_from_slice(&dim.to_le_bytes());
+            let spacing = axis as f32 + 1.0;
+            data[80 + axis * 4..84 + axis * 4].copy_from_slice(&spacing.to_le_bytes());
+        }
+        data[70..72].copy_from_slice(&4i16.to_le_bytes());
+        data[108..112].copy_from_slice(&352.0f32.to_le_bytes());
+        data[112..116].copy_from_slice(&slope.to_le_bytes());
+        data[344..348].copy_from_slice(b"n+1\0");
+        let count = dims.iter().product::<i16>();
+        for value in 0..count {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn nifti_volume() {
+        let volume = parse_nifti(&nifti([2, 3, 4], 0.0)).unwrap();
+        assert_eq!(volume.dims, [2, 3, 4]);
+        assert_eq!(volume.spacing, [1.0, 2.0, 3.0]);
+        assert_eq!(volume.value(1, 2, 3), 23.0);
+        assert_eq!(volume.range(), (0.0, 23.0));
+    }
+
+    #[test]
+    fn nifti_scaled_and_compressed() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        std::io::Write::write_all(&mut encoder, &nifti([2, 2, 2], 0.5)).unwrap();
+        let volume = parse_nifti(&encoder.finish().unwrap()).unwrap();
+        assert_eq!(volume.value(1, 1, 1), 3.5);
+    }
+
+    #[test]
+    fn nifti_truncated() {
+        let mut data = nifti([2, 2, 2], 0.0);
+        data.pop();
+        assert!(parse_nifti(&data).is_err());
+    }
+
+    #[test]
+    fn raw_dimensions_from_name() {
+        let data: Vec<u8> = (0..24).collect();
+        let volume = parse_raw(&data, "head_2x3x4_uint8").unwrap();
+        assert_eq!(volume.dims, [2, 3, 4]);
+        assert_eq!(volume.value(1, 0, 1), 7.0);
+        assert!(parse_raw(&data, "head_2x3x4").is_err());
+        assert!(parse_raw(&data, "head_2x3x5_uint8").is_err());
+    }
+
+    #[test]
+    fn slicer_color_table() {
+        let text = "# Color table file\n0 Background 0 0 0 0\n1 Internal carotid 255 0 0 255\n";
+        let labels = parse_color_table(text).unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[1].value, 1);
+        assert_eq!(labels[1].name, "Internal carotid");
+        assert_eq!(labels[1].color, [1.0, 0.0, 0.0]);
+        assert!(parse_color_table("1 Vein 0 0").is_err());
+    }
+}
@@ -0,0 +1,280 @@
+//! Loader for VTK PolyData files, in the legacy `.vtk` format (ASCII or binary) and the XML `.vtp`
+//! format (ASCII, base64 or raw appended data). Parsing is left to [vtkio].
+
+use crate::mesh::{Mesh, ScalarField};
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use vtkio::model::{Attribute, DataSet, ElementType, IOBuffer, PolyDataPiece, VertexNumbers};
+use vtkio::Vtk;
+
+/// Loads a VTK PolyData file, giving one mesh per piece.
+pub fn load_vtk(path: &Path, name: &str) -> Result<Vec<Mesh>> {
+    let vtk = Vtk::import(path).context("Failed to read VTK file.")?;
+    vtk_to_meshes(vtk, name)
+}
+
+/// Converts the polygons and triangle strips of every piece of a parsed VTK file to meshes.
+/// Vertices and lines are not rendered and are ignored.
+fn vtk_to_meshes(vtk: Vtk, name: &str) -> Result<Vec<Mesh>> {
+    let pieces = match vtk.data {
+        DataSet::PolyData { pieces, .. } => pieces,
+        _ => return Err(anyhow!("VTK file does not contain PolyData.")),
+    };
+
+    let piece_count = pieces.len();
+    let mut meshes = Vec::with_capacity(piece_count);
+    for (i, piece) in pieces.into_iter().enumerate() {
+        let piece = piece
+            .into_loaded_piece_data(vtk.file_path.as_deref())
+            .context("Failed to load VTK piece.")?;
+        let name = match piece_count {
+            1 => name.to_owned(),
+            _ => format!("{} ({})", name, i + 1),
+        };
+        let mesh = piece_to_mesh(piece, name)?;
+        if !mesh.indices.is_empty() {
+            meshes.push(mesh);
+        }
+    }
+
+    if meshes.is_empty() {
+        return Err(anyhow!("VTK file has no polygons!"));
+    }
+    Ok(meshes)
+}
+
+fn piece_to_mesh(piece: PolyDataPiece, name: String) -> Result<Mesh> {
+    let positions: Vec<f32> = piece
+        .points
+        .cast_into()
+        .ok_or_else(|| anyhow!("VTK points are not numeric."))?;
+    let vertex_count = positions.len() / 3;
+
+    let mut indices = Vec::new();
+    if let Some(polys) = piece.polys {
+        for cell in cells(polys)? {
+            // Polygons are assumed to be convex, and triangulated as fans.
+            for corner in 1..cell.len().saturating_sub(1) {
+                indices.extend([cell[0], cell[corner], cell[corner + 1]]);
+            }
+        }
+    }
+    if let Some(strips) = piece.strips {
+        for cell in cells(strips)? {
+            // Every other triangle of a strip has its winding reversed.
+            for (i, triangle) in cell.windows(3).enumerate() {
+                match i % 2 {
+                    0 => indices.extend([triangle[0], triangle[1], triangle[2]]),
+                    _ => indices.extend([triangle[1], triangle[0], triangle[2]]),
+                }
+            }
+        }
+    }
+    if indices.iter().any(|&i| i as usize >= vertex_count) {
+        return Err(anyhow!("VTK file has a cell index out of bounds."));
+    }
+
+    let mut mesh = Mesh {
+        name,
+        positions,
+        indices,
+        ..Default::default()
+    };
+    for attribute in piece.data.point {
+        match attribute {
+            Attribute::DataArray(array) => {
+                add_point_data(&mut mesh, array.name, array.elem, array.data)
+            }
+            Attribute::Field { data_array, .. } => {
+                for array in data_array {
+                    add_point_data(
+                        &mut mesh,
+                        array.name,
+                        ElementType::Generic(array.elem),
+                        array.data,
+                    );
+                }
+            }
+        }
+    }
+    Ok(mesh)
+}
+
+/// Splits cell connectivity into the point indices of each cell.
+fn cells(numbers: VertexNumbers) -> Result<Vec<Vec<u32>>> {
+    let (connectivity, offsets) = numbers.into_xml();
+    let mut start = 0;
+    offsets
+        .into_iter()
+        .map(|end| {
+            if end < start || end as usize > connectivity.len() {
+                return Err(anyhow!("VTK file has a cell offset out of bounds."));
+            }
+            let cell = connectivity[start as usize..end as usize]
+                .iter()
+                .map(|&i| i as u32)
+                .collect();
+            start = end;
+            Ok(cell)
+        })
+        .collect()
+}
+
+/// Adds a point data array to the mesh. Normals and colours are read into the vertex data, any
+/// other array is kept as a [ScalarField], using the magnitude of arrays with several components.
+fn add_point_data(mesh: &mut Mesh, name: String, elem: ElementType, data: IOBuffer) {
+    let components = elem.num_comp() as usize;
+    let is_byte_data = matches!(data, IOBuffer::U8(_));
+    let values: Vec<f32> = match data.cast_into() {
+        Some(values) => values,
+        None => return,
+    };
+    if components == 0 || values.len() != mesh.vertex_count() * components {
+        eprintln!("Skipping VTK point data '{}' of unexpected size.", name);
+        return;
+    }
+
+    match elem {
+        ElementType::Normals => mesh.normals = values,
+        ElementType::ColorScalars(3 | 4) => {
+            mesh.vertex_color = rgb(&values, components, is_byte_data)
+        }
+        // Byte arrays of three or four components are colours in the XML format.
+        _ if is_byte_data && (components == 3 || components == 4) => {
+            mesh.vertex_color = rgb(&values, components, is_byte_data)
+        }
+        _ if components == 1 => mesh.scalars.push(ScalarField { name, values }),
+        _ => mesh.scalars.push(ScalarField {
+            name: format!("{} magnitude", name),
+            values: values
+                .chunks_exact(components)
+                .map(|c| c.iter().map(|v| v * v).sum::<f32>().sqrt())
+                .collect(),
+        }),
+    }
+}
+
+/// Drops any alpha channel of a colour array, scaling unsigned char colours to 0..=1.
+fn rgb(values: &[f32], components: usize, is_byte_data: bool) -> Vec<f32> {
+    let scale = match is_byte_data {
+        true => u8::MAX as f32,
+        false => 1.0,
+    };
+    values
+        .chunks_exact(components)
+        .flat_map(|c| [c[0], c[1], c[2]].map(|v| v / scale))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_polygons_and_strips() {
+        let text = "# vtk DataFile Version 2.0
+Test
+ASCII
+DATASET POLYDATA
+POINTS 6 float
+0 0 0  1 0 0  1 1 0
+0 1 0  2 0 0  2 1 0
+POLYGONS 1 5
+4 0 1 2 3
+TRIANGLE_STRIPS 1 5
+4 1 4 2 5
+POINT_DATA 6
+SCALARS thickness float 1
+LOOKUP_TABLE default
+0 1 2 3 4 5
+VECTORS velocity float
+3 4 0  0 0 0  0 0 0  0 0 0  0 0 0  0 0 0
+";
+        let vtk = Vtk::parse_legacy_be(text.as_bytes()).unwrap();
+        let meshes = vtk_to_meshes(vtk, "vessel").unwrap();
+        assert_eq!(meshes.len(), 1);
+        let mesh = &meshes[0];
+        assert_eq!(mesh.vertex_count(), 6);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3, 1, 4, 2, 2, 4, 5]);
+        assert_eq!(mesh.scalars.len(), 2);
+        assert_eq!(mesh.scalars[0].name, "thickness");
+        assert_eq!(mesh.scalars[0].values, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(mesh.scalars[1].name, "velocity magnitude");
+        assert_eq!(mesh.scalars[1].values[0], 5.0);
+    }
+
+    #[test]
+    fn xml_polygons_with_normals() {
+        let text = r#"<?xml version="1.0"?>
+<VTKFile type="PolyData" version="0.1" byte_order="LittleEndian">
+  <PolyData>
+    <Piece NumberOfPoints="3" NumberOfVerts="0" NumberOfLines="0" NumberOfStrips="0" NumberOfPolys="1">
+      <PointData Normals="Normals">
+        <DataArray type="Float32" Name="Normals" NumberOfComponents="3" format="ascii">
+          0 0 1 0 0 1 0 0 1
+        </DataArray>
+        <DataArray type="Float32" Name="pressure" format="ascii">
+          1 2 3
+        </DataArray>
+      </PointData>
+      <Points>
+        <DataArray type="Float32" NumberOfComponents="3" format="ascii">
+          0 0 0 1 0 0 0 1 0
+        </DataArray>
+      </Points>
+      <Polys>
+        <DataArray type="Int32" Name="connectivity" format="ascii">0 1 2</DataArray>
+        <DataArray type="Int32" Name="offsets" format="ascii">3</DataArray>
+      </Polys>
+    </Piece>
+  </PolyData>
+</VTKFile>
+"#;
+        let vtk = Vtk::parse_xml(text.as_bytes()).unwrap();
+        let meshes = vtk_to_meshes(vtk, "vessel").unwrap();
+        let mesh = &meshes[0];
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        assert_eq!(mesh.normals, [0.0, 0.0, 1.0].repeat(3));
+        assert_eq!(mesh.scalars[0].name, "pressure");
+        assert_eq!(mesh.scalars[0].values, vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn byte_color_scalars_are_normalised() {
+        let mut mesh = Mesh {
+            positions: vec![0.0; 6],
+            ..Default::default()
+        };
+        let data = IOBuffer::U8(vec![255, 0, 51, 255, 0, 255, 102, 255]);
+        add_point_data(
+            &mut mesh,
+            "colors".into(),
+            ElementType::ColorScalars(4),
+            data,
+        );
+        assert_eq!(mesh.vertex_color, vec![1.0, 0.0, 0.2, 0.0, 1.0, 0.4]);
+    }
+
+    #[test]
+    fn decreasing_offsets_are_rejected() {
+        let text = r#"<?xml version="1.0"?>
+<VTKFile type="PolyData" version="0.1" byte_order="LittleEndian">
+  <PolyData>
+    <Piece NumberOfPoints="3" NumberOfVerts="0" NumberOfLines="0" NumberOfStrips="0" NumberOfPolys="3">
+      <Points>
+        <DataArray type="Float32" NumberOfComponents="3" format="ascii">
+          0 0 0 1 0 0 0 1 0
+        </DataArray>
+      </Points>
+      <Polys>
+        <DataArray type="Int32" Name="connectivity" format="ascii">0 1 2</DataArray>
+        <DataArray type="Int32" Name="offsets" format="ascii">3 1 3</DataArray>
+      </Polys>
+    </Piece>
+  </PolyData>
+</VTKFile>
+"#;
+        let vtk = Vtk::parse_xml(text.as_bytes()).unwrap();
+        assert!(vtk_to_meshes(vtk, "vessel").is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Contains [CrossSection], the contours where a plane cuts the surface of a model, and their
+//! area, perimeter and equivalent diameter.
+
+use crate::clipping::ClipPlane;
+use na::{Point2, Point3, Vector3};
+use nalgebra as na;
+use std::collections::HashMap;
+
+/// Identifies a mesh edge by the bit patterns of its end positions, in a fixed order, so the
+/// triangles sharing an edge find the same crossing point even when vertices are duplicated.
+type EdgeKey = ([u32; 3], [u32; 3]);
+
+/// A polyline where the plane cuts the surface, closed unless the surface has a hole there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contour {
+    pub points: Vec<Point3<f32>>,
+    pub closed: bool,
+}
+
+impl Contour {
+    /// Gets the line segments of the contour.
+    pub fn segments(&self) -> impl Iterator<Item = [Point3<f32>; 2]> + '_ {
+        let closing = match self.closed && self.points.len() > 2 {
+            true => self.points.last().zip(self.points.first()),
+            false => None,
+        };
+        self.points
+            .windows(2)
+            .map(|pair| [pair[0], pair[1]])
+            .chain(closing.map(|(last, first)| [*last, *first]))
+    }
+}
+
+/// The contours where a plane cuts a triangle mesh.
+#[derive(Clone, Debug)]
+pub struct CrossSection {
+    pub contours: Vec<Contour>,
+    origin: Point3<f32>,
+    /// Unit vectors in the plane, for coordinates within it.
+    basis: (Vector3<f32>, Vector3<f32>),
+}
+
+impl CrossSection {
+    /// Finds the contours where the plane cuts the given triangles. Contours are oriented
+    /// counter-clockwise around the surface inside when seen against the plane normal, provided
+    /// the triangles face outwards.
+    pub fn new(triangles: impl IntoIterator<Item = [Point3<f32>; 3]>, plane: &ClipPlane) -> Self {
+        let normal = plane.normal();
+        let distance = |p: &Point3<f32>| normal.dot(&p.coords) - plane.offset;
+
+        // Each cut triangle gives one segment between the two edges crossing the plane. Going
+        // round the triangle, the segment runs from where the edges pass behind the plane to
+        // where they come back, which orients it even when it has no length.
+        let mut segments: Vec<[(EdgeKey, Point3<f32>); 2]> = Vec::new();
+        for triangle in triangles {
+            let (mut leaving, mut entering) = (None, None);
+            for i in 0..3 {
+                let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+                // Points on the plane count as in front of it, so no edge crosses twice.
+                let (in_front_a, in_front_b) = (distance(&a) >= 0.0, distance(&b) >= 0.0);
+                if in_front_a != in_front_b {
+                    let point = crossing(a, b, distance(&a), distance(&b));
+                    match in_front_a {
+                        true => leaving = Some(point),
+                        false => entering = Some(point),
+                    }
+                }
+            }
+            if let (Some(leaving), Some(entering)) = (leaving, entering) {
+                segments.push([leaving, entering]);
+            }
+        }
+
+        Self {
+            contours: chain(&segments),
+            origin: plane.centre(),
+            basis: plane.basis(),
+        }
+    }
+
+    /// Gets the coordinates of a point within the plane.
+    pub fn to_plane(&self, point: &Point3<f32>) -> Point2<f32> {
+        let offset = point - self.origin;
+        Point2::new(offset.dot(&self.basis.0), offset.dot(&self.basis.1))
+    }
+
+    /// Gets the area enclosed by the closed contours, where contours inside others are holes.
+    pub fn area(&self) -> f32 {
+        let signed: f32 = self
+            .contours
+            .iter()
+            .filter(|contour| contour.closed)
+            .flat_map(Contour::segments)
+            .map(|[a, b]| {
+                let (a, b) = (self.to_plane(&a), self.to_plane(&b));
+                a.x * b.y - b.x * a.y
+            })
+            .sum();
+        (signed / 2.0).abs()
+    }
+
+    /// Gets the total length of the contours.
+    pub fn perimeter(&self) -> f32 {
+        self.contours
+            .iter()
+            .flat_map(Contour::segments)
+            .map(|[a, b]| (b - a).norm())
+            .sum()
+    }
+
+    /// Gets the diameter of the circle with the same area as the cross-section.
+    pub fn equivalent_diameter(&self) -> f32 {
+        2.0 * (self.area() / std::f32::consts::PI).sqrt()
+    }
+}
+
+/// Finds where the edge from `a` to `b` crosses the plane, given their distances from it.
+fn crossing(a: Point3<f32>, b: Point3<f32>, da: f32, db: f32) -> (EdgeKey, Point3<f32>) {
+    let (key_a, key_b) = (
+        a.coords.map(f32::to_bits).into(),
+        b.coords.map(f32::to_bits).into(),
+    );
+    // Calculating from the same end either way round gives the exact same point.
+    let ((a, da, key_a), (b, db, key_b)) = match key_a <= key_b {
+        true => ((a, da, key_a), (b, db, key_b)),
+        false => ((b, db, key_b), (a, da, key_a)),
+    };
+    let t = da / (da - db);
+    ((key_a, key_b), a + (b - a) * t)
+}
+
+/// Joins oriented segments that share end points into contours. Several segments may share an
+/// end point on double-sided or non-manifold surfaces, in which case one that does not turn
+/// straight back is preferred.
+fn chain(segments: &[[(EdgeKey, Point3<f32>); 2]]) -> Vec<Contour> {
+    let mut starts: HashMap<EdgeKey, Vec<usize>> = HashMap::new();
+    let mut ends: HashMap<EdgeKey, Vec<usize>> = HashMap::new();
+    for (i, [(start, _), (end, _)]) in segments.iter().enumerate() {
+        starts.entry(*start).or_default().push(i);
+        ends.entry(*end).or_default().push(i);
+    }
+    // Picks an unused segment of those sharing a key, avoiding one back to the key `back`.
+    let pick = |candidates: Option<&Vec<usize>>, back: &EdgeKey, used: &[bool], side: usize| {
+        let unused: Vec<usize> = candidates
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|&i| !used[i])
+            .collect();
+        unused
+            .iter()
+            .copied()
+            .find(|&i| segments[i][side].0 != *back)
+            .or(unused.first().copied())
+    };
+
+    let mut used = vec![false; segments.len()];
+    let mut contours = Vec::new();
+    for first in 0..segments.len() {
+        if used[first] {
+            continue;
+        }
+        // Step back to where an open contour begins. The steps are limited, as the walk may
+        // circle without passing `first` where segments share end points.
+        let mut start = first;
+        for _ in 0..segments.len() {
+            let [(start_key, _), (end_key, _)] = &segments[start];
+            match pick(ends.get(start_key), end_key, &used, 0) {
+                Some(previous) if previous != first => start = previous,
+                _ => break,
+            }
+        }
+
+        let mut points = Vec::new();
+        let mut current = start;
+        let closed = loop {
+            used[current] = true;
+            points.push(segments[current][0].1);
+            let [(start_key, _), (end_key, _)] = &segments[current];
+            let candidates = starts.get(end_key);
+            if candidates.is_some_and(|next| next.contains(&start)) {
+                break true;
+            }
+            match pick(candidates, start_key, &used, 1) {
+                Some(next) => current = next,
+                None => {
+                    points.push(segments[current][1].1);
+                    break false;
+                }
+            }
+        };
+        contours.push(Contour { points, closed });
+    }
+    contours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Triangles of an axis-aligned box, facing outwards, or inwards if `inwards` is set.
+    fn cuboid(min: [f32; 3], max: [f32; 3], inwards: bool) -> Vec<[Point3<f32>; 3]> {
+        let corner = |i: usize| {
+            Point3::new(
+                [min[0], max[0]][i & 1],
+                [min[1], max[1]][(i >> 1) & 1],
+                [min[2], max[2]][(i >> 2) & 1],
+            )
+        };
+        let faces = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        faces
+            .iter()
+            .flat_map(|&[a, b, c, d]| [[a, b, c], [a, c, d]])
+            .map(|[a, b, c]| match inwards {
+                true => [corner(a), corner(c), corner(b)],
+                false => [corner(a), corner(b), corner(c)],
+            })
+            .collect()
+    }
+
+    fn plane(elevation: f32, offset: f32) -> ClipPlane {
+        ClipPlane {
+            elevation,
+            offset,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn box_cross_section() {
+        let section = CrossSection::new(cuboid([-1.0; 3], [1.0; 3], false), &plane(0.0, 0.25));
+        assert_eq!(section.contours.len(), 1);
+        assert!(section.contours[0].closed);
+        assert!((section.area() - 4.0).abs() < 1e-5);
+        assert!((section.perimeter() - 8.0).abs() < 1e-5);
+        let diameter = 2.0 * (4.0 / std::f32::consts::PI).sqrt();
+        assert!((section.equivalent_diameter() - diameter).abs() < 1e-5);
+
+        // Through the vertices of the box, where points lie exactly on the plane.
+        let section = CrossSection::new(cuboid([-1.0; 3], [1.0; 3], false), &plane(90.0, 1.0));
+        assert!(section.contours.iter().all(|contour| contour.closed));
+    }
+
+    #[test]
+    fn hole_subtracted() {
+        // A thick-walled tube cut across, with the inner surface facing the hole.
+        let mut triangles = cuboid([-2.0, -2.0, -1.0], [2.0, 2.0, 1.0], false);
+        triangles.extend(cuboid([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0], true));
+        let section = CrossSection::new(triangles, &plane(0.0, 0.0));
+        assert_eq!(section.contours.len(), 2);
+        assert!((section.area() - 12.0).abs() < 1e-4);
+        assert!((section.perimeter() - 24.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn open_surface() {
+        let triangles = vec![
+            [
+                Point3::new(0.0, 0.0, -1.0),
+                Point3::new(2.0, 0.0, -1.0),
+                Point3::new(0.0, 0.0, 1.0),
+            ],
+            [
+                Point3::new(2.0, 0.0, -1.0),
+                Point3::new(2.0, 0.0, 1.0),
+                Point3::new(0.0, 0.0, 1.0),
+            ],
+        ];
+        let section = CrossSection::new(triangles, &plane(0.0, 0.0));
+        assert_eq!(section.contours.len(), 1);
+        assert!(!section.contours[0].closed);
+        assert_eq!(section.contours[0].points.len(), 3);
+        assert!((section.perimeter() - 2.0).abs() < 1e-5);
+        assert_eq!(section.area(), 0.0);
+    }
+
+    #[test]
+    fn double_sided_surface() {
+        // Every face is stored both ways round, so two segments start at each crossing point.
+        let mut triangles = cuboid([-1.0; 3], [1.0; 3], false);
+        triangles.extend(cuboid([-1.0; 3], [1.0; 3], true));
+        let section = CrossSection::new(triangles, &plane(0.0, 0.25));
+        assert_eq!(section.contours.len(), 2);
+        assert!(section.contours.iter().all(|contour| contour.closed));
+        assert!((section.perimeter() - 16.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,247 @@
+//! Contains [SplitView], which divides the window into panes showing the model with different
+//! settings, or other models, from the same camera.
+
+use crate::{
+    camera::Camera,
+    model::{Attributes, Model},
+    preset::Preset,
+    render_gl::Viewport,
+};
+use nalgebra as na;
+
+/// How the window is divided into panes.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Layout {
+    #[default]
+    Single,
+    /// Two panes side by side.
+    Double,
+    /// Four panes in a grid.
+    Quad,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 3] = [Layout::Single, Layout::Double, Layout::Quad];
+
+    /// Gets the number of panes, including the first one showing the main model.
+    pub fn pane_count(&self) -> usize {
+        match self {
+            Layout::Single => 1,
+            Layout::Double => 2,
+            Layout::Quad => 4,
+        }
+    }
+}
+
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Layout::Single => write!(f, "Single"),
+            Layout::Double => write!(f, "2 panes"),
+            Layout::Quad => write!(f, "4 panes"),
+        }
+    }
+}
+
+/// A rectangle of the window in pixels, measured from the top left corner like the cursor.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PaneRect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl PaneRect {
+    /// Gets the size of the pane.
+    pub fn size(&self) -> (u32, u32) {
+        (self.w, self.h)
+    }
+
+    /// Gets the OpenGL viewport of the pane in a window of the given height, which is measured
+    /// from the bottom left corner.
+    pub fn viewport(&self, window_height: u32) -> Viewport {
+        Viewport {
+            x: self.x as i32,
+            y: window_height.saturating_sub(self.y + self.h) as i32,
+            w: self.w,
+            h: self.h,
+        }
+    }
+
+    /// Gets the cursor position within the pane, if the cursor is over it.
+    pub fn local(&self, cursor: (i32, i32)) -> Option<(i32, i32)> {
+        let (x, y) = (cursor.0 - self.x as i32, cursor.1 - self.y as i32);
+        let inside = (0..self.w as i32).contains(&x) && (0..self.h as i32).contains(&y);
+        inside.then_some((x, y))
+    }
+}
+
+/// A pane after the first, with its own shader attributes and optionally its own model.
+#[derive(Clone, Debug)]
+pub struct Pane {
+    /// Name of the preset last applied, shown in the pane.
+    pub name: String,
+    /// Shader attributes, of which those depending on the camera, model or time are ignored.
+    pub attributes: Attributes,
+    /// File of the model shown in the pane, or `None` to show the main model.
+    pub file: Option<String>,
+}
+
+/// The layout of the panes, and the settings of the panes after the first. The first pane shows
+/// the main model with the settings of the settings window, and is the one models are picked,
+/// measured and cut in.
+#[derive(Clone, Debug, Default)]
+pub struct SplitView {
+    pub layout: Layout,
+    pub panes: Vec<Pane>,
+}
+
+impl SplitView {
+    /// Adds panes until there are enough for the layout, starting from the given attributes with
+    /// a different built-in preset applied to each. Panes beyond the layout are kept, so their
+    /// settings return when switching back.
+    pub fn fill_panes(&mut self, attributes: &Attributes) {
+        const PRESETS: [Preset; 3] = [Preset::ChromaDepth, Preset::Aerial, Preset::Plain];
+        while self.panes.len() + 1 < self.layout.pane_count() {
+            let preset = PRESETS[self.panes.len() % PRESETS.len()];
+            let mut attributes = attributes.clone();
+            preset.apply(&mut attributes);
+            self.panes.push(Pane {
+                name: preset.description().to_owned(),
+                attributes,
+                file: None,
+            });
+        }
+    }
+
+    /// Gets the shown panes after the first.
+    pub fn visible_panes(&self) -> &[Pane] {
+        let count = (self.layout.pane_count() - 1).min(self.panes.len());
+        &self.panes[..count]
+    }
+
+    /// Divides a window of the given size into the panes of the layout, the first pane first and
+    /// then left to right, top to bottom.
+    pub fn rects(&self, window: (u32, u32)) -> Vec<PaneRect> {
+        let (columns, rows) = match self.layout {
+            Layout::Single => (1, 1),
+            Layout::Double => (2, 1),
+            Layout::Quad => (2, 2),
+        };
+        let (w, h) = (window.0 / columns, window.1 / rows);
+        (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| PaneRect {
+                x: column * w,
+                y: row * h,
+                // The last column and row take the pixels left over by the division.
+                w: if column + 1 == columns {
+                    window.0 - column * w
+                } else {
+                    w
+                },
+                h: if row + 1 == rows {
+                    window.1 - row * h
+                } else {
+                    h
+                },
+            })
+            .collect()
+    }
+
+    /// Finds the pane under the cursor, with the cursor position within it.
+    pub fn pane_at(&self, window: (u32, u32), cursor: (i32, i32)) -> Option<(usize, PaneRect)> {
+        self.rects(window)
+            .into_iter()
+            .enumerate()
+            .find(|(_, rect)| rect.local(cursor).is_some())
+    }
+}
+
+/// Renders the model into the viewport of a pane with the given attributes, seen from the
+/// camera at the given time, and then restores the attributes of the model.
+pub fn render_pane(
+    model: &mut Model,
+    attributes: &Attributes,
+    camera: &Camera,
+    elapsed: f32,
+    viewport: &Viewport,
+) {
+    let previous = model.get_attributes().clone();
+    let aspect = viewport.w as f32 / viewport.h.max(1) as f32;
+    let c = camera.position();
+    model.set_attributes(Attributes {
+        projection_matrix: camera.construct_mvp(aspect, na::Isometry3::identity()),
+        camera_position: na::Vector3::new(c[0], c[1], c[2]),
+        model_size: previous.model_size,
+        elapsed,
+        ..attributes.clone()
+    });
+    // Every attribute may differ from the last pane, so all are passed to the shaders.
+    model.reset_all_attributes();
+    model.render(viewport);
+    model.set_attributes(previous);
+    model.reset_all_attributes();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panes_tile_the_window() {
+        let mut split = SplitView::default();
+        let window = (801, 601);
+        assert_eq!(
+            split.rects(window),
+            [PaneRect {
+                x: 0,
+                y: 0,
+                w: 801,
+                h: 601
+            }]
+        );
+
+        split.layout = Layout::Quad;
+        let rects = split.rects(window);
+        assert_eq!(rects.len(), 4);
+        let area: u32 = rects.iter().map(|rect| rect.w * rect.h).sum();
+        assert_eq!(area, window.0 * window.1);
+        assert_eq!(
+            rects[3],
+            PaneRect {
+                x: 400,
+                y: 300,
+                w: 401,
+                h: 301
+            }
+        );
+
+        // The top panes are at the top of the OpenGL viewport, which starts at the bottom.
+        assert_eq!(rects[0].viewport(window.1).y, 301);
+        assert_eq!(rects[3].viewport(window.1).y, 0);
+
+        assert_eq!(split.pane_at(window, (10, 10)).map(|(i, _)| i), Some(0));
+        assert_eq!(split.pane_at(window, (500, 10)).map(|(i, _)| i), Some(1));
+        assert_eq!(split.pane_at(window, (10, 400)).map(|(i, _)| i), Some(2));
+        assert_eq!(rects[3].local((410, 310)), Some((10, 10)));
+        assert_eq!(split.pane_at(window, (900, 10)), None);
+    }
+
+    #[test]
+    fn panes_filled_and_kept() {
+        let mut split = SplitView {
+            layout: Layout::Quad,
+            ..Default::default()
+        };
+        split.fill_panes(&Attributes::default());
+        assert_eq!(split.panes.len(), 3);
+        assert_eq!(split.panes[0].name, Preset::ChromaDepth.description());
+
+        split.layout = Layout::Double;
+        split.fill_panes(&Attributes::default());
+        assert_eq!(split.panes.len(), 3);
+        assert_eq!(split.visible_panes().len(), 1);
+    }
+}
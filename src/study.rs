@@ -0,0 +1,734 @@
+//! Contains the study mode, which shows randomised trials of models rendered with different
+//! presets and camera views full-screen, asks which of two marked points is closer, and logs the
+//! answers and reaction times of each participant to CSV.
+
+use crate::{
+    camera::{Camera, CameraView},
+    cli::StudyArgs,
+    geometry::project_to_window,
+    model::{Attributes, Model},
+    preset::{Preset, PresetFile},
+    render_gl::{self, Viewport},
+    resources::{self, Resources},
+    split_view,
+    ui::UIRenderer,
+};
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use na::Point3;
+use nalgebra as na;
+use sdl2::{event::Event, keyboard::Keycode};
+use serde::Deserialize;
+use std::{
+    fs,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Columns of the response log.
+const CSV_HEADER: &str = "participant,seed,trial,model,preset,azimuth,elevation,distance,\
+                          x1,y1,z1,x2,y2,z2,depth1,depth2,correct,answer,is_correct,reaction_ms";
+/// Random cursor positions tried per trial to find two suitable points on the model.
+const MAX_POINT_ATTEMPTS: usize = 1000;
+/// Radius of the rings marking the points, in pixels.
+const MARKER_RADIUS: f32 = 14.0;
+
+/// What the participant is asked about the two marked points.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Task {
+    /// Which point is closer to the viewer.
+    #[default]
+    Closer,
+    /// Which point is farther from the viewer.
+    Farther,
+}
+
+impl Task {
+    /// Gets the default question shown for the task.
+    fn question(&self) -> &'static str {
+        match self {
+            Task::Closer => "Which marked point is closer to you?",
+            Task::Farther => "Which marked point is farther from you?",
+        }
+    }
+
+    /// Gets the correct answer, 1 or 2, for points at the given distances from the camera.
+    fn correct_answer(&self, depths: [f32; 2]) -> usize {
+        let first_closer = depths[0] < depths[1];
+        match (self, first_closer) {
+            (Task::Closer, true) | (Task::Farther, false) => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// A study, as described by its configuration file. Every combination of model, preset and view
+/// is shown `repetitions` times, in random order.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StudyConfig {
+    /// Model files, relative to the configuration file.
+    pub models: Vec<PathBuf>,
+    /// Built-in presets, such as "aerial" or "chroma-depth", or preset files relative to the
+    /// configuration file.
+    pub presets: Vec<String>,
+    pub views: Vec<CameraView>,
+    pub repetitions: u32,
+    pub task: Task,
+    /// Question shown instead of the default one for the task.
+    pub question: Option<String>,
+    pub background: na::Vector3<f32>,
+    /// Smallest difference in distance from the camera between the marked points, relative to the
+    /// model size.
+    pub min_depth_difference: f32,
+    /// Smallest distance between the marked points on screen, relative to the window height.
+    pub min_separation: f32,
+    /// Blank time between trials, in milliseconds.
+    pub pause_ms: u64,
+    /// Seed of the trial order and points, to reproduce them. Random if not given.
+    pub seed: Option<u64>,
+}
+
+impl Default for StudyConfig {
+    fn default() -> Self {
+        Self {
+            models: Vec::new(),
+            presets: vec!["plain".to_owned()],
+            views: vec![CameraView::default()],
+            repetitions: 1,
+            task: Task::default(),
+            question: None,
+            background: na::Vector3::new(0.3, 0.3, 0.5),
+            min_depth_difference: 0.05,
+            min_separation: 0.15,
+            pause_ms: 500,
+            seed: None,
+        }
+    }
+}
+
+impl StudyConfig {
+    /// Loads a TOML configuration file, with model paths made relative to its directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).context("Failed to read study configuration.")?;
+        let mut config: StudyConfig = toml::from_str(&text)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for model in config.models.iter_mut() {
+            *model = directory.join(&*model);
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that the study has at least one trial, and that its background colour is valid.
+    fn validate(&self) -> Result<()> {
+        if self.models.is_empty() || self.presets.is_empty() || self.views.is_empty() {
+            return Err(anyhow!(
+                "The study needs at least one model, preset and view."
+            ));
+        }
+        if self.repetitions == 0 {
+            return Err(anyhow!("The study needs at least one repetition."));
+        }
+        if self.background.iter().any(|c| !(0.0..=1.0).contains(c)) {
+            return Err(anyhow!(
+                "Background colour components must be between 0 and 1."
+            ));
+        }
+        Ok(())
+    }
+
+    /// Gets the question shown in every trial.
+    fn question(&self) -> &str {
+        self.question
+            .as_deref()
+            .unwrap_or_else(|| self.task.question())
+    }
+}
+
+/// Gets the shader attributes of the built-in preset of the given name, or else of the preset
+/// file of that name relative to the given directory.
+fn preset_attributes(name: &str, directory: &Path) -> Result<Attributes> {
+    let mut attributes = Attributes::default();
+    match Preset::from_str(name, true) {
+        Ok(preset) => preset.apply(&mut attributes),
+        Err(_) => PresetFile::load(&directory.join(name))
+            .with_context(|| format!("Failed to load preset '{}'.", name))?
+            .apply(&mut attributes),
+    }
+    Ok(attributes)
+}
+
+/// Indices of the model, preset and view shown in a trial.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Trial {
+    pub model: usize,
+    pub preset: usize,
+    pub view: usize,
+}
+
+/// Lists every combination of model, preset and view as many times as it is repeated, in random
+/// order.
+fn trials(config: &StudyConfig, rng: &mut Rng) -> Vec<Trial> {
+    let mut trials = Vec::new();
+    for _ in 0..config.repetitions {
+        for model in 0..config.models.len() {
+            for preset in 0..config.presets.len() {
+                for view in 0..config.views.len() {
+                    trials.push(Trial {
+                        model,
+                        preset,
+                        view,
+                    });
+                }
+            }
+        }
+    }
+    rng.shuffle(&mut trials);
+    trials
+}
+
+/// Random numbers from SplitMix64, so the trials of a study can be reproduced from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Gets a number between 0 and 1.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Shuffles the items with the Fisher-Yates algorithm.
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+/// A trial as shown, with the two marked points.
+struct Stimulus {
+    trial: Trial,
+    points: [Point3<f32>; 2],
+    /// Distances of the points from the camera.
+    depths: [f32; 2],
+}
+
+/// An answer given in a trial.
+struct Response<'a> {
+    participant: &'a str,
+    seed: u64,
+    /// Number of the trial, from 1.
+    number: usize,
+    model: &'a str,
+    preset: &'a str,
+    view: &'a CameraView,
+    stimulus: &'a Stimulus,
+    correct: usize,
+    answer: usize,
+    reaction_ms: u32,
+}
+
+/// The CSV file the responses of a participant are written to, one line per trial as soon as it
+/// is answered, so nothing is lost if the study is stopped early.
+struct ResponseLog {
+    writer: BufWriter<fs::File>,
+}
+
+/// Gets the path of the log of a participant in the output directory. The participant must not
+/// contain path separators, so the log cannot end up elsewhere.
+fn log_path(output: &Path, participant: &str) -> Result<PathBuf> {
+    if participant.is_empty() || participant.contains(['/', '\\']) {
+        return Err(anyhow!(
+            "Invalid participant '{}'. Use a name without path separators.",
+            participant
+        ));
+    }
+    Ok(output.join(format!("{}.csv", participant)))
+}
+
+impl ResponseLog {
+    /// Creates the log, failing if it already exists so no responses are overwritten.
+    fn create(path: &Path) -> Result<Self> {
+        let file = fs::File::create_new(path).with_context(|| {
+            format!(
+                "Failed to create '{}'. Choose another participant or output directory.",
+                path.display()
+            )
+        })?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{}", CSV_HEADER)?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    /// Writes a response to the log.
+    fn record(&mut self, response: &Response) -> Result<()> {
+        let [a, b] = response.stimulus.points;
+        let [depth_a, depth_b] = response.stimulus.depths;
+        let view = response.view;
+        writeln!(
+            self.writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            csv_field(response.participant),
+            response.seed,
+            response.number,
+            csv_field(response.model),
+            csv_field(response.preset),
+            view.azimuth,
+            view.elevation,
+            view.distance.map(|d| d.to_string()).unwrap_or_default(),
+            a.x,
+            a.y,
+            a.z,
+            b.x,
+            b.y,
+            b.z,
+            depth_a,
+            depth_b,
+            response.correct,
+            response.answer,
+            response.answer == response.correct,
+            response.reaction_ms,
+        )?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Quotes a CSV field if it contains characters that would break the columns.
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_owned(),
+    }
+}
+
+/// Points the camera as described by the view, fitting the model in view if no distance is given.
+fn set_view(camera: &mut Camera, view: &CameraView, model: &Model) {
+    camera.set_view(*view);
+    if view.distance.is_none() {
+        camera.set_dist(model.get_size().magnitude() * 1.2);
+    }
+}
+
+/// Finds two visible points on the model that differ enough in distance from the camera and are
+/// far enough apart on screen, by picking random cursor positions in the middle of the window.
+fn find_points(
+    model: &Model,
+    camera: &Camera,
+    window: (u32, u32),
+    config: &StudyConfig,
+    rng: &mut Rng,
+) -> Option<([Point3<f32>; 2], [f32; 2])> {
+    let min_depth_difference = config.min_depth_difference * model.get_size().magnitude();
+    let min_separation = config.min_separation * window.1 as f32;
+    let mut random_cursor = || {
+        let x = window.0 as f32 * (0.1 + 0.8 * rng.next_f32());
+        let y = window.1 as f32 * (0.1 + 0.8 * rng.next_f32());
+        (x as i32, y as i32)
+    };
+    for _ in 0..MAX_POINT_ATTEMPTS {
+        let cursors = [random_cursor(), random_cursor()];
+        let [dx, dy] = [cursors[0].0 - cursors[1].0, cursors[0].1 - cursors[1].1];
+        if ((dx * dx + dy * dy) as f32).sqrt() < min_separation {
+            continue;
+        }
+        let hits = cursors.map(|cursor| {
+            camera
+                .ray_through(cursor, window)
+                .and_then(|ray| model.pick(&ray))
+        });
+        if let [Some(a), Some(b)] = hits {
+            if (a.distance - b.distance).abs() >= min_depth_difference {
+                return Some(([a.point, b.point], [a.distance, b.distance]));
+            }
+        }
+    }
+    None
+}
+
+/// What the study window is showing.
+enum Phase {
+    /// Instructions, until the participant starts.
+    Instructions,
+    /// A blank screen between trials, until the given time.
+    Pause(Instant),
+    /// A trial, with the time it was first shown in SDL ticks once it has been.
+    Trial(Option<u32>),
+    Finished,
+}
+
+/// Runs the study described by the command-line arguments until it is finished or stopped with
+/// escape.
+pub fn run(res: &Resources, args: &StudyArgs) -> Result<()> {
+    let config = StudyConfig::load(&args.config)?;
+    let directory = args.config.parent().unwrap_or(Path::new(""));
+    let presets = config
+        .presets
+        .iter()
+        .map(|name| preset_attributes(name, directory))
+        .collect::<Result<Vec<_>>>()?;
+    let seed = config.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64)
+    });
+    let mut rng = Rng(seed);
+    let trials = trials(&config, &mut rng);
+    // An existing log is reported before the window opens, but the log is only created once
+    // the stimuli are ready, so a study failing to start leaves nothing behind.
+    let log_path = log_path(&args.output, &args.participant)?;
+    if log_path.exists() {
+        return Err(anyhow!(
+            "'{}' already exists. Choose another participant or output directory.",
+            log_path.display()
+        ));
+    }
+
+    let sdl = sdl2::init().map_err(|e| anyhow!(e))?;
+    let video_subsystem = sdl.video().map_err(|e| anyhow!(e))?;
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+    gl_attr.set_context_version(4, 1);
+    let mut window_builder = video_subsystem.window("IVRIGST study", args.width, args.height);
+    window_builder.opengl();
+    if !args.windowed {
+        window_builder.fullscreen_desktop();
+    }
+    let window = window_builder.build()?;
+    let _gl_context = window.gl_create_context().map_err(|e| anyhow!(e))?;
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+    sdl.mouse().show_cursor(false);
+    let timer = sdl.timer().map_err(|e| anyhow!(e))?;
+
+    let size = window.drawable_size();
+    let viewport = Viewport::for_window(size.0 as i32, size.1 as i32);
+    let color_buffer = render_gl::ColorBuffer::from_color(config.background);
+    let renderer = UIRenderer::new(res)?;
+    let ctx = egui::Context::default();
+
+    let mut models = config
+        .models
+        .iter()
+        .map(|path| {
+            let path = std::path::absolute(path)?;
+            Model::new(res, &path.to_string_lossy())
+                .with_context(|| format!("Failed to load '{}'.", path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // The points of every trial are chosen up front, so the whole study is fixed by the seed.
+    let mut camera = Camera::new();
+    let stimuli = trials
+        .into_iter()
+        .map(|trial| {
+            let model = &models[trial.model];
+            set_view(&mut camera, &config.views[trial.view], model);
+            let (points, depths) = find_points(model, &camera, size, &config, &mut rng)
+                .with_context(|| {
+                    format!(
+                        "Found no points differing enough in depth on '{}'.",
+                        config.models[trial.model].display()
+                    )
+                })?;
+            Ok(Stimulus {
+                trial,
+                points,
+                depths,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut log = ResponseLog::create(&log_path)?;
+    let names: Vec<String> = config
+        .models
+        .iter()
+        .map(|path| resources::file_stem(path))
+        .collect();
+
+    let mut phase = Phase::Instructions;
+    let mut current = 0;
+    let mut trial_start = Instant::now();
+    let mut event_pump = sdl.event_pump().map_err(|e| anyhow!(e))?;
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            let (keycode, timestamp) = match event {
+                Event::Quit { .. } => break 'main,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    timestamp,
+                    repeat: false,
+                    ..
+                } => (keycode, timestamp),
+                _ => continue,
+            };
+            if keycode == Keycode::Escape {
+                break 'main;
+            }
+            match phase {
+                Phase::Instructions if keycode == Keycode::Space => {
+                    phase = Phase::Pause(Instant::now());
+                }
+                Phase::Trial(Some(shown_at)) => {
+                    let answer = match keycode {
+                        Keycode::Num1 | Keycode::Kp1 => 1,
+                        Keycode::Num2 | Keycode::Kp2 => 2,
+                        _ => continue,
+                    };
+                    let stimulus = &stimuli[current];
+                    log.record(&Response {
+                        participant: &args.participant,
+                        seed,
+                        number: current + 1,
+                        model: &names[stimulus.trial.model],
+                        preset: &config.presets[stimulus.trial.preset],
+                        view: &config.views[stimulus.trial.view],
+                        stimulus,
+                        correct: config.task.correct_answer(stimulus.depths),
+                        answer,
+                        reaction_ms: timestamp.saturating_sub(shown_at),
+                    })?;
+                    current += 1;
+                    phase = match current < stimuli.len() {
+                        true => {
+                            Phase::Pause(Instant::now() + Duration::from_millis(config.pause_ms))
+                        }
+                        false => Phase::Finished,
+                    };
+                }
+                Phase::Finished => break 'main,
+                _ => {}
+            }
+        }
+        if let Phase::Pause(until) = phase {
+            if Instant::now() >= until {
+                phase = Phase::Trial(None);
+                trial_start = Instant::now();
+            }
+        }
+
+        color_buffer.clear();
+        ctx.begin_frame(egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(size.0 as f32, size.1 as f32),
+            )),
+            ..Default::default()
+        });
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("study"),
+        ));
+        let centre = egui::pos2(size.0 as f32 / 2.0, size.1 as f32 / 2.0);
+        let font = egui::FontId::proportional(28.0);
+        match phase {
+            Phase::Instructions => {
+                let text = format!(
+                    "{}\n\nTwo points on each model are marked 1 and 2.\nAnswer with the 1 or 2 \
+                     key, as quickly and accurately as you can.\n\nPress space to begin.",
+                    config.question()
+                );
+                painter.text(
+                    centre,
+                    egui::Align2::CENTER_CENTER,
+                    text,
+                    font,
+                    egui::Color32::WHITE,
+                );
+            }
+            Phase::Trial(_) => {
+                let stimulus = &stimuli[current];
+                let model = &mut models[stimulus.trial.model];
+                set_view(&mut camera, &config.views[stimulus.trial.view], model);
+                let elapsed = trial_start.elapsed().as_millis() as f32;
+                split_view::render_pane(
+                    model,
+                    &presets[stimulus.trial.preset],
+                    &camera,
+                    elapsed,
+                    &viewport,
+                );
+
+                let aspect = size.0 as f32 / size.1.max(1) as f32;
+                let mvp = camera.construct_mvp(aspect, na::Isometry3::identity());
+                for (label, point) in ["1", "2"].iter().zip(&stimulus.points) {
+                    if let Some(position) = project_to_window(&mvp, point, size) {
+                        draw_marker(&painter, egui::pos2(position.x, position.y), label);
+                    }
+                }
+                painter.text(
+                    egui::pos2(centre.x, 40.0),
+                    egui::Align2::CENTER_TOP,
+                    config.question(),
+                    font,
+                    egui::Color32::WHITE,
+                );
+            }
+            Phase::Pause(_) => {}
+            Phase::Finished => {
+                painter.text(
+                    centre,
+                    egui::Align2::CENTER_CENTER,
+                    "Thank you! Press any key to close.",
+                    font,
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+        let full_output = ctx.end_frame();
+        let primitives = ctx.tessellate(full_output.shapes);
+        renderer.egui_texture_delta(full_output.textures_delta);
+        viewport.set_used();
+        renderer.render_primitives(&primitives, size);
+        window.gl_swap_window();
+        render_gl::check_gl_error();
+
+        // Reaction times are measured from when the trial was first on screen.
+        if let Phase::Trial(None) = phase {
+            phase = Phase::Trial(Some(timer.ticks()));
+        }
+    }
+    eprintln!(
+        "Saved {} of {} responses to '{}'.",
+        current,
+        stimuli.len(),
+        log_path.display()
+    );
+    Ok(())
+}
+
+/// Draws a ring around a marked point, with its label next to it.
+fn draw_marker(painter: &egui::Painter, position: egui::Pos2, label: &str) {
+    painter.circle_stroke(
+        position,
+        MARKER_RADIUS,
+        egui::Stroke::new(5.0, egui::Color32::BLACK),
+    );
+    painter.circle_stroke(
+        position,
+        MARKER_RADIUS,
+        egui::Stroke::new(2.5, egui::Color32::WHITE),
+    );
+    let offset = egui::vec2(MARKER_RADIUS, -MARKER_RADIUS) * 1.2;
+    let font = egui::FontId::proportional(26.0);
+    let anchor = egui::Align2::LEFT_BOTTOM;
+    painter.text(
+        position + offset + egui::vec2(1.5, 1.5),
+        anchor,
+        label,
+        font.clone(),
+        egui::Color32::BLACK,
+    );
+    painter.text(position + offset, anchor, label, font, egui::Color32::WHITE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_and_trials() {
+        let config: StudyConfig = toml::from_str(
+            r#"
+            models = ["pre.stl", "post.stl"]
+            presets = ["aerial", "chroma-depth", "mine.toml"]
+            repetitions = 2
+            task = "farther"
+            seed = 7
+
+            [[views]]
+            azimuth = 30.0
+
+            [[views]]
+            azimuth = -30.0
+            elevation = 20.0
+            distance = 80.0
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.views[1].distance, Some(80.0));
+        assert_eq!(config.question(), Task::Farther.question());
+        assert_eq!(config.task.correct_answer([2.0, 1.0]), 1);
+        assert_eq!(Task::Closer.correct_answer([2.0, 1.0]), 2);
+
+        // Every combination is shown as often as it is repeated, in an order fixed by the seed.
+        let list = trials(&config, &mut Rng(7));
+        assert_eq!(list.len(), 2 * 3 * 2 * 2);
+        assert_eq!(list, trials(&config, &mut Rng(7)));
+        assert_ne!(list, trials(&config, &mut Rng(8)));
+        let mut sorted = list.clone();
+        sorted.sort();
+        assert!(sorted.chunks(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(sorted.windows(2).filter(|w| w[0] != w[1]).count(), 11);
+
+        let empty = StudyConfig::default();
+        assert!(empty.validate().is_err());
+        let bright = StudyConfig {
+            background: na::Vector3::new(0.5, 2.0, 0.5),
+            ..config.clone()
+        };
+        assert!(bright.validate().is_err());
+        assert!(toml::from_str::<StudyConfig>("modles = []").is_err());
+    }
+
+    #[test]
+    fn log_path_inside_output() {
+        let output = Path::new("results");
+        assert_eq!(log_path(output, "P01").unwrap(), output.join("P01.csv"));
+        for participant in ["", "../P01", "a/b", "a\\b"] {
+            assert!(log_path(output, participant).is_err(), "{}", participant);
+        }
+    }
+
+    #[test]
+    fn responses_logged() {
+        let dir = std::env::temp_dir().join(format!("ivrigst_study_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("P01.csv");
+        let mut log = ResponseLog::create(&path).unwrap();
+        let stimulus = Stimulus {
+            trial: Trial {
+                model: 0,
+                preset: 0,
+                view: 0,
+            },
+            points: [Point3::new(1.0, 2.0, 3.0), Point3::new(-1.0, 0.5, 0.0)],
+            depths: [10.0, 12.5],
+        };
+        log.record(&Response {
+            participant: "P01",
+            seed: 7,
+            number: 1,
+            model: "pre, op",
+            preset: "aerial",
+            view: &CameraView::default(),
+            stimulus: &stimulus,
+            correct: 1,
+            answer: 2,
+            reaction_ms: 812,
+        })
+        .unwrap();
+        drop(log);
+
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "P01,7,1,\"pre, op\",aerial,45,0,,1,2,3,-1,0.5,0,10,12.5,1,2,false,812"
+        );
+        // An existing log is never overwritten.
+        assert!(ResponseLog::create(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
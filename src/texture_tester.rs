@@ -24,14 +24,18 @@ struct Vertex {
     pub uv: data::f32_f32,
 }
 
-/// Renders textures to corners of the viewport. This is used to debug
-/// intermediate render passes for shadows and hatching.
+/// Bottom edges of the quads the textures are rendered to, stacked up the right side of the
+/// viewport in normalized device coordinates.
+const QUAD_BOTTOMS: [f32; 3] = [-1.0, -0.49, 0.02];
+/// Height of each quad in normalized device coordinates.
+const QUAD_HEIGHT: f32 = 0.49;
+
+/// Renders textures to the right side of the viewport. This is used to debug intermediate render
+/// passes for shadows, hatching and ambient occlusion.
 pub struct TextureTester {
     program: render_gl::Program,
-    vao1: buffer::VertexArray,
-    vao2: buffer::VertexArray,
-    _vbo1: buffer::ArrayBuffer,
-    _vbo2: buffer::ArrayBuffer,
+    /// A vertex array per quad, with the buffer of its vertices.
+    quads: Vec<(buffer::VertexArray, buffer::ArrayBuffer)>,
     ibo: buffer::ElementArrayBuffer,
     indices: i32,
 }
@@ -42,60 +46,39 @@ impl TextureTester {
         // Compile shader program
         let program = render_gl::Program::from_res(res, SHADER_PATH)?;
 
-        let vertices1: Vec<Vertex> = vec![
-            Vertex {
-                pos: f32_f32::from((0.5, -1.0)),
-                uv: f32_f32::from((0.0, 0.0)),
-            },
-            Vertex {
-                pos: f32_f32::from((1.0, -1.0)),
-                uv: f32_f32::from((1.0, 0.0)),
-            },
-            Vertex {
-                pos: f32_f32::from((0.5, -0.51)),
-                uv: f32_f32::from((0.0, 1.0)),
-            },
-            Vertex {
-                pos: f32_f32::from((1.0, -0.51)),
-                uv: f32_f32::from((1.0, 1.0)),
-            },
-        ];
-
-        let vertices2: Vec<Vertex> = vec![
-            Vertex {
-                pos: f32_f32::from((0.5, -0.49)),
-                uv: f32_f32::from((0.0, 0.0)),
-            },
-            Vertex {
-                pos: f32_f32::from((1.0, -0.49)),
-                uv: f32_f32::from((1.0, 0.0)),
-            },
-            Vertex {
-                pos: f32_f32::from((0.5, 0.0)),
-                uv: f32_f32::from((0.0, 1.0)),
-            },
-            Vertex {
-                pos: f32_f32::from((1.0, 0.0)),
-                uv: f32_f32::from((1.0, 1.0)),
-            },
-        ];
-
         // Build array buffers
-        let vbo1 = buffer::ArrayBuffer::new();
-        vbo1.bind();
-        vbo1.static_draw_data(&vertices1);
-        let vao1 = buffer::VertexArray::new();
-        vao1.bind();
-        Vertex::vertex_attrib_pointers();
-        vbo1.unbind();
-
-        let vbo2 = buffer::ArrayBuffer::new();
-        vbo2.bind();
-        vbo2.static_draw_data(&vertices2);
-        let vao2 = buffer::VertexArray::new();
-        vao2.bind();
-        Vertex::vertex_attrib_pointers();
-        vbo2.unbind();
+        let quads = QUAD_BOTTOMS
+            .iter()
+            .map(|&bottom| {
+                let top = bottom + QUAD_HEIGHT;
+                let vertices: Vec<Vertex> = vec![
+                    Vertex {
+                        pos: f32_f32::from((0.5, bottom)),
+                        uv: f32_f32::from((0.0, 0.0)),
+                    },
+                    Vertex {
+                        pos: f32_f32::from((1.0, bottom)),
+                        uv: f32_f32::from((1.0, 0.0)),
+                    },
+                    Vertex {
+                        pos: f32_f32::from((0.5, top)),
+                        uv: f32_f32::from((0.0, 1.0)),
+                    },
+                    Vertex {
+                        pos: f32_f32::from((1.0, top)),
+                        uv: f32_f32::from((1.0, 1.0)),
+                    },
+                ];
+                let vbo = buffer::ArrayBuffer::new();
+                vbo.bind();
+                vbo.static_draw_data(&vertices);
+                let vao = buffer::VertexArray::new();
+                vao.bind();
+                Vertex::vertex_attrib_pointers();
+                vbo.unbind();
+                (vao, vbo)
+            })
+            .collect();
 
         // Build indice buffer
         let indices: Vec<u32> = vec![0, 1, 2, 1, 2, 3];
@@ -106,48 +89,36 @@ impl TextureTester {
 
         let value = Self {
             program,
-            _vbo1: vbo1,
-            _vbo2: vbo2,
-            vao1,
-            vao2,
+            quads,
             ibo,
             indices: indices.len() as i32,
         };
         Ok(value)
     }
 
-    /// Render the given textures to the viewport.
-    pub fn render(&self, viewport: &Viewport, texture1: &Texture, texture2: &Texture) {
+    /// Render the given textures to the viewport, the first at the bottom. Textures beyond the
+    /// number of quads are left out.
+    pub fn render(&self, viewport: &Viewport, textures: &[&Texture]) {
         self.program.set_used();
         viewport.set_used();
 
-        self.vao1.bind();
-        self.ibo.bind();
         unsafe {
             gl::Disable(gl::CULL_FACE);
             gl::Disable(gl::BLEND);
             gl::Disable(gl::DEPTH_TEST);
-            texture1.bind_to(gl::TEXTURE0);
-            gl::DrawElements(
-                gl::TRIANGLES,
-                self.indices,
-                gl::UNSIGNED_INT,
-                std::ptr::null::<std::ffi::c_void>(),
-            );
-            texture1.unbind();
-            self.vao1.unbind();
-
-            self.vao2.bind();
-            self.ibo.bind();
-            texture2.bind_to(gl::TEXTURE0);
-            gl::DrawElements(
-                gl::TRIANGLES,
-                self.indices,
-                gl::UNSIGNED_INT,
-                std::ptr::null::<std::ffi::c_void>(),
-            );
-            texture2.unbind();
-            self.vao2.unbind();
+            for ((vao, _), texture) in self.quads.iter().zip(textures) {
+                vao.bind();
+                self.ibo.bind();
+                texture.bind_to(gl::TEXTURE0);
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    self.indices,
+                    gl::UNSIGNED_INT,
+                    std::ptr::null::<std::ffi::c_void>(),
+                );
+                texture.unbind();
+                vao.unbind();
+            }
         }
         self.ibo.unbind();
     }
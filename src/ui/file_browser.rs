@@ -0,0 +1,262 @@
+//! Contains [FileBrowser], a window for opening models and volumes from anywhere in the
+//! filesystem, which also remembers the most recently opened files between sessions.
+
+use crate::resources;
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Number of recently opened files to remember.
+const MAX_RECENT_FILES: usize = 10;
+/// Name of the file in the configuration directory listing the recently opened files.
+const RECENT_FILES_NAME: &str = "recent_files.txt";
+
+/// A directory or supported file shown in the browser.
+struct Entry {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// Window for navigating the filesystem and choosing a file to open.
+pub struct FileBrowser {
+    pub open: bool,
+    /// Whether the chosen file is added to the scene, rather than replacing it.
+    pub add_to_scene: bool,
+    directory: PathBuf,
+    entries: Vec<Entry>,
+    /// Reason the current directory could not be listed, shown to the user.
+    error: Option<String>,
+    /// Absolute paths of the recently opened files, most recent first.
+    recent_files: Vec<String>,
+    recent_files_path: Option<PathBuf>,
+}
+
+#[allow(clippy::new_without_default)]
+impl FileBrowser {
+    /// Initializes a closed [FileBrowser] in the working directory, loading the recently opened
+    /// files from the configuration directory.
+    pub fn new() -> Self {
+        let recent_files_path = resources::config_dir().map(|dir| dir.join(RECENT_FILES_NAME));
+        let recent_files = recent_files_path
+            .as_deref()
+            .and_then(|path| load_recent_files(path).ok())
+            .unwrap_or_default();
+        let mut browser = Self {
+            open: false,
+            add_to_scene: false,
+            directory: std::env::current_dir().unwrap_or_default(),
+            entries: Vec::new(),
+            error: None,
+            recent_files,
+            recent_files_path,
+        };
+        browser.refresh();
+        browser
+    }
+
+    /// Shows the contents of the given directory.
+    fn navigate(&mut self, directory: PathBuf) {
+        self.directory = directory;
+        self.refresh();
+    }
+
+    /// Lists the current directory again.
+    fn refresh(&mut self) {
+        match list_directory(&self.directory) {
+            Ok(entries) => {
+                self.entries = entries;
+                self.error = None;
+            }
+            Err(e) => {
+                self.entries.clear();
+                self.error = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    /// Remembers the given file as the most recently opened one, and saves the recent files. The
+    /// file is stored by its absolute path, so it can be opened from any working directory.
+    pub fn add_recent_file(&mut self, file: &Path) {
+        let file = match fs::canonicalize(file) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Failed to remember '{}': {}", file.display(), e);
+                return;
+            }
+        };
+        push_recent_file(&mut self.recent_files, &file.to_string_lossy());
+        if let Some(path) = &self.recent_files_path {
+            if let Err(e) = save_recent_files(path, &self.recent_files) {
+                eprintln!("Failed to save recent files: {:#}", e);
+            }
+        }
+    }
+
+    /// Builds the file browser window if it is open, setting `file_to_load` to the chosen file,
+    /// or `file_to_add` if it is to be added to the scene.
+    pub fn build_ui(
+        &mut self,
+        ctx: &egui::Context,
+        file_to_load: &mut String,
+        file_to_add: &mut Option<String>,
+    ) {
+        let mut open = self.open;
+        let mut directory = None;
+        let mut chosen = None;
+        egui::Window::new("Open file")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Up").clicked() {
+                        directory = self.directory.parent().map(Path::to_owned);
+                    }
+                    if ui.button("Refresh").clicked() {
+                        directory = Some(self.directory.clone());
+                    }
+                    ui.label(self.directory.to_string_lossy().as_ref());
+                });
+                ui.checkbox(&mut self.add_to_scene, "Add to scene")
+                    .on_hover_text("Show the file alongside the models already open.");
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        if let Some(error) = &self.error {
+                            ui.label(error);
+                        }
+                        for entry in &self.entries {
+                            if entry.is_dir {
+                                if ui
+                                    .selectable_label(false, format!("{}/", entry.name))
+                                    .clicked()
+                                {
+                                    directory = Some(entry.path.clone());
+                                }
+                            } else if ui.selectable_label(false, &entry.name).clicked() {
+                                chosen = Some(entry.path.to_string_lossy().into_owned());
+                            }
+                        }
+                    });
+
+                ui.collapsing("Recent files", |ui| {
+                    if self.recent_files.is_empty() {
+                        ui.label("No files opened yet.");
+                    }
+                    for file in &self.recent_files {
+                        let name = Path::new(file).file_name().unwrap_or_default();
+                        if ui
+                            .selectable_label(file_to_load == file, name.to_string_lossy().as_ref())
+                            .on_hover_text(file.as_str())
+                            .clicked()
+                        {
+                            chosen = Some(file.clone());
+                        }
+                    }
+                });
+            });
+        self.open = open;
+        match chosen {
+            Some(file) if self.add_to_scene => *file_to_add = Some(file),
+            Some(file) => *file_to_load = file,
+            None => {}
+        }
+        if let Some(directory) = directory {
+            self.navigate(directory);
+        }
+    }
+}
+
+/// Lists the subdirectories and supported files of a directory, directories first and each
+/// sorted by name. Hidden entries are skipped.
+fn list_directory(directory: &Path) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(directory).context("Failed to read directory")? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        let is_dir = path.is_dir();
+        if !name.starts_with('.') && (is_dir || resources::is_supported_file(&path)) {
+            entries.push(Entry { name, path, is_dir });
+        }
+    }
+    entries.sort_by_key(|entry| (!entry.is_dir, entry.name.to_lowercase()));
+    Ok(entries)
+}
+
+/// Moves or adds a file to the front of the recent files, forgetting the oldest ones.
+fn push_recent_file(recent_files: &mut Vec<String>, file: &str) {
+    recent_files.retain(|recent| recent != file);
+    recent_files.insert(0, file.to_owned());
+    recent_files.truncate(MAX_RECENT_FILES);
+}
+
+/// Loads the recent files, stored one per line. Relative paths are skipped, as it is not known
+/// what they were relative to.
+fn load_recent_files(path: &Path) -> Result<Vec<String>> {
+    let text = fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .filter(|line| Path::new(line).is_absolute())
+        .take(MAX_RECENT_FILES)
+        .map(str::to_owned)
+        .collect())
+}
+
+/// Saves the recent files one per line, creating the directory if needed.
+fn save_recent_files(path: &Path, recent_files: &[String]) -> Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).context("Failed to create configuration directory")?;
+    }
+    fs::write(path, recent_files.join("\n")).context("Failed to write recent files")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_files_order() {
+        let mut recent = Vec::new();
+        for i in 0..12 {
+            push_recent_file(&mut recent, &format!("{}.obj", i));
+        }
+        assert_eq!(recent.len(), MAX_RECENT_FILES);
+        assert_eq!(recent[0], "11.obj");
+        push_recent_file(&mut recent, "5.obj");
+        assert_eq!(recent[..3], ["5.obj", "11.obj", "10.obj"]);
+        assert_eq!(recent.len(), MAX_RECENT_FILES);
+    }
+
+    #[test]
+    fn directory_listing_and_recent_files() {
+        let dir = std::env::temp_dir().join(format!("ivrigst_browser_{}", std::process::id()));
+        fs::create_dir_all(dir.join("Models")).unwrap();
+        for file in ["b.stl", "A.obj", "head.nii.gz", "notes.txt", ".hidden.obj"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let names: Vec<String> = list_directory(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["Models", "A.obj", "b.stl", "head.nii.gz"]);
+
+        let recent_path = dir.join("config").join(RECENT_FILES_NAME);
+        let recent = vec!["/data/a.obj".to_owned(), "model.obj".to_owned()];
+        save_recent_files(&recent_path, &recent).unwrap();
+        assert_eq!(load_recent_files(&recent_path).unwrap(), ["/data/a.obj"]);
+
+        // Files are remembered by absolute path, wherever they were opened from.
+        let mut browser = FileBrowser::new();
+        browser.recent_files_path = None;
+        browser.add_recent_file(&dir.join("Models").join("..").join("A.obj"));
+        let absolute = fs::canonicalize(dir.join("A.obj")).unwrap();
+        assert_eq!(browser.recent_files[0], absolute.to_string_lossy());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
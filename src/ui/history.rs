@@ -0,0 +1,197 @@
+//! Contains [History], an undo and redo stack, and [Settings], the part of the user interface
+//! state recorded in it.
+
+use super::UiActions;
+use crate::model::Attributes;
+use nalgebra as na;
+use serde::Serialize;
+
+/// Number of steps kept in the history, after which the oldest ones are forgotten.
+const MAX_STEPS: usize = 100;
+
+/// A linear history of states that can be stepped back and forth through. Recording a new state
+/// forgets the states that were undone.
+pub struct History<T> {
+    /// Every state with a description of how it differs from the one before.
+    steps: Vec<(String, T)>,
+    /// Index of the current state in `steps`.
+    position: usize,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// Gets the current state, if any has been recorded.
+    pub fn current(&self) -> Option<&T> {
+        self.steps.get(self.position).map(|(_, state)| state)
+    }
+
+    /// Records a new current state, forgetting any undone states.
+    pub fn record(&mut self, description: String, state: T) {
+        self.steps.truncate(self.position + 1);
+        self.steps.push((description, state));
+        if self.steps.len() > MAX_STEPS {
+            self.steps.remove(0);
+        }
+        self.position = self.steps.len() - 1;
+    }
+
+    /// Steps back to the previous state, if there is one.
+    pub fn undo(&mut self) -> Option<&T> {
+        self.go_to(self.position.checked_sub(1)?)
+    }
+
+    /// Steps forward to the next state, if there is one.
+    pub fn redo(&mut self) -> Option<&T> {
+        self.go_to(self.position + 1)
+    }
+
+    /// Jumps to the state at the given index, if it exists.
+    pub fn go_to(&mut self, index: usize) -> Option<&T> {
+        let (_, state) = self.steps.get(index)?;
+        self.position = index;
+        Some(state)
+    }
+
+    /// Gets the index of the current state.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Gets the descriptions of every state, oldest first.
+    pub fn descriptions(&self) -> impl Iterator<Item = &str> {
+        self.steps
+            .iter()
+            .map(|(description, _)| description.as_str())
+    }
+}
+
+/// The settings changed through the user interface that can be undone.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Settings {
+    /// Shader attributes, with those depending on the camera, model or time left at default.
+    #[serde(flatten)]
+    pub attributes: Attributes,
+    pub background_colour: na::Vector3<f32>,
+    pub display_shader_buffers: bool,
+    pub iso_value: Option<f32>,
+    pub label_map: Option<bool>,
+}
+
+impl Settings {
+    /// Takes the current settings.
+    pub fn capture(attributes: &Attributes, ui_actions: &UiActions) -> Self {
+        let defaults = Attributes::default();
+        Self {
+            attributes: Attributes {
+                projection_matrix: defaults.projection_matrix,
+                camera_position: defaults.camera_position,
+                model_size: defaults.model_size,
+                elapsed: defaults.elapsed,
+                ..attributes.clone()
+            },
+            background_colour: ui_actions.clear_color,
+            display_shader_buffers: ui_actions.show_debug,
+            iso_value: ui_actions.volume.as_ref().map(|volume| volume.iso_value),
+            label_map: ui_actions.volume.as_ref().map(|volume| volume.show_labels),
+        }
+    }
+
+    /// Restores the settings, keeping the attributes that depend on the camera, model or time.
+    pub fn restore(&self, attributes: &mut Attributes, ui_actions: &mut UiActions) {
+        *attributes = Attributes {
+            projection_matrix: attributes.projection_matrix,
+            camera_position: attributes.camera_position,
+            model_size: attributes.model_size,
+            elapsed: attributes.elapsed,
+            ..self.attributes.clone()
+        };
+        ui_actions.clear_color = self.background_colour;
+        ui_actions.show_debug = self.display_shader_buffers;
+        if let Some(volume) = ui_actions.volume.as_mut() {
+            volume.iso_value = self.iso_value.unwrap_or(volume.iso_value);
+            volume.show_labels = self.label_map.unwrap_or(volume.show_labels);
+        }
+    }
+
+    /// Describes which settings differ from the given ones, such as "Toon factor, Hatching depth".
+    pub fn describe_changes(&self, previous: &Settings) -> String {
+        let (current, previous) = match (serde_json::to_value(self), serde_json::to_value(previous))
+        {
+            (Ok(serde_json::Value::Object(current)), Ok(serde_json::Value::Object(previous))) => {
+                (current, previous)
+            }
+            _ => return "Settings changed".to_owned(),
+        };
+        let changed: Vec<String> = current
+            .iter()
+            .filter(|(name, value)| previous.get(*name) != Some(value))
+            .map(|(name, _)| {
+                let name = name.replace('_', " ");
+                name[..1].to_uppercase() + &name[1..]
+            })
+            .collect();
+        changed.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo() {
+        let mut history = History::default();
+        assert_eq!(history.undo(), None);
+        for state in 0..3 {
+            history.record(state.to_string(), state);
+        }
+        assert_eq!(history.undo(), Some(&1));
+        assert_eq!(history.undo(), Some(&0));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo(), Some(&1));
+
+        // Recording after undoing forgets the undone states.
+        history.record("3".to_owned(), 3);
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.descriptions().collect::<Vec<_>>(), ["0", "1", "3"]);
+        assert_eq!(history.go_to(0), Some(&0));
+        assert_eq!(history.current(), Some(&0));
+    }
+
+    #[test]
+    fn oldest_steps_forgotten() {
+        let mut history = History::default();
+        for state in 0..MAX_STEPS + 5 {
+            history.record(String::new(), state);
+        }
+        assert_eq!(history.position(), MAX_STEPS - 1);
+        assert_eq!(history.go_to(0), Some(&5));
+    }
+
+    #[test]
+    fn changes_described() {
+        let previous = Settings {
+            attributes: Attributes::default(),
+            background_colour: na::Vector3::zeros(),
+            display_shader_buffers: false,
+            iso_value: None,
+            label_map: None,
+        };
+        let mut current = previous.clone();
+        current.attributes.toon_factor = 0.2;
+        current.attributes.hatching_depth = 2.0;
+        current.background_colour.x = 1.0;
+        assert_eq!(
+            current.describe_changes(&previous),
+            "Toon factor, Hatching depth, Background colour"
+        );
+    }
+}
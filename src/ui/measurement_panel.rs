@@ -0,0 +1,178 @@
+//! Contains [MeasurementPanel], a side panel for choosing what to measure and listing the
+//! measurements, and the labels shown next to them in the view.
+
+use crate::measurement::{LengthUnit, MeasurementKind, Measurements};
+use nalgebra as na;
+use std::path::Path;
+
+/// Colour of the measurement lines and labels.
+pub const MEASUREMENT_COLOR: [f32; 3] = [1.0, 0.85, 0.1];
+
+/// Side panel listing the measurements, with their units and CSV export.
+pub struct MeasurementPanel {
+    pub open: bool,
+    csv_path: String,
+    /// Result of the last export, shown to the user.
+    status: String,
+}
+
+#[allow(clippy::new_without_default)]
+impl MeasurementPanel {
+    /// Initializes a closed [MeasurementPanel].
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            csv_path: "measurements.csv".to_owned(),
+            status: String::new(),
+        }
+    }
+
+    /// Builds the panel if it is open. Closing it stops picking points. Measurements are exported
+    /// in file coordinates, given by `to_file`.
+    pub fn build_ui(
+        &mut self,
+        ctx: &egui::Context,
+        measurements: &mut Measurements,
+        to_file: &na::Isometry3<f32>,
+    ) {
+        if !self.open {
+            measurements.active = None;
+            measurements.cancel();
+            return;
+        }
+        egui::SidePanel::right("measurements")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Measurements");
+                    if ui.small_button("Close").clicked() {
+                        self.open = false;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let before = measurements.active;
+                    ui.selectable_value(&mut measurements.active, None, "Off");
+                    for kind in [MeasurementKind::Distance, MeasurementKind::Angle] {
+                        ui.selectable_value(&mut measurements.active, Some(kind), kind.to_string());
+                    }
+                    if measurements.active != before {
+                        measurements.cancel();
+                    }
+                });
+                ui.label(match measurements.active {
+                    Some(MeasurementKind::Distance) => "Click two points on the model.",
+                    Some(MeasurementKind::Angle) => {
+                        "Click three points on the model, with the angle at the second."
+                    }
+                    None => "Choose what to measure.",
+                });
+                ui.separator();
+
+                egui::Grid::new("measurement_units_grid")
+                    .striped(true)
+                    .spacing([40.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("Model units");
+                        unit_combo_box(ui, "model_unit", &mut measurements.model_unit);
+                        ui.end_row();
+
+                        ui.label("Show in");
+                        unit_combo_box(ui, "display_unit", &mut measurements.display_unit);
+                        ui.end_row();
+                    });
+                ui.separator();
+
+                let mut removed = None;
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        if measurements.list().is_empty() {
+                            ui.label("Nothing measured yet.");
+                        }
+                        for (index, measurement) in measurements.list().iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(format!(
+                                    "{}. {}: {}",
+                                    index + 1,
+                                    measurement.kind(),
+                                    measurements.label(measurement)
+                                ));
+                                if ui.small_button("Remove").clicked() {
+                                    removed = Some(index);
+                                }
+                            });
+                        }
+                    });
+                if let Some(index) = removed {
+                    measurements.remove(index);
+                }
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("File");
+                    ui.text_edit_singleline(&mut self.csv_path);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Export CSV").clicked() {
+                        self.status =
+                            match measurements.save_csv(Path::new(&self.csv_path), to_file) {
+                                Ok(()) => format!("Saved '{}'.", self.csv_path),
+                                Err(e) => format!("Export failed: {:#}", e),
+                            };
+                    }
+                    if ui.button("Clear").clicked() {
+                        measurements.clear();
+                    }
+                });
+                ui.label(&self.status);
+            });
+    }
+}
+
+fn unit_combo_box(ui: &mut egui::Ui, id: &str, unit: &mut LengthUnit) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(format!("{} ({})", unit, unit.symbol()))
+        .show_ui(ui, |ui| {
+            for option in LengthUnit::ALL {
+                ui.selectable_value(unit, option, format!("{} ({})", option, option.symbol()));
+            }
+        });
+}
+
+/// Draws the value of every measurement next to it in the view, behind all windows.
+/// `projection_matrix` is the model view projection matrix the model is rendered with into the
+/// given part of the screen.
+pub fn draw_measurement_labels(
+    ctx: &egui::Context,
+    measurements: &Measurements,
+    projection_matrix: &na::Matrix4<f32>,
+    screen: egui::Rect,
+) {
+    let painter = ctx
+        .layer_painter(egui::LayerId::new(
+            egui::Order::Background,
+            egui::Id::new("measurement_labels"),
+        ))
+        .with_clip_rect(screen);
+    let [r, g, b] = MEASUREMENT_COLOR.map(|c| (c * 255.0) as u8);
+    for measurement in measurements.list() {
+        let clip = projection_matrix * measurement.label_position().to_homogeneous();
+        // Skip labels behind the camera.
+        if clip.w <= 0.0 {
+            continue;
+        }
+        let ndc = clip.xy() / clip.w;
+        let position = egui::pos2(
+            screen.left() + (ndc.x + 1.0) / 2.0 * screen.width(),
+            screen.top() + (1.0 - ndc.y) / 2.0 * screen.height(),
+        );
+        let text = measurements.label(measurement);
+        let font = egui::FontId::proportional(16.0);
+        let galley = painter.layout_no_wrap(text, font, egui::Color32::from_rgb(r, g, b));
+        let rect = egui::Align2::LEFT_BOTTOM
+            .anchor_rect(egui::Rect::from_min_size(position, galley.size()))
+            .translate(egui::vec2(6.0, -6.0));
+        painter.rect_filled(rect.expand(2.0), 2.0, egui::Color32::from_black_alpha(160));
+        painter.galley(rect.min, galley);
+    }
+}
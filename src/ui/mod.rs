@@ -1,9 +1,19 @@
 //! This module contains UI-related code.
 
+mod file_browser;
+mod history;
+mod measurement_panel;
+mod preset_dialog;
 mod render;
 mod sdl2_egui_translation;
+mod section_view;
 mod ui_builder;
 
+pub use file_browser::FileBrowser;
+pub use history::{History, Settings};
+pub use measurement_panel::{draw_measurement_labels, MeasurementPanel, MEASUREMENT_COLOR};
+pub use preset_dialog::{PresetDialog, PresetDialogEvent, PresetDialogMode};
 pub use render::UIRenderer;
 pub use sdl2_egui_translation::*;
-pub use ui_builder::{UiActions, UI};
+pub use section_view::SectionView;
+pub use ui_builder::{UiActions, VolumeSettings, UI};
@@ -0,0 +1,109 @@
+//! Contains [PresetDialog], a window for saving the current settings to a preset file, or loading
+//! one from anywhere in the filesystem.
+
+use crate::preset::{self, PresetFile};
+use std::path::Path;
+
+/// Whether the [PresetDialog] saves or loads a preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresetDialogMode {
+    Save,
+    Load,
+}
+
+/// What happened in the [PresetDialog] this frame.
+pub enum PresetDialogEvent {
+    Saved,
+    Loaded(Box<PresetFile>),
+}
+
+/// Window asking for the path of a preset file to save or load.
+pub struct PresetDialog {
+    mode: Option<PresetDialogMode>,
+    path: String,
+    /// Result of the last save or load, shown to the user.
+    status: String,
+}
+
+#[allow(clippy::new_without_default)]
+impl PresetDialog {
+    /// Initializes a closed [PresetDialog], pointing to a file in the presets directory.
+    pub fn new() -> Self {
+        let path = preset::presets_dir()
+            .map(|dir| dir.join("preset.toml"))
+            .unwrap_or_else(|| "preset.toml".into());
+        Self {
+            mode: None,
+            path: path.to_string_lossy().into_owned(),
+            status: String::new(),
+        }
+    }
+
+    /// Opens the dialog in the given mode.
+    pub fn open(&mut self, mode: PresetDialogMode) {
+        self.mode = Some(mode);
+        self.status.clear();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    /// Builds the dialog window if it is open. When saving, `current` holds the settings to save.
+    pub fn build_ui(
+        &mut self,
+        ctx: &egui::Context,
+        current: Option<PresetFile>,
+    ) -> Option<PresetDialogEvent> {
+        let mode = self.mode?;
+        let title = match mode {
+            PresetDialogMode::Save => "Save preset",
+            PresetDialogMode::Load => "Load preset",
+        };
+        let mut open = true;
+        let mut event = None;
+        egui::Window::new(title)
+            .open(&mut open)
+            .auto_sized()
+            .show(ctx, |ui| {
+                ui.label("Presets are saved as TOML, or as JSON with a .json extension.");
+                ui.horizontal(|ui| {
+                    ui.label("File");
+                    ui.text_edit_singleline(&mut self.path);
+                    let path = Path::new(&self.path);
+                    match mode {
+                        PresetDialogMode::Save => {
+                            let clicked = ui
+                                .add_enabled(current.is_some(), egui::Button::new("Save"))
+                                .clicked();
+                            if let Some(current) = current.filter(|_| clicked) {
+                                self.status = match current.save(path) {
+                                    Ok(()) => {
+                                        event = Some(PresetDialogEvent::Saved);
+                                        format!("Saved '{}'.", self.path)
+                                    }
+                                    Err(e) => format!("Saving failed: {:#}", e),
+                                };
+                            }
+                        }
+                        PresetDialogMode::Load => {
+                            if ui.button("Load").clicked() {
+                                self.status = match PresetFile::load(path) {
+                                    Ok(preset) => {
+                                        event = Some(PresetDialogEvent::Loaded(Box::new(preset)));
+                                        format!("Loaded '{}'.", self.path)
+                                    }
+                                    Err(e) => format!("Loading failed: {:#}", e),
+                                };
+                            }
+                        }
+                    }
+                });
+                ui.label(&self.status);
+            });
+        if !open {
+            self.mode = None;
+        }
+        event
+    }
+}
@@ -89,7 +89,7 @@ impl UIRenderer {
     /// left unimplemented.
     pub fn egui_texture_delta(&self, textures_delta: egui::TexturesDelta) {
         // Free texture_ids no longer in use:
-        for _texture_id in textures_delta.free {
+        if !textures_delta.free.is_empty() {
             unimplemented!("Freeing egui textures is not currently implemented.");
         }
 
@@ -139,6 +139,27 @@ impl UIRenderer {
         }
     }
 
+    /// Renders tessellated `egui` output. Only meshes are supported.
+    pub fn render_primitives(
+        &self,
+        primitives: &[egui::ClippedPrimitive],
+        window_size: (u32, u32),
+    ) {
+        for egui::ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in primitives
+        {
+            let mesh = match primitive {
+                egui::epaint::Primitive::Mesh(mesh) => mesh,
+                _ => panic!("Custom paint callbacks and other primitives are not supported."),
+            };
+            debug_assert!(mesh.is_valid());
+
+            self.render(&mesh.vertices, &mesh.indices, *clip_rect, window_size);
+        }
+    }
+
     /// Renders `egui`'s vertices.
     pub fn render(
         &self,
@@ -0,0 +1,161 @@
+//! Contains [SectionView], a window showing the cross-section of the model at the selected
+//! clipping plane, with its area, perimeter and equivalent diameter.
+
+use crate::{
+    clipping::{ClipPlane, Clipping},
+    measurement::Measurements,
+    section::CrossSection,
+};
+use nalgebra as na;
+
+/// Side length of the drawing of the cross-section, in points.
+const VIEW_SIZE: f32 = 300.0;
+
+/// Placement of a model in the scene and which of its objects are shown, which the cross-section
+/// depends on.
+type SceneKey = (na::Isometry3<f32>, Vec<bool>);
+
+/// Window drawing the contours where the selected plane cuts the model.
+pub struct SectionView {
+    pub open: bool,
+    /// Cross-section last extracted, with the plane, scene and size it was extracted for, so it
+    /// is only extracted again when one of them changes.
+    cache: Option<(ClipPlane, Vec<SceneKey>, na::Vector3<f32>, CrossSection)>,
+}
+
+#[allow(clippy::new_without_default)]
+impl SectionView {
+    /// Initializes a closed [SectionView].
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            cache: None,
+        }
+    }
+
+    /// Builds the window if it is open.
+    pub fn build_ui(
+        &mut self,
+        ctx: &egui::Context,
+        model: &mut crate::Model,
+        clipping: &Clipping,
+        measurements: &Measurements,
+    ) {
+        if !self.open {
+            self.cache = None;
+            return;
+        }
+        let section = clipping.selected_plane().map(|plane| {
+            let scene: Vec<SceneKey> = model
+                .get_models_mut()
+                .iter_mut()
+                .map(|scene_model| {
+                    let visible = scene_model.is_visible();
+                    let isometry = *scene_model.get_isometry();
+                    let objects = scene_model
+                        .get_objects_mut()
+                        .iter()
+                        .map(|object| visible && object.visible)
+                        .collect();
+                    (isometry, objects)
+                })
+                .collect();
+            let size = model.get_size();
+            match self.cache.take() {
+                Some(cache) if cache.0 == *plane && cache.1 == scene && cache.2 == size => {
+                    self.cache.insert(cache)
+                }
+                _ => {
+                    let section = model.cross_section(plane);
+                    self.cache.insert((*plane, scene, size, section))
+                }
+            }
+        });
+        let section = section.map(|cache| &cache.3);
+        let [r, g, b] = clipping.cap_color.map(|c| (c * 255.0) as u8).into();
+        let color = egui::Color32::from_rgb(r, g, b);
+
+        egui::Window::new("Cross-section")
+            .open(&mut self.open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some(section) = section else {
+                    ui.label("Select a clipping plane to show where it cuts the model.");
+                    return;
+                };
+                draw_section(ui, section, color);
+
+                egui::Grid::new("cross_section_grid")
+                    .striped(true)
+                    .spacing([40.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("Area");
+                        ui.label(measurements.format_area(section.area()));
+                        ui.end_row();
+
+                        ui.label("Perimeter");
+                        ui.label(measurements.format_length(section.perimeter()));
+                        ui.end_row();
+
+                        ui.label("Equivalent diameter");
+                        ui.label(measurements.format_length(section.equivalent_diameter()))
+                            .on_hover_text("Diameter of the circle with the same area.");
+                        ui.end_row();
+
+                        ui.label("Contours");
+                        ui.label(section.contours.len().to_string());
+                        ui.end_row();
+                    });
+                if section.contours.iter().any(|contour| !contour.closed) {
+                    ui.label(
+                        "Some contours are open, where the surface has holes. \
+                         They are not counted in the area.",
+                    );
+                }
+            });
+    }
+}
+
+/// Draws the contours, seen against the plane normal and scaled to fit the view.
+fn draw_section(ui: &mut egui::Ui, section: &CrossSection, color: egui::Color32) {
+    let (response, painter) =
+        ui.allocate_painter(egui::vec2(VIEW_SIZE, VIEW_SIZE), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+
+    let contours: Vec<Vec<na::Point2<f32>>> = section
+        .contours
+        .iter()
+        .map(|contour| contour.points.iter().map(|p| section.to_plane(p)).collect())
+        .collect();
+    let mut points = contours.iter().flatten();
+    let Some(first) = points.next() else {
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "The plane does not cut the model.",
+            egui::FontId::proportional(14.0),
+            egui::Color32::GRAY,
+        );
+        return;
+    };
+    let (min, max) = points.fold((*first, *first), |(min, max), p| (min.inf(p), max.sup(p)));
+    let extent = (max - min).max().max(f32::EPSILON);
+    let scale = (VIEW_SIZE - 20.0) / extent;
+    let centre = na::center(&min, &max);
+    // Plane coordinates have y pointing up, the screen has it pointing down.
+    let to_screen =
+        |p: &na::Point2<f32>| rect.center() + egui::vec2(p.x - centre.x, centre.y - p.y) * scale;
+
+    for (contour, points) in section.contours.iter().zip(&contours) {
+        let mut points: Vec<egui::Pos2> = points.iter().map(to_screen).collect();
+        if contour.closed {
+            points.extend(points.first().copied());
+        }
+        let stroke = match contour.closed {
+            true => egui::Stroke::new(2.0, color),
+            false => egui::Stroke::new(1.0, egui::Color32::YELLOW),
+        };
+        painter.add(egui::Shape::line(points, stroke));
+    }
+}
@@ -1,63 +1,114 @@
 //! Contains the UI construction and interaction code.
 
 use crate::{
-    model::{Attributes, DistanceShadingChannel},
-    resources::Resources,
+    ambient_occlusion,
+    bvh::Hit,
+    camera::{CameraMode, CameraView},
+    clipping::{ClipPlane, Clipping, MAX_CLIP_PLANES},
+    edges,
+    measurement::Measurements,
+    model::{Attributes, ColorSource},
+    preset::{self, Preset, PresetFile},
+    resources::{self, Resources},
+    split_view::{Layout, PaneRect, SplitView},
     ui::sdl2_egui_translation::egui_to_sdl2_cursor,
 };
 use anyhow::{anyhow, Result};
 use nalgebra as na;
+use std::path::Path;
 
-use super::UIRenderer;
+use super::{
+    draw_measurement_labels, FileBrowser, History, MeasurementPanel, PresetDialog,
+    PresetDialogEvent, PresetDialogMode, SectionView, Settings, UIRenderer,
+};
 
 /// Main struct for handling the user interface.
 pub struct UI {
     pub renderer: UIRenderer,
     preset: Preset,
+    /// Preset file chosen by the user, used instead of [UI::preset] if set.
+    user_preset: Option<PresetFile>,
+    user_presets: Vec<PresetFile>,
+    preset_dialog: PresetDialog,
     model_files: Vec<String>,
+    file_browser: FileBrowser,
+    history: History<Settings>,
+    measurement_panel: MeasurementPanel,
+    section_view: SectionView,
 }
 
 /// Describes actions the UI wishes the backend to execute.
 pub struct UiActions {
     pub show_debug: bool,
     pub file_to_load: String,
+    /// File to add to the scene, alongside the models already shown.
+    pub file_to_add: Option<String>,
     pub clear_color: na::Vector3<f32>,
+    pub volume: Option<VolumeSettings>,
+    pub export: ExportSettings,
+    /// Current camera view, to be saved in presets.
+    pub camera: CameraView,
+    /// Camera view to switch to, set when a preset is loaded.
+    pub camera_to_apply: Option<CameraView>,
+    pub camera_mode: CameraMode,
+    /// Surface point last double-clicked, which the camera rotates around.
+    pub picked: Option<Hit>,
+    /// Distances and angles measured on the model, and what clicking it measures next.
+    pub measurements: Measurements,
+    pub clipping: Clipping,
+    pub split_view: SplitView,
 }
 
-/// Describes visualization presets.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Preset {
-    Aerial,
-    ChromaDepth,
-    Plain,
+/// Describes how to export the current view as an image.
+pub struct ExportSettings {
+    pub requested: bool,
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub include_ui: bool,
+    /// Result of the last export, shown to the user.
+    pub status: String,
 }
 
-impl Preset {
-    pub fn description(&self) -> &'static str {
-        match self {
-            Preset::Plain => "Plain",
-            Preset::Aerial => "Aerial",
-            Preset::ChromaDepth => "Colored depth",
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            requested: false,
+            path: "screenshot.png".to_owned(),
+            width: 4000,
+            height: 3000,
+            include_ui: false,
+            status: String::new(),
         }
     }
 }
 
-impl std::fmt::Display for Preset {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        f.write_str(self.description())
-    }
+/// Describes the surface extraction settings of a loaded volume.
+pub struct VolumeSettings {
+    pub iso_value: f32,
+    pub value_range: (f32, f32),
+    pub extracting: bool,
+    /// Whether the volume looks like a label map, allowing one surface per label.
+    pub is_label_map: bool,
+    pub show_labels: bool,
 }
 
 impl UI {
-    /// Initialize the user interface and it's rendering code.
-    pub fn new(res: &Resources) -> Result<Self> {
+    /// Initialize the user interface and it's rendering code, starting with the given preset.
+    pub fn new(res: &Resources, preset: Preset) -> Result<Self> {
         let renderer = UIRenderer::new(res)?;
-        let preset = Preset::Plain;
         let model_files = res.list_models();
         Ok(Self {
             renderer,
             preset,
+            user_preset: None,
+            user_presets: preset::load_user_presets(),
+            preset_dialog: PresetDialog::new(),
             model_files,
+            file_browser: FileBrowser::new(),
+            history: History::default(),
+            measurement_panel: MeasurementPanel::new(),
+            section_view: SectionView::new(),
         })
     }
 
@@ -87,34 +138,389 @@ impl UI {
                     selected_file.push_str(&ui_actions.file_to_load);
                     selected_file.push('\'');
                 }
-                egui::ComboBox::from_id_source("model")
-                    .selected_text(selected_file)
-                    .show_ui(ui, |ui| {
-                        for file in &self.model_files {
-                            ui.selectable_value(&mut ui_actions.file_to_load, file.clone(), file);
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("model")
+                        .selected_text(selected_file)
+                        .show_ui(ui, |ui| {
+                            for file in &self.model_files {
+                                ui.selectable_value(
+                                    &mut ui_actions.file_to_load,
+                                    file.clone(),
+                                    file,
+                                );
+                            }
+                        });
+                    if ui
+                        .button("Open…")
+                        .on_hover_text("Browse for a file, or drop one onto the window.")
+                        .clicked()
+                    {
+                        self.file_browser.add_to_scene = false;
+                        self.file_browser.open = true;
+                    }
+                });
+                ui.end_row();
+
+                if let Some(volume) = ui_actions.volume.as_mut() {
+                    ui.horizontal(|ui| {
+                        if volume.is_label_map {
+                            ui.checkbox(&mut volume.show_labels, "Label map")
+                                .on_hover_text("Show every label as a separate object.");
+                        }
+                        ui.add_enabled_ui(!volume.show_labels, |ui| {
+                            ui.label("Iso-value");
+                            let (min, max) = volume.value_range;
+                            ui.add(egui::Slider::new(&mut volume.iso_value, min..=max));
+                        });
+                        if volume.extracting {
+                            ui.spinner();
                         }
                     });
-                ui.end_row();
+                }
 
                 if let Some(model) = model {
                     let mut attr = model.get_attributes().clone();
 
                     ui.label("Choose visualization preset:");
 
-                    ui.horizontal(|ui| {
-                        if ui.button(Preset::Plain.description()).clicked() {
-                            self.preset = Preset::Plain;
-                            attr = self.apply_preset(model);
+                    ui.horizontal_wrapped(|ui| {
+                        for preset in [Preset::Plain, Preset::Aerial, Preset::ChromaDepth] {
+                            if ui.button(preset.description()).clicked() {
+                                self.preset = preset;
+                                self.user_preset = None;
+                                attr = self.apply_preset(model);
+                            }
                         }
-                        if ui.button(Preset::Aerial.description()).clicked() {
-                            self.preset = Preset::Aerial;
-                            attr = self.apply_preset(model);
+                        let mut chosen = None;
+                        for preset in &self.user_presets {
+                            if ui
+                                .button(&preset.name)
+                                .on_hover_text("Saved preset, with background and camera.")
+                                .clicked()
+                            {
+                                chosen = Some(preset.clone());
+                            }
                         }
-                        if ui.button(Preset::ChromaDepth.description()).clicked() {
-                            self.preset = Preset::ChromaDepth;
+                        if let Some(preset) = chosen {
+                            self.use_preset_file(preset, ui_actions);
                             attr = self.apply_preset(model);
                         }
                     });
+                    ui.horizontal(|ui| {
+                        if ui.button("Save preset…").clicked() {
+                            self.preset_dialog.open(PresetDialogMode::Save);
+                        }
+                        if ui.button("Load preset…").clicked() {
+                            self.preset_dialog.open(PresetDialogMode::Load);
+                        }
+                        if ui
+                            .button("Measure…")
+                            .on_hover_text("Measure distances and angles on the model.")
+                            .clicked()
+                        {
+                            self.measurement_panel.open = !self.measurement_panel.open;
+                        }
+                    });
+
+                    ui.collapsing("Objects", |ui| {
+                        let removable = model.get_models_mut().len() > 1;
+                        let mut removed = None;
+                        let mut shown = None;
+                        let mut placed = None;
+                        for (index, scene_model) in model.get_models_mut().iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                let mut visible = scene_model.is_visible();
+                                if ui.checkbox(&mut visible, &scene_model.name).changed() {
+                                    shown = Some((index, visible));
+                                }
+                                let color = &mut scene_model.color;
+                                let mut rgb = [color[0], color[1], color[2]];
+                                ui.color_edit_button_rgb(&mut rgb)
+                                    .on_hover_text("Tints every object of the model.");
+                                *color = na::Vector3::from(rgb);
+                                if removable && ui.small_button("Remove").clicked() {
+                                    removed = Some(index);
+                                }
+                            });
+                            ui.indent(("model", index), |ui| {
+                                egui::CollapsingHeader::new("Placement")
+                                    .id_source(("placement", index))
+                                    .show(ui, |ui| {
+                                        let mut isometry = *scene_model.get_isometry();
+                                        let speed = scene_model.get_size().magnitude() * 0.005;
+                                        if placement_grid(ui, index, speed, &mut isometry) {
+                                            placed = Some((index, isometry));
+                                        }
+                                    });
+                                egui::Grid::new(("objects_grid", index))
+                                    .striped(true)
+                                    .spacing([40.0, 4.0])
+                                    .show(ui, |ui| {
+                                        for object in scene_model.get_objects_mut() {
+                                            let size = object.get_size();
+                                            ui.checkbox(&mut object.visible, &object.name)
+                                                .on_hover_text(format!(
+                                                    "Size: {:.1} x {:.1} x {:.1}",
+                                                    size.x, size.y, size.z
+                                                ));
+                                            let mut color =
+                                                [object.color[0], object.color[1], object.color[2]];
+                                            ui.color_edit_button_rgb(&mut color);
+                                            object.color = na::Vector3::from(color);
+                                            ui.end_row();
+                                        }
+                                    });
+                            });
+                        }
+                        if let Some((index, visible)) = shown {
+                            model.set_visible(index, visible);
+                        }
+                        if let Some((index, isometry)) = placed {
+                            model.set_placement(index, isometry);
+                        }
+                        if let Some(index) = removed {
+                            model.remove(index);
+                            ui_actions.picked = None;
+                        }
+                        if ui
+                            .button("Add model…")
+                            .on_hover_text("Show another model alongside, to compare them.")
+                            .clicked()
+                        {
+                            self.file_browser.add_to_scene = true;
+                            self.file_browser.open = true;
+                        }
+                        ui.separator();
+                        match &ui_actions.picked {
+                            Some(hit) => {
+                                ui.label(format!(
+                                    "Rotation centre: {:.2}, {:.2}, {:.2}",
+                                    hit.point.x, hit.point.y, hit.point.z
+                                ));
+                                ui.label(format!(
+                                    "Triangle {}, normal {:.2}, {:.2}, {:.2}",
+                                    hit.triangle, hit.normal.x, hit.normal.y, hit.normal.z
+                                ));
+                            }
+                            None => {
+                                ui.label("Double-click the model to rotate around that point.");
+                            }
+                        }
+                    });
+
+                    ui.collapsing("Clipping", |ui| {
+                        let clipping = &mut ui_actions.clipping;
+                        let extent = model.get_size().magnitude() / 2.0;
+                        let mut removed = None;
+                        for (index, plane) in clipping.planes.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut plane.enabled, "");
+                                let selected = clipping.selected == Some(index);
+                                if ui
+                                    .selectable_label(selected, format!("Plane {}", index + 1))
+                                    .on_hover_text("Show the gizmo for moving and rotating it.")
+                                    .clicked()
+                                {
+                                    clipping.selected = match selected {
+                                        true => None,
+                                        false => Some(index),
+                                    };
+                                }
+                                if ui.small_button("Remove").clicked() {
+                                    removed = Some(index);
+                                }
+                            });
+                            egui::Grid::new(("clip_plane_grid", index))
+                                .striped(true)
+                                .spacing([40.0, 4.0])
+                                .show(ui, |ui| {
+                                    ui.label("Offset");
+                                    ui.add(egui::Slider::new(&mut plane.offset, -extent..=extent));
+                                    ui.end_row();
+
+                                    ui.label("Azimuth");
+                                    ui.add(
+                                        egui::Slider::new(&mut plane.azimuth, -180.0..=180.0)
+                                            .suffix("°"),
+                                    );
+                                    ui.end_row();
+
+                                    ui.label("Elevation");
+                                    ui.add(
+                                        egui::Slider::new(&mut plane.elevation, -90.0..=90.0)
+                                            .suffix("°"),
+                                    );
+                                    ui.end_row();
+                                });
+                        }
+                        if let Some(index) = removed {
+                            clipping.planes.remove(index);
+                            clipping.selected = None;
+                        }
+                        ui.horizontal(|ui| {
+                            let can_add = clipping.planes.len() < MAX_CLIP_PLANES;
+                            if ui
+                                .add_enabled(can_add, egui::Button::new("Add plane"))
+                                .on_hover_text(
+                                    "Drag the arrow of the selected plane to move it, \
+                                     and its outline to rotate it.",
+                                )
+                                .clicked()
+                            {
+                                clipping.planes.push(ClipPlane::default());
+                                clipping.selected = Some(clipping.planes.len() - 1);
+                            }
+                            ui.checkbox(&mut clipping.cap, "Fill cut");
+                            let mut color = [
+                                clipping.cap_color[0],
+                                clipping.cap_color[1],
+                                clipping.cap_color[2],
+                            ];
+                            ui.color_edit_button_rgb(&mut color);
+                            clipping.cap_color = na::Vector3::from(color);
+                        });
+                        if ui
+                            .button("Cross-section…")
+                            .on_hover_text(
+                                "Show where the selected plane cuts the model, with its area. \
+                                 Disable the plane to keep the model whole.",
+                            )
+                            .clicked()
+                        {
+                            self.section_view.open = !self.section_view.open;
+                        }
+                    });
+
+                    ui.collapsing("Split view", |ui| {
+                        let split = &mut ui_actions.split_view;
+                        ui.horizontal(|ui| {
+                            for layout in Layout::ALL {
+                                ui.selectable_value(&mut split.layout, layout, layout.to_string());
+                            }
+                        });
+                        split.fill_panes(&attr);
+                        let count = split.visible_panes().len();
+                        for (index, pane) in split.panes.iter_mut().take(count).enumerate() {
+                            ui.separator();
+                            ui.label(format!("Pane {}: {}", index + 2, pane.name));
+                            ui.horizontal_wrapped(|ui| {
+                                for preset in [Preset::Plain, Preset::Aerial, Preset::ChromaDepth] {
+                                    if ui.small_button(preset.description()).clicked() {
+                                        preset.apply(&mut pane.attributes);
+                                        pane.name = preset.description().to_owned();
+                                    }
+                                }
+                                for preset in &self.user_presets {
+                                    if ui.small_button(&preset.name).clicked() {
+                                        preset.apply(&mut pane.attributes);
+                                        pane.name = preset.name.clone();
+                                    }
+                                }
+                                if ui
+                                    .small_button("Same as main")
+                                    .on_hover_text("Use the settings of this window.")
+                                    .clicked()
+                                {
+                                    pane.attributes = attr.clone();
+                                    pane.name = self.preset_name().to_owned();
+                                }
+                            });
+                            egui::ComboBox::from_id_source(("pane_model", index))
+                                .selected_text(pane.file.as_deref().unwrap_or("Main model"))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut pane.file, None, "Main model");
+                                    for file in &self.model_files {
+                                        ui.selectable_value(
+                                            &mut pane.file,
+                                            Some(file.clone()),
+                                            file,
+                                        );
+                                    }
+                                });
+                        }
+                        if count > 0 {
+                            ui.label(
+                                "The camera is shared by all panes. Pick, measure and move \
+                                 clipping planes in the first one.",
+                            );
+                        }
+                    });
+
+                    ui.collapsing("History", |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.button("Undo").on_hover_text("Ctrl+Z").clicked() {
+                                if let Some(settings) = self.history.undo() {
+                                    settings.restore(&mut attr, ui_actions);
+                                }
+                            }
+                            if ui.button("Redo").on_hover_text("Ctrl+Shift+Z").clicked() {
+                                if let Some(settings) = self.history.redo() {
+                                    settings.restore(&mut attr, ui_actions);
+                                }
+                            }
+                        });
+                        let mut chosen = None;
+                        egui::ScrollArea::vertical()
+                            .max_height(150.0)
+                            .stick_to_bottom()
+                            .show(ui, |ui| {
+                                let position = self.history.position();
+                                for (index, description) in self.history.descriptions().enumerate()
+                                {
+                                    if ui
+                                        .selectable_label(index == position, description)
+                                        .clicked()
+                                    {
+                                        chosen = Some(index);
+                                    }
+                                }
+                            });
+                        if let Some(settings) = chosen.and_then(|index| self.history.go_to(index)) {
+                            settings.restore(&mut attr, ui_actions);
+                        }
+                    });
+
+                    ui.collapsing("Export image", |ui| {
+                        let export = &mut ui_actions.export;
+                        egui::Grid::new("export_grid")
+                            .striped(true)
+                            .spacing([40.0, 4.0])
+                            .show(ui, |ui| {
+                                ui.label("File");
+                                ui.text_edit_singleline(&mut export.path);
+                                ui.end_row();
+
+                                ui.label("Size");
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        egui::DragValue::new(&mut export.width)
+                                            .clamp_range(1..=16384),
+                                    );
+                                    ui.label("x");
+                                    ui.add(
+                                        egui::DragValue::new(&mut export.height)
+                                            .clamp_range(1..=16384),
+                                    );
+                                });
+                                ui.end_row();
+
+                                ui.label("Include user interface");
+                                ui.checkbox(&mut export.include_ui, "").on_hover_text(
+                                    "Only if the image has the same aspect ratio as the window.",
+                                );
+                                ui.end_row();
+                            });
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button("Export PNG")
+                                .on_hover_text("Shortcut: F12")
+                                .clicked()
+                            {
+                                export.requested = true;
+                            }
+                            ui.label(&export.status);
+                        });
+                    });
 
                     ui.collapsing("Advanced", |ui| {
                         egui::Grid::new("settings_grid")
@@ -138,6 +544,24 @@ impl UI {
                                 attr.color = na::Vector3::from(color);
                                 ui.end_row();
 
+                                ui.label("Model colour source");
+                                egui::ComboBox::from_id_source("color_source")
+                                    .selected_text(attr.color_source.to_string())
+                                    .show_ui(ui, |ui| {
+                                        for source in [
+                                            ColorSource::Vertex,
+                                            ColorSource::Material,
+                                            ColorSource::Flat,
+                                        ] {
+                                            ui.selectable_value(
+                                                &mut attr.color_source,
+                                                source,
+                                                source.to_string(),
+                                            );
+                                        }
+                                    });
+                                ui.end_row();
+
                                 ui.label("Model colouring mix");
                                 ui.add(egui::Slider::new(&mut attr.vertex_color_mix, 0.0..=1.0));
                                 ui.end_row();
@@ -184,6 +608,24 @@ impl UI {
 
                                 ui.end_row();
 
+                                ui.label("Camera rotation");
+                                egui::ComboBox::from_id_source("camera_mode")
+                                    .selected_text(ui_actions.camera_mode.to_string())
+                                    .show_ui(ui, |ui| {
+                                        for mode in [CameraMode::Orbit, CameraMode::Arcball] {
+                                            ui.selectable_value(
+                                                &mut ui_actions.camera_mode,
+                                                mode,
+                                                mode.to_string(),
+                                            );
+                                        }
+                                    })
+                                    .response
+                                    .on_hover_text(
+                                        "Drag with the right or middle mouse button to pan.",
+                                    );
+                                ui.end_row();
+
                                 ui.label("Display shader buffers");
                                 ui.checkbox(&mut ui_actions.show_debug, "");
                                 ui.end_row();
@@ -275,6 +717,100 @@ impl UI {
                                     ui.end_row();
                                 })
                         });
+
+                        ui.collapsing("Ambient occlusion settings", |ui| {
+                            egui::Grid::new("ambient_occlusion_settings_grid")
+                                .striped(true)
+                                .spacing([40.0, 4.0])
+                                .show(ui, |ui| {
+                                    ui.label("Occlusion intensity");
+                                    ui.add(egui::Slider::new(
+                                        &mut attr.ambient_occlusion_intensity,
+                                        0.0..=1.0,
+                                    ))
+                                    .on_hover_text(
+                                        "Darkens creases and places where vessels lie close \
+                                         together. 0 turns it off.",
+                                    );
+                                    ui.end_row();
+
+                                    let enabled = attr.ambient_occlusion_intensity > 0.0;
+                                    ui.label("Occlusion radius");
+                                    ui.add_enabled(
+                                        enabled,
+                                        egui::Slider::new(
+                                            &mut attr.ambient_occlusion_radius,
+                                            0.005..=0.2,
+                                        ),
+                                    )
+                                    .on_hover_text("Relative to the model size.");
+                                    ui.end_row();
+
+                                    ui.label("Occlusion samples");
+                                    ui.add_enabled(
+                                        enabled,
+                                        egui::Slider::new(
+                                            &mut attr.ambient_occlusion_samples,
+                                            4..=ambient_occlusion::MAX_SAMPLES,
+                                        ),
+                                    );
+                                    ui.end_row();
+                                })
+                        });
+
+                        ui.collapsing("Edge settings", |ui| {
+                            egui::Grid::new("edge_settings_grid")
+                                .striped(true)
+                                .spacing([40.0, 4.0])
+                                .show(ui, |ui| {
+                                    ui.label("Edge width");
+                                    ui.add(egui::Slider::new(
+                                        &mut attr.edge_width,
+                                        0.0..=edges::MAX_EDGE_WIDTH,
+                                    ))
+                                    .on_hover_text(
+                                        "Outlines silhouettes and creases, in pixels. 0 turns \
+                                         it off.",
+                                    );
+                                    ui.end_row();
+
+                                    let enabled = attr.edge_width > 0.0;
+                                    ui.label("Edge colour");
+                                    ui.add_enabled_ui(enabled, |ui| {
+                                        let mut color = [
+                                            attr.edge_color[0],
+                                            attr.edge_color[1],
+                                            attr.edge_color[2],
+                                        ];
+                                        ui.color_edit_button_rgb(&mut color);
+                                        attr.edge_color = na::Vector3::from(color);
+                                    });
+                                    ui.end_row();
+
+                                    ui.label("Crease angle");
+                                    ui.add_enabled(
+                                        enabled,
+                                        egui::Slider::new(&mut attr.edge_crease_angle, 10.0..=90.0)
+                                            .suffix("°"),
+                                    )
+                                    .on_hover_text(
+                                        "Smallest angle between neighbouring surfaces that is \
+                                         outlined.",
+                                    );
+                                    ui.end_row();
+
+                                    ui.label("Edge depth scaling");
+                                    ui.add_enabled(
+                                        enabled,
+                                        egui::Slider::new(&mut attr.edge_depth_scaling, 0.0..=1.0),
+                                    )
+                                    .on_hover_text(
+                                        "Makes distant outlines thinner and lighter, like \
+                                         distance shading.",
+                                    );
+                                    ui.end_row();
+                                })
+                        });
                     });
 
                     ui.horizontal(|ui| {
@@ -284,47 +820,124 @@ impl UI {
                     model.set_attributes(attr);
                 }
             });
+
+        self.file_browser.build_ui(
+            ctx,
+            &mut ui_actions.file_to_load,
+            &mut ui_actions.file_to_add,
+        );
+        let to_file = model
+            .as_ref()
+            .map_or_else(na::Isometry3::identity, |model| model.file_transform());
+        self.measurement_panel
+            .build_ui(ctx, &mut ui_actions.measurements, &to_file);
+        let screen = ctx.input().screen_rect();
+        let rects = ui_actions
+            .split_view
+            .rects((screen.width() as u32, screen.height() as u32));
+        if ui_actions.split_view.layout != Layout::Single {
+            let mut names = vec![self.preset_name().to_owned()];
+            names.extend(ui_actions.split_view.visible_panes().iter().map(
+                |pane| match &pane.file {
+                    Some(file) => {
+                        format!("{} ({})", pane.name, resources::file_stem(Path::new(file)))
+                    }
+                    None => pane.name.clone(),
+                },
+            ));
+            draw_pane_names(ctx, &rects, &names);
+        }
+        if let Some(model) = model {
+            let projection_matrix = model.get_attributes().projection_matrix;
+            draw_measurement_labels(
+                ctx,
+                &ui_actions.measurements,
+                &projection_matrix,
+                to_egui_rect(&rects[0]),
+            );
+            self.section_view
+                .build_ui(ctx, model, &ui_actions.clipping, &ui_actions.measurements);
+        }
+
+        // Record changes once the pointer is released, so a slider drag becomes a single step.
+        if let Some(model) = model.as_ref().filter(|_| !ctx.is_using_pointer()) {
+            let settings = Settings::capture(model.get_attributes(), ui_actions);
+            let description = match self.history.current() {
+                Some(current) if *current == settings => None,
+                Some(current) => Some(settings.describe_changes(current)),
+                None => Some("Initial settings".to_owned()),
+            };
+            if let Some(description) = description {
+                self.history.record(description, settings);
+            }
+        }
+
+        if self.preset_dialog.is_open() {
+            let current = model.as_ref().map(|model| PresetFile {
+                name: String::new(),
+                background: ui_actions.clear_color,
+                camera: ui_actions.camera,
+                attributes: model.get_attributes().clone(),
+            });
+            match self.preset_dialog.build_ui(ctx, current) {
+                Some(PresetDialogEvent::Saved) => self.user_presets = preset::load_user_presets(),
+                Some(PresetDialogEvent::Loaded(preset)) => {
+                    self.use_preset_file(*preset, ui_actions);
+                    if let Some(model) = model {
+                        self.apply_preset(model);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Undoes the last settings change.
+    pub fn undo(&mut self, model: &mut crate::Model, ui_actions: &mut UiActions) {
+        if let Some(settings) = self.history.undo() {
+            let mut attr = model.get_attributes().clone();
+            settings.restore(&mut attr, ui_actions);
+            model.set_attributes(attr);
+        }
+    }
+
+    /// Redoes the last undone settings change.
+    pub fn redo(&mut self, model: &mut crate::Model, ui_actions: &mut UiActions) {
+        if let Some(settings) = self.history.redo() {
+            let mut attr = model.get_attributes().clone();
+            settings.restore(&mut attr, ui_actions);
+            model.set_attributes(attr);
+        }
+    }
+
+    /// Switches to a preset file, requesting its background colour and camera view. Its
+    /// attributes are used from the next [UI::apply_preset] on.
+    fn use_preset_file(&mut self, preset: PresetFile, ui_actions: &mut UiActions) {
+        ui_actions.clear_color = preset.background;
+        ui_actions.camera_to_apply = Some(preset.camera);
+        self.user_preset = Some(preset);
+    }
+
+    /// Remembers the given file as recently opened.
+    pub fn add_recent_file(&mut self, file: &Path) {
+        self.file_browser.add_recent_file(file);
+    }
+
+    /// Gets the name of the preset used for the main model.
+    fn preset_name(&self) -> &str {
+        match &self.user_preset {
+            Some(user_preset) => &user_preset.name,
+            None => self.preset.description(),
+        }
     }
 
     /// Applies a preset to model renderer.
     pub fn apply_preset(&self, model: &mut crate::Model) -> Attributes {
         let mut preset = model.get_attributes().clone();
-        match self.preset {
-            Preset::Plain => {
-                preset.toon_factor = 0.0;
-                preset.vertex_color_mix = 1.0;
-                preset.distance_shading_channel = DistanceShadingChannel::None;
-
-                preset.replace_shadows_with_hatching = true;
-                preset.hatching_depth = 0.75;
-                preset.hatching_steps = 150;
-                preset.hatching_frequency = 4;
-                preset.hatching_intensity = 0.75;
-            }
-            Preset::Aerial => {
-                preset.toon_factor = 0.0;
-                preset.vertex_color_mix = 1.0;
-                preset.distance_shading_channel = DistanceShadingChannel::Saturation;
-                preset.distance_shading_power = 0.6;
-
-                preset.replace_shadows_with_hatching = true;
-                preset.hatching_depth = 0.75;
-                preset.hatching_steps = 150;
-                preset.hatching_frequency = 4;
-                preset.hatching_intensity = 0.75;
-            }
-            Preset::ChromaDepth => {
-                preset.toon_factor = 0.0;
-                preset.distance_shading_channel = DistanceShadingChannel::Hue;
-                preset.distance_shading_power = 0.6;
-
-                preset.replace_shadows_with_hatching = true;
-                preset.hatching_depth = 0.75;
-                preset.hatching_steps = 150;
-                preset.hatching_frequency = 4;
-                preset.hatching_intensity = 0.75;
-            }
-        };
+        match &self.user_preset {
+            Some(user_preset) => user_preset.apply(&mut preset),
+            None => self.preset.apply(&mut preset),
+        }
         model.set_attributes(preset.clone());
         preset
     }
@@ -351,3 +964,87 @@ impl UI {
         Ok(cursor)
     }
 }
+
+/// Builds the grid for moving and rotating a model in the scene, dragging positions by `speed`.
+/// Returns whether the placement was changed.
+fn placement_grid(
+    ui: &mut egui::Ui,
+    index: usize,
+    speed: f32,
+    isometry: &mut na::Isometry3<f32>,
+) -> bool {
+    let mut changed = false;
+    egui::Grid::new(("placement_grid", index))
+        .striped(true)
+        .spacing([40.0, 4.0])
+        .show(ui, |ui| {
+            ui.label("Position");
+            ui.horizontal(|ui| {
+                for axis in 0..3 {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut isometry.translation.vector[axis])
+                                .speed(speed),
+                        )
+                        .changed();
+                }
+            });
+            ui.end_row();
+
+            // The angles are only written back when changed, so they do not drift.
+            let (roll, pitch, yaw) = isometry.rotation.euler_angles();
+            let mut angles = [roll, pitch, yaw].map(f32::to_degrees);
+            let mut rotated = false;
+            for (name, angle) in ["Roll", "Pitch", "Yaw"].into_iter().zip(&mut angles) {
+                ui.label(name);
+                rotated |= ui
+                    .add(egui::Slider::new(angle, -180.0..=180.0).suffix("°"))
+                    .changed();
+                ui.end_row();
+            }
+            if rotated {
+                let [roll, pitch, yaw] = angles.map(f32::to_radians);
+                isometry.rotation = na::UnitQuaternion::from_euler_angles(roll, pitch, yaw);
+            }
+
+            if ui.button("Reset").clicked() {
+                *isometry = na::Isometry3::identity();
+                rotated = true;
+            }
+            changed |= rotated;
+            ui.end_row();
+        });
+    changed
+}
+
+/// Converts a pane of the window to an [egui] rectangle.
+fn to_egui_rect(rect: &PaneRect) -> egui::Rect {
+    egui::Rect::from_min_size(
+        egui::pos2(rect.x as f32, rect.y as f32),
+        egui::vec2(rect.w as f32, rect.h as f32),
+    )
+}
+
+/// Draws the name of what each pane shows in its bottom left corner, clear of the settings window
+/// at the top, behind all windows.
+fn draw_pane_names(ctx: &egui::Context, rects: &[PaneRect], names: &[String]) {
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("pane_names"),
+    ));
+    for (rect, name) in rects.iter().zip(names) {
+        let rect = to_egui_rect(rect);
+        painter.rect_stroke(
+            rect,
+            0.0,
+            egui::Stroke::new(1.0, egui::Color32::from_gray(20)),
+        );
+        painter.text(
+            rect.left_bottom() + egui::vec2(8.0, -8.0),
+            egui::Align2::LEFT_BOTTOM,
+            name,
+            egui::FontId::proportional(16.0),
+            egui::Color32::WHITE,
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Isosurface extraction with marching cubes. Rather than the usual hand-written case tables,
+//! the triangles of each of the 256 cube cases are traced from the cube faces once at startup.
+//! Faces with two diagonal corners inside always keep those corners separated, so neighbouring
+//! cells agree on their shared face and the surface has no holes.
+
+use super::Volume;
+use crate::mesh::Mesh;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Cube edges as pairs of corners, where bit `a` of a corner index is its offset along axis `a`.
+/// Edges `4 * a..4 * a + 4` run along axis `a`, starting at the lower corner.
+const EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/// Cube faces, with their corners in counter-clockwise order as seen from outside the cube.
+const FACES: [[usize; 4]; 6] = [
+    [0, 4, 6, 2],
+    [1, 3, 7, 5],
+    [0, 1, 5, 4],
+    [2, 6, 7, 3],
+    [0, 2, 3, 1],
+    [4, 5, 7, 6],
+];
+
+/// Extracts the surface where the volume crosses `iso_value`, treating samples at or above it as
+/// inside. Triangles face outwards, and normals are taken from the volume gradient.
+pub fn extract_isosurface(volume: &Volume, iso_value: f32) -> Mesh {
+    let table = case_table();
+    let [nx, ny, nz] = volume.dims;
+    let mut mesh = Mesh::default();
+    // Vertices are shared between neighbouring cells, keyed by the grid edge they lie on.
+    let mut edge_vertices: HashMap<usize, u32> = HashMap::new();
+
+    for z in 0..nz.saturating_sub(1) {
+        for y in 0..ny.saturating_sub(1) {
+            for x in 0..nx.saturating_sub(1) {
+                let corner = |c: usize| (x + (c & 1), y + ((c >> 1) & 1), z + ((c >> 2) & 1));
+                let mut values = [0.0; 8];
+                let mut case = 0;
+                for (c, value) in values.iter_mut().enumerate() {
+                    let (cx, cy, cz) = corner(c);
+                    *value = volume.value(cx, cy, cz);
+                    if *value >= iso_value {
+                        case |= 1 << c;
+                    }
+                }
+
+                for triangle in &table[case] {
+                    for &edge in triangle {
+                        let (a, b) = EDGES[edge];
+                        let (ax, ay, az) = corner(a);
+                        let key = ((az * ny + ay) * nx + ax) * 3 + edge / 4;
+                        let index = *edge_vertices.entry(key).or_insert_with(|| {
+                            let (bx, by, bz) = corner(b);
+                            let t = (iso_value - values[a]) / (values[b] - values[a]);
+                            let start = [ax, ay, az];
+                            let end = [bx, by, bz];
+                            for axis in 0..3 {
+                                let position = start[axis] as f32
+                                    + t * (end[axis] as f32 - start[axis] as f32);
+                                mesh.positions.push(position * volume.spacing[axis]);
+                            }
+                            let gradient = volume.gradient(ax, ay, az) * (1.0 - t)
+                                + volume.gradient(bx, by, bz) * t;
+                            let normal = -gradient.try_normalize(0.0).unwrap_or_default();
+                            mesh.normals.extend(normal.iter());
+                            (mesh.positions.len() / 3 - 1) as u32
+                        });
+                        mesh.indices.push(index);
+                    }
+                }
+            }
+        }
+    }
+    mesh
+}
+
+/// Gets the triangles of every cube case, as edge indices.
+fn case_table() -> &'static [Vec<[usize; 3]>] {
+    static TABLE: OnceLock<Vec<Vec<[usize; 3]>>> = OnceLock::new();
+    TABLE.get_or_init(|| (0..256).map(triangulate_case).collect())
+}
+
+/// Triangulates a single cube case, where bit `c` of `case` is set if corner `c` is inside.
+///
+/// Walking around each face, the surface leaves through the edge where the walk goes from an
+/// inside to an outside corner, and is linked to the edge where that run of inside corners was
+/// entered. Every crossed edge is left on one face and entered on the other, so following these
+/// links gives closed polygons, which are triangulated as fans.
+fn triangulate_case(case: usize) -> Vec<[usize; 3]> {
+    let inside = |corner: usize| case & (1 << corner) != 0;
+    let edge_between = |a: usize, b: usize| {
+        EDGES
+            .iter()
+            .position(|&(c, d)| (c, d) == (a.min(b), a.max(b)))
+            .expect("Corners do not share an edge.")
+    };
+
+    let mut next_edge = [None; 12];
+    for face in FACES {
+        for i in 0..4 {
+            let (a, b) = (face[i], face[(i + 1) % 4]);
+            if inside(a) && !inside(b) {
+                let mut j = i;
+                while inside(face[(j + 3) % 4]) {
+                    j = (j + 3) % 4;
+                }
+                next_edge[edge_between(a, b)] = Some(edge_between(face[(j + 3) % 4], face[j]));
+            }
+        }
+    }
+
+    let mut triangles = Vec::new();
+    let mut visited = [false; 12];
+    for start in 0..12 {
+        if visited[start] || next_edge[start].is_none() {
+            continue;
+        }
+        let mut polygon = Vec::new();
+        let mut edge = start;
+        while !visited[edge] {
+            visited[edge] = true;
+            polygon.push(edge);
+            edge = next_edge[edge].expect("Isosurface polygon is not closed.");
+        }
+        // The traced polygons wind clockwise seen from outside, so the fans are reversed.
+        for i in 1..polygon.len() - 1 {
+            triangles.push([polygon[0], polygon[i + 1], polygon[i]]);
+        }
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra as na;
+
+    /// A volume sampling the distance from its centre subtracted from `radius`, which is
+    /// positive inside a sphere of that radius.
+    fn sphere(size: usize, radius: f32, spacing: f32) -> Volume {
+        let center = (size - 1) as f32 / 2.0 * spacing;
+        let mut data = Vec::with_capacity(size * size * size);
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let p = na::Vector3::new(x as f32, y as f32, z as f32) * spacing;
+                    data.push(radius - (p - na::Vector3::from_element(center)).norm());
+                }
+            }
+        }
+        Volume {
+            dims: [size; 3],
+            spacing: [spacing; 3],
+            data,
+        }
+    }
+
+    #[test]
+    fn sphere_faces_outwards() {
+        let (size, spacing) = (16, 0.5);
+        let mesh = extract_isosurface(&sphere(size, 3.0, spacing), 0.0);
+        assert!(!mesh.indices.is_empty());
+        let center = na::Vector3::from_element((size - 1) as f32 / 2.0 * spacing);
+        let position = |i: u32| {
+            let i = i as usize * 3;
+            na::Vector3::new(
+                mesh.positions[i],
+                mesh.positions[i + 1],
+                mesh.positions[i + 2],
+            )
+        };
+
+        for i in 0..mesh.vertex_count() as u32 {
+            assert!(((position(i) - center).norm() - 3.0).abs() < spacing);
+        }
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| position(triangle[corner]));
+            let normal = (b - a).cross(&(c - a));
+            assert!(normal.dot(&(a - center)) > 0.0);
+        }
+        for (i, normal) in mesh.normals.chunks_exact(3).enumerate() {
+            let outwards = (position(i as u32) - center).normalize();
+            assert!(na::Vector3::new(normal[0], normal[1], normal[2]).dot(&outwards) > 0.9);
+        }
+    }
+
+    #[test]
+    fn sphere_is_closed() {
+        let mesh = extract_isosurface(&sphere(12, 3.0, 1.0), 0.0);
+        let mut edges: HashMap<(u32, u32), i32> = HashMap::new();
+        for triangle in mesh.indices.chunks_exact(3) {
+            for i in 0..3 {
+                let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += if a < b { 1 } else { -1 };
+            }
+        }
+        // Every edge of a closed, consistently wound surface is used once in each direction.
+        assert!(edges.values().all(|&count| count == 0));
+    }
+
+    #[test]
+    fn every_case_is_closed() {
+        for case in 0..256 {
+            let triangles = triangulate_case(case);
+            let mut used = [0; 12];
+            for edge in triangles.iter().flatten() {
+                used[*edge] += 1;
+            }
+            for (edge, &(a, b)) in EDGES.iter().enumerate() {
+                let crossed = (case >> a & 1) != (case >> b & 1);
+                assert_eq!(crossed, used[edge] > 0, "case {}, edge {}", case, edge);
+            }
+        }
+    }
+
+    #[test]
+    fn iso_value_outside_range() {
+        let mesh = extract_isosurface(&sphere(8, 2.0, 1.0), 10.0);
+        assert!(mesh.indices.is_empty());
+    }
+}
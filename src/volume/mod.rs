@@ -0,0 +1,336 @@
+//! Contains [Volume], a scalar volume loaded from a raw or NIfTI file, and
+//! [IsosurfaceExtractor], which turns a volume into surface meshes on a background thread. A
+//! volume is either shown as a single isosurface, or as a label map with one mesh per label.
+
+mod marching_cubes;
+
+pub use marching_cubes::extract_isosurface;
+
+use crate::mesh::Mesh;
+use nalgebra as na;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+/// Volumes with more distinct values than this are not considered label maps.
+const MAX_LABELS: usize = 64;
+
+/// Colours given to labels missing from the lookup table.
+const LABEL_PALETTE: [[f32; 3]; 10] = [
+    [0.90, 0.10, 0.10],
+    [0.20, 0.40, 0.90],
+    [0.95, 0.75, 0.10],
+    [0.20, 0.70, 0.30],
+    [0.60, 0.30, 0.80],
+    [0.95, 0.50, 0.15],
+    [0.10, 0.75, 0.80],
+    [0.90, 0.40, 0.70],
+    [0.55, 0.35, 0.20],
+    [0.60, 0.60, 0.60],
+];
+
+/// A regular grid of scalar samples, stored with x varying fastest.
+#[derive(Debug, Clone, Default)]
+pub struct Volume {
+    /// Number of samples along each axis.
+    pub dims: [usize; 3],
+    /// Distance between samples along each axis, usually in millimetres.
+    pub spacing: [f32; 3],
+    /// Sample values, `dims[0] * dims[1] * dims[2]` in total.
+    pub data: Vec<f32>,
+}
+
+impl Volume {
+    /// Gets the sample at the given grid coordinates.
+    pub fn value(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.data[(z * self.dims[1] + y) * self.dims[0] + x]
+    }
+
+    /// Gets the smallest and largest sample values.
+    pub fn range(&self) -> (f32, f32) {
+        self.data
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f32::MAX, f32::MIN), |(min, max), &v| {
+                (min.min(v), max.max(v))
+            })
+    }
+
+    /// Gets the distinct non-zero values of the volume, if it looks like a label map. This
+    /// requires every value to be an integer, with at most [MAX_LABELS] distinct values.
+    pub fn label_values(&self) -> Option<Vec<i32>> {
+        let mut values = HashSet::new();
+        for &value in &self.data {
+            if value.fract() != 0.0 || !value.is_finite() {
+                return None;
+            }
+            if value != 0.0 && values.insert(value as i32) && values.len() > MAX_LABELS {
+                return None;
+            }
+        }
+        let mut values: Vec<i32> = values.into_iter().collect();
+        values.sort_unstable();
+        Some(values)
+    }
+
+    /// Gets the gradient at the given grid coordinates in world units, using central
+    /// differences inside the volume and one-sided differences at its borders.
+    fn gradient(&self, x: usize, y: usize, z: usize) -> na::Vector3<f32> {
+        let mut gradient = na::Vector3::zeros();
+        let position = [x, y, z];
+        for axis in 0..3 {
+            let mut before = position;
+            let mut after = position;
+            before[axis] = position[axis].saturating_sub(1);
+            after[axis] = (position[axis] + 1).min(self.dims[axis] - 1);
+            let steps = (after[axis] - before[axis]) as f32;
+            if steps > 0.0 {
+                let difference = self.value(after[0], after[1], after[2])
+                    - self.value(before[0], before[1], before[2]);
+                gradient[axis] = difference / (steps * self.spacing[axis]);
+            }
+        }
+        gradient
+    }
+}
+
+/// Name and colour of a value in a label map volume.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub value: i32,
+    pub name: String,
+    pub color: [f32; 3],
+}
+
+impl Label {
+    /// Finds the given label value in a lookup table, or makes up a name and colour for it.
+    pub fn from_table(value: i32, table: &[Label]) -> Self {
+        table
+            .iter()
+            .find(|label| label.value == value)
+            .cloned()
+            .unwrap_or_else(|| Label {
+                value,
+                name: format!("Label {}", value),
+                color: LABEL_PALETTE[value.unsigned_abs() as usize % LABEL_PALETTE.len()],
+            })
+    }
+}
+
+/// Extracts the surface of every label of a label map into its own mesh, coloured and named
+/// after the label. Labels not present in the volume are skipped.
+pub fn extract_labels(volume: &Volume, labels: &[Label]) -> Vec<Mesh> {
+    // Find the bounding box of each label, so only that part of the volume is searched.
+    let mut bounds: HashMap<i32, ([usize; 3], [usize; 3])> = HashMap::new();
+    for z in 0..volume.dims[2] {
+        for y in 0..volume.dims[1] {
+            for x in 0..volume.dims[0] {
+                let value = volume.value(x, y, z);
+                if value != 0.0 {
+                    let (min, max) = bounds.entry(value as i32).or_insert(([x, y, z], [x, y, z]));
+                    for (axis, position) in [x, y, z].into_iter().enumerate() {
+                        min[axis] = min[axis].min(position);
+                        max[axis] = max[axis].max(position);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut meshes = Vec::new();
+    for label in labels {
+        let (min, max) = match bounds.get(&label.value) {
+            Some(bounds) => *bounds,
+            None => continue,
+        };
+        // The mask is zero-padded by one sample on every side, also past the volume borders, so
+        // surfaces touching the bounds are closed.
+        let dims = [0, 1, 2].map(|axis| max[axis] - min[axis] + 3);
+        let mut mask = Volume {
+            dims,
+            spacing: volume.spacing,
+            data: Vec::with_capacity(dims[0] * dims[1] * dims[2]),
+        };
+        for z in min[2]..min[2] + dims[2] {
+            for y in min[1]..min[1] + dims[1] {
+                for x in min[0]..min[0] + dims[0] {
+                    // Mask sample (x, y, z) is volume sample (x - 1, y - 1, z - 1).
+                    let inside = [x, y, z]
+                        .into_iter()
+                        .zip(volume.dims)
+                        .all(|(position, size)| (1..=size).contains(&position))
+                        && volume.value(x - 1, y - 1, z - 1) == label.value as f32;
+                    mask.data.push(inside as u8 as f32);
+                }
+            }
+        }
+
+        let mut mesh = extract_isosurface(&mask, 0.5);
+        for position in mesh.positions.chunks_exact_mut(3) {
+            for axis in 0..3 {
+                position[axis] += (min[axis] as f32 - 1.0) * volume.spacing[axis];
+            }
+        }
+        mesh.name = label.name.clone();
+        mesh.vertex_color = label.color.repeat(mesh.vertex_count());
+        mesh.material_color = Some(label.color);
+        meshes.push(mesh);
+    }
+    meshes
+}
+
+/// The kinds of surfaces an [IsosurfaceExtractor] can extract.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Extraction {
+    /// A single isosurface at the given iso-value.
+    Isosurface(f32),
+    /// One surface per label of a label map.
+    Labels,
+}
+
+/// Extracts surfaces from a [Volume] on a background thread, so the user interface stays
+/// responsive while the threshold is changed. Only the most recent request is extracted when
+/// several arrive while the thread is busy.
+pub struct IsosurfaceExtractor {
+    requests: Sender<Extraction>,
+    results: Receiver<(Extraction, Vec<Mesh>)>,
+    requested: Option<Extraction>,
+    received: Option<Extraction>,
+}
+
+impl IsosurfaceExtractor {
+    /// Starts the extraction thread for the given volume. Isosurface meshes are given `name`,
+    /// while label meshes are named after their entry in `labels`.
+    pub fn new(volume: Volume, name: String, labels: Vec<Label>) -> Self {
+        let (requests, request_receiver) = channel::<Extraction>();
+        let (result_sender, results) = channel();
+        thread::spawn(move || {
+            while let Ok(mut extraction) = request_receiver.recv() {
+                // Skip to the latest request, as the earlier ones are already outdated.
+                while let Ok(newer) = request_receiver.try_recv() {
+                    extraction = newer;
+                }
+                let meshes = match extraction {
+                    Extraction::Isosurface(iso_value) => {
+                        let mut mesh = extract_isosurface(&volume, iso_value);
+                        mesh.name = name.clone();
+                        vec![mesh]
+                    }
+                    Extraction::Labels => extract_labels(&volume, &labels),
+                };
+                if result_sender.send((extraction, meshes)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests,
+            results,
+            requested: None,
+            received: None,
+        }
+    }
+
+    /// Requests the given surfaces, unless they were the ones last requested.
+    pub fn request(&mut self, extraction: Extraction) {
+        if self.requested != Some(extraction) && self.requests.send(extraction).is_ok() {
+            self.requested = Some(extraction);
+        }
+    }
+
+    /// Gets the most recently extracted meshes, if new ones have finished since the last call.
+    pub fn poll(&mut self) -> Option<Vec<Mesh>> {
+        let (extraction, meshes) = self.results.try_iter().last()?;
+        self.received = Some(extraction);
+        Some(meshes)
+    }
+
+    /// Checks if the latest requested surfaces are still being extracted.
+    pub fn is_extracting(&self) -> bool {
+        self.requested != self.received
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 6x4x4 label map with two touching boxes, labelled 1 and 2.
+    fn two_boxes() -> Volume {
+        let mut volume = Volume {
+            dims: [6, 4, 4],
+            spacing: [1.0, 1.0, 2.0],
+            data: vec![0.0; 6 * 4 * 4],
+        };
+        for z in 1..3 {
+            for y in 1..3 {
+                for x in 1..5 {
+                    volume.data[(z * 4 + y) * 6 + x] = if x < 3 { 1.0 } else { 2.0 };
+                }
+            }
+        }
+        volume
+    }
+
+    #[test]
+    fn label_values() {
+        assert_eq!(two_boxes().label_values(), Some(vec![1, 2]));
+        let mut volume = two_boxes();
+        volume.data[0] = 0.5;
+        assert_eq!(volume.label_values(), None);
+    }
+
+    #[test]
+    fn labels_extracted_separately() {
+        let table = [Label {
+            value: 2,
+            name: "Aneurysm".to_owned(),
+            color: [1.0, 0.0, 0.0],
+        }];
+        let labels: Vec<Label> = [1, 2, 3]
+            .into_iter()
+            .map(|value| Label::from_table(value, &table))
+            .collect();
+        let meshes = extract_labels(&two_boxes(), &labels);
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].name, "Label 1");
+        assert_eq!(meshes[1].name, "Aneurysm");
+        assert_eq!(meshes[1].material_color, Some([1.0, 0.0, 0.0]));
+
+        // The surface of each box lies halfway between its samples and the neighbouring ones.
+        let x_range = |mesh: &Mesh| {
+            let x = mesh.positions.iter().step_by(3);
+            let min = x.clone().fold(f32::MAX, |a, &b| a.min(b));
+            (min, x.fold(f32::MIN, |a, &b| a.max(b)))
+        };
+        assert_eq!(x_range(&meshes[0]), (0.5, 2.5));
+        assert_eq!(x_range(&meshes[1]), (2.5, 4.5));
+        let z_max = meshes[1].positions.iter().skip(2).step_by(3);
+        assert_eq!(z_max.fold(f32::MIN, |a, &b| a.max(b)), 5.0);
+    }
+
+    #[test]
+    fn labels_on_the_border_are_closed() {
+        // The box of label 1 fills the whole first x slice.
+        let mut volume = two_boxes();
+        for z in 0..4 {
+            for y in 0..4 {
+                volume.data[(z * 4 + y) * 6] = 1.0;
+            }
+        }
+        let meshes = extract_labels(&volume, &[Label::from_table(1, &[])]);
+        let mesh = &meshes[0];
+        let x_min = mesh.positions.iter().step_by(3);
+        assert_eq!(x_min.fold(f32::MAX, |a, &b| a.min(b)), -0.5);
+
+        let mut edges: HashMap<(u32, u32), i32> = HashMap::new();
+        for triangle in mesh.indices.chunks_exact(3) {
+            for i in 0..3 {
+                let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += if a < b { 1 } else { -1 };
+            }
+        }
+        assert!(edges.values().all(|&count| count == 0));
+    }
+}